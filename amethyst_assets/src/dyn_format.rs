@@ -31,7 +31,7 @@ use std::{collections::BTreeMap, marker::PhantomData};
 ///     }
 
 ///     fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
-///         Ok(AudioData(bytes))
+///         Ok(AudioData::new(bytes))
 ///     }
 /// }
 
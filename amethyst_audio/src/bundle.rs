@@ -8,18 +8,34 @@ use amethyst_core::{
 };
 use amethyst_error::Error;
 
-use crate::{output::Output, source::*, systems::AudioSystemDesc};
+use crate::{
+    event::AudioEventBank,
+    music::Playlist,
+    output::Output,
+    source::*,
+    systems::{AudioSystemDesc, MixerSystem, OutputSystem},
+};
 
 /// Audio bundle
 ///
-/// This will only add the audio system and the asset processor for `Source`.
+/// This will only add the audio system, the mixer system, the output system and the asset processors
+/// for `Source`, `Playlist` and `AudioEventBank`.
 ///
-/// `DjSystem` must be added separately if you want to use our background music system.
+/// `DjSystem` or `MusicSystem` must be added separately if you want to use our background music
+/// systems, and `AudioEventSystem` to play audio events.
 ///
-/// The generic N type should be the same as the one in `Transform`.
+/// The default bundle plays on the default output device, use `AudioBundle::with_output` on
+/// machines without audio hardware.
 #[derive(Default, Debug)]
 pub struct AudioBundle(Output);
 
+impl AudioBundle {
+    /// Creates a bundle playing on `output`, like `Output::null` or `Output::capture`.
+    pub fn with_output(output: Output) -> Self {
+        AudioBundle(output)
+    }
+}
+
 impl<'a, 'b> SystemBundle<'a, 'b> for AudioBundle {
     fn build(
         self,
@@ -31,7 +47,15 @@ impl<'a, 'b> SystemBundle<'a, 'b> for AudioBundle {
             "audio_system",
             &[],
         );
+        builder.add(MixerSystem, "mixer_system", &["audio_system"]);
+        builder.add(OutputSystem, "output_system", &["mixer_system"]);
         builder.add(Processor::<Source>::new(), "source_processor", &[]);
+        builder.add(Processor::<Playlist>::new(), "playlist_processor", &[]);
+        builder.add(
+            Processor::<AudioEventBank>::new(),
+            "audio_event_bank_processor",
+            &[],
+        );
         Ok(())
     }
 }
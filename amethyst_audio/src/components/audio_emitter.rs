@@ -1,23 +1,37 @@
-use std::{
-    io::Cursor,
-    sync::{atomic::AtomicBool, Arc},
-};
+use std::sync::Arc;
 
-use rodio::{Decoder, SpatialSink};
 use smallvec::SmallVec;
 
 use amethyst_core::ecs::{prelude::Component, storage::BTreeStorage};
 
-use crate::{source::Source, DecoderError};
+use crate::{
+    effects::{Effect, EffectChain},
+    playback::PlaybackHandle,
+    source::{Source, SourceSamples},
+    spatial::{Attenuation, Doppler},
+    voice::Voice,
+    DecoderError,
+};
 
 /// An audio source, add this component to anything that emits sound.
-/// TODO: This should get a proper Debug impl parsing the sinks and sound queue
+/// TODO: This should get a proper Debug impl parsing the voices
 #[allow(missing_debug_implementations)]
 #[derive(Default)]
 pub struct AudioEmitter {
-    pub(crate) sinks: SmallVec<[(SpatialSink, Arc<AtomicBool>); 4]>,
-    pub(crate) sound_queue: SmallVec<[Decoder<Cursor<Source>>; 4]>,
+    /// The sounds of the emitter, the `AudioSystem` plays them on sinks within the voice limit.
+    pub(crate) voices: SmallVec<[Voice; 4]>,
+    /// Audio events triggered on the emitter, played by the `AudioEventSystem`.
+    pub(crate) events: SmallVec<[String; 4]>,
     pub(crate) picker: Option<Box<dyn FnMut(&mut AudioEmitter) -> bool + Send + Sync>>,
+    pub(crate) bus: Option<String>,
+    pub(crate) attenuation: Option<Attenuation>,
+    pub(crate) doppler: Option<Doppler>,
+    pub(crate) occludable: bool,
+    /// Position at the previous frame, to compute the velocity of the emitter.
+    pub(crate) last_position: Option<[f32; 3]>,
+    pub(crate) effects: Vec<Effect>,
+    /// The effects of the emitter, reverb zone and bus, applied to the sounds of the emitter.
+    pub(crate) effect_chain: Arc<EffectChain>,
 }
 
 impl AudioEmitter {
@@ -29,10 +43,40 @@ impl AudioEmitter {
     }
 
     /// Plays an audio source from this emitter.
-    pub fn play(&mut self, source: &Source) -> Result<(), DecoderError> {
-        self.sound_queue
-            .push(Decoder::new(Cursor::new(source.clone())).map_err(|_| DecoderError)?);
-        Ok(())
+    ///
+    /// The returned handle controls the sound while it plays, dropping it doesn't stop the sound.
+    pub fn play(&mut self, source: &Source) -> Result<PlaybackHandle, DecoderError> {
+        self.play_with_priority(source, 0)
+    }
+
+    /// Plays an audio source from this emitter with a priority.
+    ///
+    /// When more sounds play than the `Voices` limit, the sounds with the lowest priority are
+    /// virtualized first, see `Voices`.
+    pub fn play_with_priority(
+        &mut self,
+        source: &Source,
+        priority: i32,
+    ) -> Result<PlaybackHandle, DecoderError> {
+        let handle = PlaybackHandle::new();
+        self.play_samples(source.samples()?, handle.clone(), priority);
+        Ok(handle)
+    }
+
+    pub(crate) fn play_samples(
+        &mut self,
+        samples: SourceSamples,
+        handle: PlaybackHandle,
+        priority: i32,
+    ) {
+        self.voices.push(Voice::new(samples, handle, priority));
+    }
+
+    /// Plays the audio event with this name from this emitter, see `AudioEvents`.
+    ///
+    /// The event is played by the `AudioEventSystem` once its bank and sounds are loaded.
+    pub fn trigger(&mut self, event: &str) {
+        self.events.push(event.to_string());
     }
 
     /// An emitter's picker will be called by the AudioSystem whenever the emitter runs out of
@@ -50,6 +94,62 @@ impl AudioEmitter {
     pub fn clear_picker(&mut self) {
         self.picker = None;
     }
+
+    /// Returns the mixer bus of the emitter, `None` for the master bus.
+    pub fn bus(&self) -> Option<&str> {
+        self.bus.as_ref().map(String::as_str)
+    }
+
+    /// Routes the sounds of this emitter to a mixer bus, `None` for the master bus.
+    pub fn set_bus(&mut self, bus: Option<String>) {
+        self.bus = bus;
+    }
+
+    /// Returns the distance attenuation of the emitter.
+    pub fn attenuation(&self) -> Option<&Attenuation> {
+        self.attenuation.as_ref()
+    }
+
+    /// Sets how the volume of this emitter decreases with the distance to the listener.
+    ///
+    /// With `None`, the volume is inversely proportional to the square of the distance, farther
+    /// than one unit.
+    pub fn set_attenuation(&mut self, attenuation: Option<Attenuation>) {
+        self.attenuation = attenuation;
+    }
+
+    /// Returns the Doppler effect of the emitter.
+    pub fn doppler(&self) -> Option<&Doppler> {
+        self.doppler.as_ref()
+    }
+
+    /// Shifts the pitch of the sounds of this emitter as it moves relative to the listener, or
+    /// disables it with `None`.
+    pub fn set_doppler(&mut self, doppler: Option<Doppler>) {
+        self.doppler = doppler;
+    }
+
+    /// Returns true if obstacles can attenuate this emitter, see `AudioOcclusion`.
+    pub fn is_occludable(&self) -> bool {
+        self.occludable
+    }
+
+    /// Returns the effects applied to the sounds of this emitter.
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    /// Sets the effects applied to the sounds of this emitter, before the effects of the active
+    /// `ReverbZone` and of its mixer bus.
+    pub fn set_effects(&mut self, effects: Vec<Effect>) {
+        self.effects = effects;
+    }
+
+    /// Makes the `AudioOcclusion` resource attenuate this emitter when obstacles hide it from the
+    /// listener.
+    pub fn set_occludable(&mut self, occludable: bool) {
+        self.occludable = occludable;
+    }
 }
 
 impl Component for AudioEmitter {
@@ -76,7 +176,7 @@ mod tests {
         f.read_to_end(&mut buffer).unwrap();
 
         // Create a Source and AudioEmitter from those bytes
-        let src = Source { bytes: buffer };
+        let src = Source::new(buffer);
         let mut emitter = AudioEmitter::default();
 
         // Call play
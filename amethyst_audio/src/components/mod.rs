@@ -1,6 +1,10 @@
 //! `amethyst` audio ecs components
 
-pub use self::{audio_emitter::AudioEmitter, audio_listener::AudioListener};
+pub use self::{
+    audio_emitter::AudioEmitter,
+    audio_listener::AudioListener,
+    reverb_zone::{ReverbZone, ZoneShape},
+};
 
 use amethyst_assets::PrefabData;
 use amethyst_core::{
@@ -15,6 +19,7 @@ use crate::output::Output;
 
 mod audio_emitter;
 mod audio_listener;
+mod reverb_zone;
 
 /// `PrefabData` for loading audio components
 ///
@@ -0,0 +1,77 @@
+use amethyst_core::ecs::{prelude::Component, storage::HashMapStorage};
+use serde::{Deserialize, Serialize};
+
+use crate::effects::Reverb;
+
+/// The volume covered by a `ReverbZone`, centered on the position of its entity.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ZoneShape {
+    /// A sphere of this radius.
+    Sphere(f32),
+    /// An axis aligned box extending by these distances on each side of the center.
+    Box([f32; 3]),
+}
+
+impl ZoneShape {
+    /// Returns true if `point` is inside the shape centered on `center`.
+    pub fn contains(&self, center: [f32; 3], point: [f32; 3]) -> bool {
+        let offset = [
+            point[0] - center[0],
+            point[1] - center[1],
+            point[2] - center[2],
+        ];
+        match self {
+            ZoneShape::Sphere(radius) => {
+                offset.iter().map(|d| d * d).sum::<f32>() <= radius * radius
+            }
+            ZoneShape::Box(half_extents) => offset
+                .iter()
+                .zip(half_extents)
+                .all(|(d, extent)| d.abs() <= *extent),
+        }
+    }
+}
+
+/// Applies a reverb to every sound of `AudioEmitter`s while the `AudioListener` is inside this
+/// zone, like in a cave or a church.
+///
+/// The entity needs a `Transform`. When zones overlap, the one with the highest `priority` is used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReverbZone {
+    /// The volume covered by the zone.
+    pub shape: ZoneShape,
+    /// The reverb applied inside the zone.
+    pub reverb: Reverb,
+    /// Chooses between overlapping zones, the highest wins.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl ReverbZone {
+    /// Creates a zone applying `reverb` inside `shape`.
+    pub fn new(shape: ZoneShape, reverb: Reverb) -> Self {
+        ReverbZone {
+            shape,
+            reverb,
+            priority: 0,
+        }
+    }
+}
+
+impl Component for ReverbZone {
+    type Storage = HashMapStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::ZoneShape;
+
+    #[test]
+    fn test_contains() {
+        let center = [1.0, 0.0, 0.0];
+        assert!(ZoneShape::Sphere(2.0).contains(center, [2.0, 1.0, 1.0]));
+        assert!(!ZoneShape::Sphere(2.0).contains(center, [3.0, 1.0, 1.0]));
+        assert!(ZoneShape::Box([1.0, 2.0, 3.0]).contains(center, [2.0, -2.0, 3.0]));
+        assert!(!ZoneShape::Box([1.0, 2.0, 3.0]).contains(center, [2.5, 0.0, 0.0]));
+    }
+}
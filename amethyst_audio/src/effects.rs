@@ -0,0 +1,743 @@
+//! Audio effects applied to the sounds of emitters and mixer buses.
+
+use std::{
+    mem::discriminant,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use cpal::Sample as CpalSample;
+use rodio::{Sample, Source};
+use serde::{Deserialize, Serialize};
+
+/// Number of samples played between two reads of the effects of a sound.
+const POLL_PERIOD: u32 = 256;
+/// Number of frames in each frame of the tail played once the sound ended.
+const TAIL_FRAME: usize = 256;
+/// Level under which the effects are silent, and their tail ends (-80 dB).
+const SILENCE: f32 = 0.0001;
+
+/// Delays of the comb filters of the reverb, in samples at 44100 Hz.
+const COMB_DELAYS: [usize; 4] = [1116, 1188, 1277, 1356];
+/// Delays of the all-pass filters of the reverb, in samples at 44100 Hz.
+const ALL_PASS_DELAYS: [usize; 2] = [556, 441];
+/// Delay added to the filters of each channel after the first, so channels reverberate
+/// differently.
+const STEREO_SPREAD: usize = 23;
+
+/// Parameters of a reverb, simulating the reflections of a sound in a room.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Reverb {
+    /// Size of the room from 0.0 to 1.0, larger rooms reverberate longer.
+    pub room_size: f32,
+    /// How much the walls absorb high frequencies, from 0.0 to 1.0.
+    pub damping: f32,
+    /// Volume of the reverberation from 0.0 to 1.0, the volume of the original sound decreases as
+    /// it increases.
+    pub mix: f32,
+}
+
+impl Default for Reverb {
+    fn default() -> Self {
+        Reverb {
+            room_size: 0.5,
+            damping: 0.5,
+            mix: 0.3,
+        }
+    }
+}
+
+/// An effect processing the samples of sounds, see `AudioEmitter::set_effects` and
+/// `Mixer::set_effects`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    /// Removes the frequencies above `cutoff` Hz, muffling the sound.
+    LowPass {
+        /// The cutoff frequency, in Hz.
+        cutoff: f32,
+    },
+    /// Removes the frequencies below `cutoff` Hz, thinning the sound.
+    HighPass {
+        /// The cutoff frequency, in Hz.
+        cutoff: f32,
+    },
+    /// Adds the reflections of the sound in a room.
+    Reverb(Reverb),
+    /// Repeats the sound.
+    Echo {
+        /// Time between the repetitions, in seconds.
+        delay: f32,
+        /// Volume of each repetition relative to the previous one, from 0.0 to 1.0.
+        feedback: f32,
+        /// Volume of the repetitions, from 0.0 to 1.0.
+        mix: f32,
+    },
+    /// Lowers the volume of loud parts of the sound.
+    Compressor {
+        /// Amplitude from which the volume is lowered, from 0.0 to 1.0.
+        threshold: f32,
+        /// How much the amplitude above the threshold is divided, 4.0 makes it four times lower.
+        ratio: f32,
+        /// Time to react to loud parts, in seconds.
+        attack: f32,
+        /// Time to restore the volume after loud parts, in seconds.
+        release: f32,
+    },
+}
+
+/// Effects applied in order to sounds, updated while they play.
+#[derive(Debug, Default)]
+pub(crate) struct EffectChain {
+    effects: Mutex<Vec<Effect>>,
+    /// Incremented on each change of the effects.
+    generation: AtomicUsize,
+}
+
+impl EffectChain {
+    pub(crate) fn new(effects: Vec<Effect>) -> Self {
+        EffectChain {
+            effects: Mutex::new(effects),
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    /// Replaces the effects, the sounds apply them within a few milliseconds.
+    pub(crate) fn set(&self, effects: Vec<Effect>) {
+        let mut current = self.effects.lock().expect("Mutex poisoned");
+        if *current != effects {
+            *current = effects;
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+struct DelayLine {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl DelayLine {
+    fn new(len: usize) -> Self {
+        DelayLine {
+            buffer: vec![0.0; len.max(1)],
+            position: 0,
+        }
+    }
+
+    fn read(&self) -> f32 {
+        self.buffer[self.position]
+    }
+
+    fn write(&mut self, value: f32) {
+        self.buffer[self.position] = value;
+        self.position = (self.position + 1) % self.buffer.len();
+    }
+
+    fn is_silent(&self) -> bool {
+        self.buffer.iter().all(|value| value.abs() < SILENCE)
+    }
+
+    /// Changes the length of the line, stretching its delayed values over the new length.
+    fn resize(&mut self, len: usize) {
+        let len = len.max(1);
+        let old_len = self.buffer.len();
+        let values = self.buffer[self.position..]
+            .iter()
+            .chain(&self.buffer[..self.position])
+            .cloned()
+            .collect::<Vec<_>>();
+        self.buffer = (0..len).map(|i| values[i * old_len / len]).collect();
+        self.position = 0;
+    }
+}
+
+/// A reverb channel, after Freeverb.
+struct ReverbChannel {
+    combs: Vec<(DelayLine, f32)>,
+    all_passes: Vec<DelayLine>,
+}
+
+impl ReverbChannel {
+    fn new(channel: usize, sample_rate: u32) -> Self {
+        let scale = |delay: usize| (delay + channel * STEREO_SPREAD) * sample_rate as usize / 44100;
+        ReverbChannel {
+            combs: COMB_DELAYS
+                .iter()
+                .map(|delay| (DelayLine::new(scale(*delay)), 0.0))
+                .collect(),
+            all_passes: ALL_PASS_DELAYS
+                .iter()
+                .map(|delay| DelayLine::new(scale(*delay)))
+                .collect(),
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let input = input * 0.03;
+        let mut output = 0.0;
+        for (line, filtered) in &mut self.combs {
+            let delayed = line.read();
+            *filtered = delayed * (1.0 - damping) + *filtered * damping;
+            line.write(input + *filtered * feedback);
+            output += delayed;
+        }
+        for line in &mut self.all_passes {
+            let delayed = line.read();
+            line.write(output + delayed * 0.5);
+            output = delayed - output;
+        }
+        output * 3.0
+    }
+
+    fn is_silent(&self) -> bool {
+        self.combs
+            .iter()
+            .all(|(line, filtered)| filtered.abs() < SILENCE && line.is_silent())
+            && self.all_passes.iter().all(DelayLine::is_silent)
+    }
+}
+
+/// The state of an effect applied to a sound.
+enum EffectState {
+    Filter {
+        high_pass: bool,
+        coefficient: f32,
+        low: Vec<f32>,
+    },
+    Reverb {
+        channels: Vec<ReverbChannel>,
+        feedback: f32,
+        damping: f32,
+        mix: f32,
+    },
+    Echo {
+        lines: Vec<DelayLine>,
+        feedback: f32,
+        mix: f32,
+    },
+    Compressor {
+        envelope: f32,
+        threshold: f32,
+        ratio: f32,
+        attack: f32,
+        release: f32,
+    },
+}
+
+/// Returns the coefficient of a one pole filter with a time constant of `seconds`.
+fn time_coefficient(seconds: f32, sample_rate: u32) -> f32 {
+    if seconds > 0.0 {
+        (-1.0 / (seconds * sample_rate as f32)).exp()
+    } else {
+        0.0
+    }
+}
+
+impl EffectState {
+    fn new(effect: &Effect, channels: u16, sample_rate: u32) -> Self {
+        let channels = usize::from(channels.max(1));
+        let mut state = match effect {
+            Effect::LowPass { .. } | Effect::HighPass { .. } => EffectState::Filter {
+                high_pass: false,
+                coefficient: 0.0,
+                low: vec![0.0; channels],
+            },
+            Effect::Reverb(_) => EffectState::Reverb {
+                channels: (0..channels)
+                    .map(|channel| ReverbChannel::new(channel, sample_rate))
+                    .collect(),
+                feedback: 0.0,
+                damping: 0.0,
+                mix: 0.0,
+            },
+            Effect::Echo { delay, .. } => EffectState::Echo {
+                lines: (0..channels)
+                    .map(|_| DelayLine::new((delay.max(0.0) * sample_rate as f32) as usize))
+                    .collect(),
+                feedback: 0.0,
+                mix: 0.0,
+            },
+            Effect::Compressor { .. } => EffectState::Compressor {
+                envelope: 0.0,
+                threshold: 1.0,
+                ratio: 1.0,
+                attack: 0.0,
+                release: 0.0,
+            },
+        };
+        state.configure(effect, sample_rate);
+        state
+    }
+
+    /// Updates the parameters of the effect, keeping its buffers.
+    fn configure(&mut self, effect: &Effect, sample_rate: u32) {
+        match (self, effect) {
+            (
+                EffectState::Filter {
+                    high_pass,
+                    coefficient,
+                    ..
+                },
+                Effect::LowPass { cutoff },
+            )
+            | (
+                EffectState::Filter {
+                    high_pass,
+                    coefficient,
+                    ..
+                },
+                Effect::HighPass { cutoff },
+            ) => {
+                *high_pass = match effect {
+                    Effect::HighPass { .. } => true,
+                    _ => false,
+                };
+                let cutoff = cutoff.max(0.0) / sample_rate.max(1) as f32;
+                *coefficient = 1.0 - (-2.0 * std::f32::consts::PI * cutoff).exp();
+            }
+            (
+                EffectState::Reverb {
+                    feedback,
+                    damping,
+                    mix,
+                    ..
+                },
+                Effect::Reverb(reverb),
+            ) => {
+                *feedback = reverb.room_size.max(0.0).min(1.0) * 0.28 + 0.7;
+                *damping = reverb.damping.max(0.0).min(1.0) * 0.4;
+                *mix = reverb.mix.max(0.0).min(1.0);
+            }
+            (
+                EffectState::Echo {
+                    lines,
+                    feedback,
+                    mix,
+                },
+                Effect::Echo {
+                    delay,
+                    feedback: new_feedback,
+                    mix: new_mix,
+                },
+            ) => {
+                // The sample rate follows the pitch of the sound. The echoes ringing are kept,
+                // and small changes like the Doppler effect of slow emitters are ignored.
+                let len = ((delay.max(0.0) * sample_rate as f32) as usize).max(1);
+                let resize = lines.first().map_or(false, |line| {
+                    (line.buffer.len() as isize - len as isize).abs() * 100
+                        > line.buffer.len() as isize
+                });
+                if resize {
+                    for line in lines.iter_mut() {
+                        line.resize(len);
+                    }
+                }
+                *feedback = new_feedback.max(0.0).min(0.99);
+                *mix = new_mix.max(0.0).min(1.0);
+            }
+            (
+                EffectState::Compressor {
+                    threshold,
+                    ratio,
+                    attack,
+                    release,
+                    ..
+                },
+                Effect::Compressor {
+                    threshold: new_threshold,
+                    ratio: new_ratio,
+                    attack: new_attack,
+                    release: new_release,
+                },
+            ) => {
+                *threshold = new_threshold.max(std::f32::EPSILON);
+                *ratio = new_ratio.max(1.0);
+                *attack = time_coefficient(*new_attack, sample_rate);
+                *release = time_coefficient(*new_release, sample_rate);
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns true if the effect outputs silence while its input is silent.
+    fn is_silent(&self) -> bool {
+        match self {
+            EffectState::Filter { low, .. } => low.iter().all(|value| value.abs() < SILENCE),
+            EffectState::Reverb { channels, .. } => channels.iter().all(ReverbChannel::is_silent),
+            EffectState::Echo { lines, .. } => lines.iter().all(DelayLine::is_silent),
+            EffectState::Compressor { .. } => true,
+        }
+    }
+
+    fn process(&mut self, sample: f32, channel: usize) -> f32 {
+        match self {
+            EffectState::Filter {
+                high_pass,
+                coefficient,
+                low,
+            } => {
+                let len = low.len();
+                let low = &mut low[channel % len];
+                *low += *coefficient * (sample - *low);
+                if *high_pass {
+                    sample - *low
+                } else {
+                    *low
+                }
+            }
+            EffectState::Reverb {
+                channels,
+                feedback,
+                damping,
+                mix,
+            } => {
+                let len = channels.len();
+                let wet = channels[channel % len].process(sample, *feedback, *damping);
+                sample * (1.0 - *mix) + wet * *mix
+            }
+            EffectState::Echo {
+                lines,
+                feedback,
+                mix,
+            } => {
+                let len = lines.len();
+                let line = &mut lines[channel % len];
+                let delayed = line.read();
+                line.write(sample + delayed * *feedback);
+                sample + delayed * *mix
+            }
+            EffectState::Compressor {
+                envelope,
+                threshold,
+                ratio,
+                attack,
+                release,
+            } => {
+                let level = sample.abs();
+                let coefficient = if level > *envelope { *attack } else { *release };
+                *envelope = level + coefficient * (*envelope - level);
+                if *envelope > *threshold {
+                    sample * (*threshold + (*envelope - *threshold) / *ratio) / *envelope
+                } else {
+                    sample
+                }
+            }
+        }
+    }
+}
+
+/// Applies an `EffectChain` to a source.
+///
+/// Once the source ends, the effects keep playing until their reverberations and echoes fade
+/// out.
+pub(crate) struct EffectsSource<I> {
+    input: I,
+    chain: Arc<EffectChain>,
+    generation: usize,
+    effects: Vec<(Effect, EffectState)>,
+    channels: u16,
+    sample_rate: u32,
+    /// Channel of the next sample.
+    channel: usize,
+    until_poll: u32,
+    /// Samples left in the current frame of the tail, `None` while the source plays.
+    tail: Option<usize>,
+}
+
+impl<I> EffectsSource<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    pub(crate) fn new(input: I, chain: Arc<EffectChain>) -> Self {
+        EffectsSource {
+            channels: input.channels(),
+            sample_rate: input.sample_rate(),
+            input,
+            // Makes the first poll read the effects.
+            generation: chain.generation.load(Ordering::Relaxed).wrapping_sub(1),
+            chain,
+            effects: Vec::new(),
+            channel: 0,
+            until_poll: 0,
+            tail: None,
+        }
+    }
+
+    fn is_silent(&self) -> bool {
+        self.effects.iter().all(|(_, state)| state.is_silent())
+    }
+
+    fn tail_frame_len(&self) -> usize {
+        TAIL_FRAME * usize::from(self.channels.max(1))
+    }
+
+    /// Starts a frame of the tail, returning false if the effects are silent.
+    fn next_tail_frame(&mut self) -> bool {
+        if self.is_silent() {
+            self.tail = Some(0);
+            false
+        } else {
+            self.tail = Some(self.tail_frame_len());
+            true
+        }
+    }
+
+    /// Reads the effects without blocking, they're read again later if the chain is being set.
+    fn poll(&mut self) {
+        let channels = self.input.channels();
+        let sample_rate = self.input.sample_rate();
+        let generation = self.chain.generation.load(Ordering::Relaxed);
+        if generation != self.generation || channels != self.channels {
+            let effects = match self.chain.effects.try_lock() {
+                Ok(effects) => effects,
+                Err(_) => return,
+            };
+            let old = std::mem::replace(&mut self.effects, Vec::with_capacity(effects.len()));
+            let mut old = old.into_iter();
+            for effect in effects.iter() {
+                let state = match old.next() {
+                    // Keeps the buffers of the effect, so the sound doesn't click.
+                    Some((previous, mut state))
+                        if channels == self.channels
+                            && discriminant(&previous) == discriminant(effect) =>
+                    {
+                        state.configure(effect, sample_rate);
+                        state
+                    }
+                    _ => EffectState::new(effect, channels, sample_rate),
+                };
+                self.effects.push((*effect, state));
+            }
+            self.generation = generation;
+        } else if sample_rate != self.sample_rate {
+            for (effect, state) in &mut self.effects {
+                state.configure(effect, sample_rate);
+            }
+        }
+        self.channels = channels;
+        self.sample_rate = sample_rate;
+    }
+}
+
+impl<I> Iterator for EffectsSource<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let mut sample = match self.tail {
+            None => {
+                // Polls between frames, so the channel of each sample stays known.
+                if self.until_poll == 0 && self.channel == 0 {
+                    self.poll();
+                    self.until_poll = POLL_PERIOD;
+                }
+                self.until_poll = self.until_poll.saturating_sub(1);
+                match self.input.next() {
+                    Some(sample) => sample.to_f32(),
+                    None if self.next_tail_frame() => 0.0,
+                    None => return None,
+                }
+            }
+            Some(0) => {
+                if !self.next_tail_frame() {
+                    return None;
+                }
+                0.0
+            }
+            Some(_) => 0.0,
+        };
+        if let Some(left) = &mut self.tail {
+            *left -= 1;
+        }
+        for (_, state) in &mut self.effects {
+            sample = state.process(sample, self.channel);
+        }
+        self.channel = (self.channel + 1) % usize::from(self.channels.max(1));
+        Some(sample)
+    }
+}
+
+impl<I> Source for EffectsSource<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.tail {
+            // Frames of the tail end when the effects are silent.
+            Some(0) if self.is_silent() => Some(0),
+            Some(0) => Some(self.tail_frame_len()),
+            Some(left) => Some(left),
+            None => match self.input.current_frame_len() {
+                Some(0) if !self.is_silent() => Some(self.tail_frame_len()),
+                len => len,
+            },
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self.tail {
+            Some(_) => self.channels,
+            None => self.input.channels(),
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self.tail {
+            Some(_) => self.sample_rate,
+            None => self.input.sample_rate(),
+        }
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        // The duration of the tail isn't known.
+        if self.effects.is_empty() {
+            self.input.total_duration()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use rodio::{buffer::SamplesBuffer, Source};
+
+    use crate::effects::{Effect, EffectChain, EffectsSource};
+
+    /// A mono sound whose sample rate can change while it plays, like a sound with a pitch.
+    struct Pitched {
+        samples: std::vec::IntoIter<f32>,
+        sample_rate: Arc<AtomicU32>,
+    }
+
+    impl Iterator for Pitched {
+        type Item = f32;
+
+        fn next(&mut self) -> Option<f32> {
+            self.samples.next()
+        }
+    }
+
+    impl Source for Pitched {
+        fn current_frame_len(&self) -> Option<usize> {
+            Some(self.samples.len())
+        }
+
+        fn channels(&self) -> u16 {
+            1
+        }
+
+        fn sample_rate(&self) -> u32 {
+            self.sample_rate.load(Ordering::Relaxed)
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    fn apply(effects: Vec<Effect>, samples: Vec<f32>) -> Vec<f32> {
+        let chain = Arc::new(EffectChain::new(effects));
+        EffectsSource::new(SamplesBuffer::new(1, 100, samples), chain).collect()
+    }
+
+    #[test]
+    fn test_no_effect() {
+        assert_eq!(apply(Vec::new(), vec![0.5, -0.5]), vec![0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_echo() {
+        let echo = Effect::Echo {
+            delay: 0.02,
+            feedback: 0.5,
+            mix: 0.5,
+        };
+        let samples = apply(vec![echo], vec![1.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(&samples[..5], &[1.0, 0.0, 0.5, 0.0, 0.25]);
+    }
+
+    #[test]
+    fn test_echo_pitch_change() {
+        let echo = Effect::Echo {
+            delay: 0.1,
+            feedback: 0.5,
+            mix: 0.5,
+        };
+        let mut samples = vec![0.0; 400];
+        samples[0] = 1.0;
+        let sample_rate = Arc::new(AtomicU32::new(1000));
+        let mut source = EffectsSource::new(
+            Pitched {
+                samples: samples.into_iter(),
+                sample_rate: sample_rate.clone(),
+            },
+            Arc::new(EffectChain::new(vec![echo])),
+        );
+        let mut output = source.by_ref().take(150).collect::<Vec<_>>();
+        assert_eq!(output[100], 0.5);
+        // The echoes ringing are stretched to the new delay, not cleared.
+        sample_rate.store(1100, Ordering::Relaxed);
+        output.extend(source.take(250));
+        assert_eq!(output[200], 0.25);
+        assert!(output[257..].iter().any(|s| s.abs() > 0.1));
+    }
+
+    #[test]
+    fn test_tail() {
+        let echo = Effect::Echo {
+            delay: 0.02,
+            feedback: 0.5,
+            mix: 0.5,
+        };
+        // The echoes continue after the sound, until they're inaudible.
+        let samples = apply(vec![echo], vec![1.0]);
+        assert_eq!(&samples[..5], &[1.0, 0.0, 0.5, 0.0, 0.25]);
+        assert_eq!(samples.len(), 1 + 256);
+        assert!(samples[200..].iter().all(|s| s.abs() < 0.0001));
+
+        let reverb = Effect::Reverb(Default::default());
+        let samples = apply(vec![reverb], vec![1.0]);
+        assert!(samples.len() > 100 && samples[1..].iter().any(|s| s.abs() > 0.001));
+
+        assert_eq!(apply(Vec::new(), vec![1.0]), vec![1.0]);
+    }
+
+    #[test]
+    fn test_low_pass_and_high_pass() {
+        let constant = vec![1.0; 400];
+        let low = apply(vec![Effect::LowPass { cutoff: 10.0 }], constant.clone());
+        let high = apply(vec![Effect::HighPass { cutoff: 10.0 }], constant);
+        // A constant signal passes through the low-pass filter, and not the high-pass one.
+        assert!(low[0] < 1.0 && (low[399] - 1.0).abs() < 0.01);
+        assert!(high[0] > 0.0 && high[399].abs() < 0.01);
+    }
+
+    #[test]
+    fn test_compressor() {
+        let compressor = Effect::Compressor {
+            threshold: 0.5,
+            ratio: 4.0,
+            attack: 0.0,
+            release: 0.0,
+        };
+        assert_eq!(apply(vec![compressor], vec![1.0, 0.25]), vec![0.625, 0.25]);
+    }
+}
@@ -0,0 +1,247 @@
+//! Sound design data loaded from RON files, see `AudioEventBank`.
+
+use amethyst_assets::{Asset, Handle};
+use amethyst_core::ecs::prelude::VecStorage;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// A handle to an audio event bank asset.
+pub type AudioEventBankHandle = Handle<AudioEventBank>;
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+fn default_range() -> (f32, f32) {
+    (1.0, 1.0)
+}
+
+fn default_avoid_repeats() -> bool {
+    true
+}
+
+/// A sound an `AudioEvent` can pick.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EventSound {
+    /// Path of the audio file, loaded with the format matching its extension.
+    pub path: String,
+    /// How likely the sound is picked compared to the other sounds of the event.
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+/// A sound effect playing one of its sounds at random, with a random volume and pitch.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AudioEvent {
+    /// The name of the event, passed to `AudioEmitter::trigger` and `AudioEvents::trigger`.
+    pub name: String,
+    /// The sounds picked by the event.
+    pub sounds: Vec<EventSound>,
+    /// Prevents the event from playing the same sound twice in a row.
+    #[serde(default = "default_avoid_repeats")]
+    pub avoid_repeats: bool,
+    /// Range of the volume of the sounds, a volume of 1.0 is unchanged.
+    #[serde(default = "default_range")]
+    pub volume: (f32, f32),
+    /// Range of the pitch of the sounds, a pitch of 1.0 is unchanged.
+    #[serde(default = "default_range")]
+    pub pitch: (f32, f32),
+    /// Minimum time between two sounds of the event in seconds, triggers in between are ignored.
+    #[serde(default)]
+    pub cooldown: f32,
+    /// Maximum number of sounds of the event playing at once, triggers beyond it are ignored.
+    #[serde(default)]
+    pub max_instances: Option<usize>,
+    /// The priority of the sounds triggered on an `AudioEmitter`, see `Voices`.
+    #[serde(default)]
+    pub priority: i32,
+    /// The mixer bus of the sounds triggered with `AudioEvents::trigger`, `None` for the master
+    /// bus. Sounds triggered on an `AudioEmitter` use the bus of the emitter.
+    #[serde(default)]
+    pub bus: Option<String>,
+}
+
+impl AudioEvent {
+    /// Picks the index of a sound by weight, `last` being the previously picked sound.
+    pub fn pick<R: Rng + ?Sized>(&self, last: Option<usize>, rng: &mut R) -> Option<usize> {
+        let excluded = if self.avoid_repeats && self.sounds.len() > 1 {
+            last
+        } else {
+            None
+        };
+        let weight = |(index, sound): (usize, &EventSound)| {
+            if Some(index) == excluded {
+                0.0
+            } else {
+                sound.weight.max(0.0)
+            }
+        };
+        let total: f32 = self.sounds.iter().enumerate().map(weight).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut remaining = rng.gen::<f32>() * total;
+        let mut picked = None;
+        for (index, sound) in self.sounds.iter().enumerate() {
+            let weight = weight((index, sound));
+            if weight > 0.0 {
+                picked = Some(index);
+                if remaining < weight {
+                    break;
+                }
+                remaining -= weight;
+            }
+        }
+        picked
+    }
+
+    /// Returns a random volume in the volume range.
+    pub fn random_volume<R: Rng + ?Sized>(&self, rng: &mut R) -> f32 {
+        random_in(self.volume, rng)
+    }
+
+    /// Returns a random pitch in the pitch range.
+    pub fn random_pitch<R: Rng + ?Sized>(&self, rng: &mut R) -> f32 {
+        random_in(self.pitch, rng)
+    }
+}
+
+fn random_in<R: Rng + ?Sized>((min, max): (f32, f32), rng: &mut R) -> f32 {
+    if max > min {
+        rng.gen_range(min, max)
+    } else {
+        min
+    }
+}
+
+/// The audio events of a game, loaded from RON files.
+///
+/// ```ron
+/// (
+///     events: [
+///         (
+///             name: "footstep",
+///             sounds: [
+///                 (path: "sfx/step_1.ogg"),
+///                 (path: "sfx/step_2.ogg"),
+///                 (path: "sfx/step_3.ogg", weight: 0.5),
+///             ],
+///             volume: (0.8, 1.0),
+///             pitch: (0.95, 1.05),
+///             cooldown: 0.1,
+///             max_instances: Some(4),
+///         ),
+///     ],
+/// )
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioEventBank {
+    /// The events of the bank.
+    pub events: Vec<AudioEvent>,
+}
+
+impl AudioEventBank {
+    /// Returns the event with this name.
+    pub fn event(&self, name: &str) -> Option<&AudioEvent> {
+        self.events.iter().find(|event| event.name == name)
+    }
+}
+
+impl Asset for AudioEventBank {
+    const NAME: &'static str = "audio::AudioEventBank";
+    type Data = Self;
+    type HandleStorage = VecStorage<AudioEventBankHandle>;
+}
+
+/// Resource triggering the events of an `AudioEventBank`, played by the `AudioEventSystem`.
+///
+/// Events triggered here play on the `Output` without position, use `AudioEmitter::trigger` to
+/// play them from an entity.
+#[derive(Debug, Default)]
+pub struct AudioEvents {
+    pub(crate) bank: Option<AudioEventBankHandle>,
+    pub(crate) triggered: Vec<String>,
+}
+
+impl AudioEvents {
+    /// Creates a resource triggering events of `bank`.
+    pub fn new(bank: AudioEventBankHandle) -> Self {
+        AudioEvents {
+            bank: Some(bank),
+            triggered: Vec::new(),
+        }
+    }
+
+    /// Returns the bank of the events.
+    pub fn bank(&self) -> Option<&AudioEventBankHandle> {
+        self.bank.as_ref()
+    }
+
+    /// Sets the bank of the events.
+    pub fn set_bank(&mut self, bank: AudioEventBankHandle) {
+        self.bank = Some(bank);
+    }
+
+    /// Plays the event with this name on the `Output`.
+    ///
+    /// The event is played once its bank and sounds are loaded.
+    pub fn trigger(&mut self, event: &str) {
+        self.triggered.push(event.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::mock::StepRng, thread_rng};
+
+    use crate::event::{AudioEvent, EventSound};
+
+    fn event(weights: &[f32]) -> AudioEvent {
+        AudioEvent {
+            name: "footstep".to_string(),
+            sounds: weights
+                .iter()
+                .map(|weight| EventSound {
+                    path: "step.ogg".to_string(),
+                    weight: *weight,
+                })
+                .collect(),
+            avoid_repeats: true,
+            volume: (0.5, 1.0),
+            pitch: (1.0, 1.0),
+            cooldown: 0.0,
+            max_instances: None,
+            priority: 0,
+            bus: None,
+        }
+    }
+
+    #[test]
+    fn test_pick() {
+        let mut rng = thread_rng();
+        let footstep = event(&[1.0, 0.0, 2.0]);
+        for _ in 0..100 {
+            assert_ne!(footstep.pick(None, &mut rng), Some(1));
+            assert_eq!(footstep.pick(Some(2), &mut rng), Some(0));
+        }
+        assert_eq!(event(&[1.0]).pick(Some(0), &mut rng), Some(0));
+        assert_eq!(event(&[]).pick(None, &mut rng), None);
+        let mut last = None;
+        for _ in 0..100 {
+            let picked = event(&[1.0, 1.0, 1.0]).pick(last, &mut rng);
+            assert_ne!(picked, last);
+            last = picked;
+        }
+    }
+
+    #[test]
+    fn test_random_ranges() {
+        let footstep = event(&[1.0]);
+        let mut rng = StepRng::new(0, 1 << 30);
+        for _ in 0..10 {
+            let volume = footstep.random_volume(&mut rng);
+            assert!(volume >= 0.5 && volume < 1.0);
+            assert_eq!(footstep.random_pitch(&mut rng), 1.0);
+        }
+    }
+}
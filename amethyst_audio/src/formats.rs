@@ -1,12 +1,77 @@
+use std::path::Path;
+
 use amethyst_assets::*;
-use amethyst_error::Error;
+use amethyst_error::{format_err, Error};
+use log::error;
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    metadata::AudioMetadata,
+    source::{DecodeMode, DecodedSamples, SharedBytes, Source, SourceHandle},
+    DecoderError,
+};
+
+/// The bytes of an audio file, when to decode them and their metadata.
 #[derive(Clone, Debug)]
-pub struct AudioData(pub Vec<u8>);
+pub struct AudioData {
+    pub(crate) bytes: SharedBytes,
+    /// When the samples of the audio file are decoded.
+    pub mode: DecodeMode,
+    /// The metadata of the audio file, read by its format or when its `Source` is processed.
+    pub metadata: Option<AudioMetadata>,
+    /// The samples decoded by `WithDecodeMode`, if the mode preloads the file.
+    pub(crate) samples: Option<DecodedSamples>,
+}
 amethyst_assets::register_format_type!(AudioData);
 
+impl AudioData {
+    /// Creates the data of an audio file from its bytes, streamed when it plays.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        AudioData {
+            bytes: bytes.into(),
+            mode: DecodeMode::Stream,
+            metadata: None,
+            samples: None,
+        }
+    }
+
+    /// Returns the bytes of the audio file.
+    pub fn bytes(&self) -> &[u8] {
+        self.bytes.as_ref()
+    }
+
+    /// Validates the bytes of an audio file and reads their metadata.
+    fn read(bytes: Vec<u8>, format: &str) -> Result<AudioData, Error> {
+        let mut data = AudioData::new(bytes);
+        data.metadata = Some(
+            AudioMetadata::from_bytes(&data.bytes)
+                .map_err(|_| format_err!("Invalid or corrupt {} audio file", format))?,
+        );
+        Ok(data)
+    }
+
+    /// Decodes the samples of the audio file if its mode preloads it, reading its metadata first
+    /// if the format didn't.
+    pub(crate) fn preload(&mut self) -> Result<(), DecoderError> {
+        if self.metadata.is_none() {
+            self.metadata = Some(AudioMetadata::from_bytes(&self.bytes)?);
+        }
+        let preload = match (self.mode.max_preload_seconds(), &self.metadata) {
+            (Some(max_seconds), Some(metadata)) => {
+                let duration = metadata.duration.as_secs() as f32
+                    + metadata.duration.subsec_nanos() as f32 / 1e9;
+                duration <= max_seconds
+            }
+            _ => false,
+        };
+        if preload && self.samples.is_none() {
+            self.samples = DecodedSamples::decode(&self.bytes, None)?;
+        }
+        Ok(())
+    }
+}
+
 /// Loads audio from wav files.
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct WavFormat;
@@ -18,7 +83,7 @@ impl Format<AudioData> for WavFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
-        Ok(AudioData(bytes))
+        AudioData::read(bytes, self.name())
     }
 }
 
@@ -33,7 +98,7 @@ impl Format<AudioData> for OggFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
-        Ok(AudioData(bytes))
+        AudioData::read(bytes, self.name())
     }
 }
 
@@ -48,7 +113,7 @@ impl Format<AudioData> for FlacFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
-        Ok(AudioData(bytes))
+        AudioData::read(bytes, self.name())
     }
 }
 
@@ -63,6 +128,72 @@ impl Format<AudioData> for Mp3Format {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
-        Ok(AudioData(bytes))
+        AudioData::read(bytes, self.name())
     }
 }
+
+/// Loads audio with another format, choosing when it's decoded.
+///
+/// ```rust,ignore
+/// loader.load(
+///     "sounds/shot.wav",
+///     WithDecodeMode::new(WavFormat, DecodeMode::Preload),
+///     (),
+///     &storage,
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct WithDecodeMode<F> {
+    /// The format of the audio file.
+    pub format: F,
+    /// When the audio is decoded.
+    pub mode: DecodeMode,
+}
+
+impl<F> WithDecodeMode<F> {
+    /// Loads audio with `format`, decoding it according to `mode`.
+    pub fn new(format: F, mode: DecodeMode) -> Self {
+        WithDecodeMode { format, mode }
+    }
+}
+
+impl<F> Format<AudioData> for WithDecodeMode<F>
+where
+    F: Format<AudioData> + Clone,
+{
+    fn name(&self) -> &'static str {
+        self.format.name()
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<AudioData, Error> {
+        let mut data = self.format.import_simple(bytes)?;
+        data.mode = self.mode;
+        // Decodes the samples on the thread loading the file, not when the `Source` is processed.
+        data.preload()
+            .map_err(|_| format_err!("Invalid or corrupt {} audio file", self.name()))?;
+        Ok(data)
+    }
+}
+
+/// Loads an audio file with the format matching its extension.
+pub(crate) fn load_source<P: Progress>(
+    loader: &Loader,
+    path: &str,
+    progress: P,
+    storage: &AssetStorage<Source>,
+) -> Option<SourceHandle> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+    Some(match extension.as_ref().map(String::as_str) {
+        Some("wav") => loader.load(path, WavFormat, progress, storage),
+        Some("ogg") => loader.load(path, OggFormat, progress, storage),
+        Some("flac") => loader.load(path, FlacFormat, progress, storage),
+        Some("mp3") => loader.load(path, Mp3Format, progress, storage),
+        _ => {
+            error!("Unsupported audio format for {}", path);
+            return None;
+        }
+    })
+}
@@ -11,10 +11,18 @@
 pub use self::{
     bundle::AudioBundle,
     components::*,
-    formats::{FlacFormat, Mp3Format, OggFormat, WavFormat},
+    effects::{Effect, Reverb},
+    event::{AudioEvent, AudioEventBank, AudioEventBankHandle, AudioEvents, EventSound},
+    formats::{AudioData, FlacFormat, Mp3Format, OggFormat, WavFormat, WithDecodeMode},
+    metadata::AudioMetadata,
+    mixer::{Bus, BusSettings, DuckingRule, Mixer, MixerSettings},
+    music::{Music, MusicTrack, Playlist, PlaylistHandle, Stem, Transition, TransitionSync},
+    playback::{LoopPoints, PlaybackHandle},
     sink::AudioSink,
-    source::{Source, SourceHandle},
+    source::{DecodeMode, Source, SourceHandle},
+    spatial::{Attenuation, AudioOcclusion, Doppler, Rolloff},
     systems::*,
+    voice::Voices,
 };
 
 use std::{
@@ -26,11 +34,18 @@ pub mod output;
 
 mod bundle;
 mod components;
-mod end_signal;
+mod effects;
+mod event;
 mod formats;
+mod metadata;
+mod mixer;
+mod music;
+mod playback;
 mod sink;
 mod source;
+mod spatial;
 mod systems;
+mod voice;
 
 /// An error occurred while decoding the source.
 #[derive(Debug)]
@@ -0,0 +1,225 @@
+//! Information about audio files, read when they're loaded.
+
+use std::{io::Cursor, time::Duration};
+
+use lewton::inside_ogg::OggStreamReader;
+use rodio::{Decoder, Source as RSource};
+
+use crate::{playback::LoopPoints, source::SharedBytes, DecoderError};
+
+/// Information about an audio file, read by the formats when it's loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AudioMetadata {
+    /// The number of channels of the sound.
+    pub channels: u16,
+    /// The number of sample frames per second.
+    pub sample_rate: u32,
+    /// The duration of the sound.
+    pub duration: Duration,
+    /// The loop of the sound, read from the `smpl` chunk of WAV files or the `LOOPSTART` and
+    /// `LOOPEND` or `LOOPLENGTH` comments of Ogg Vorbis files.
+    pub loop_points: Option<LoopPoints>,
+}
+
+impl AudioMetadata {
+    /// Reads the metadata of an audio file, from its headers or decoding it to measure its
+    /// duration if they don't have it.
+    ///
+    /// This will return an Error if the bytes can't be decoded.
+    pub fn read(bytes: &[u8]) -> Result<Self, DecoderError> {
+        AudioMetadata::from_bytes(&bytes.to_vec().into())
+    }
+
+    /// Reads the metadata of the bytes of a `Source`, sharing them with the decoder.
+    pub(crate) fn from_bytes(bytes: &SharedBytes) -> Result<Self, DecoderError> {
+        let decoder = Decoder::new(Cursor::new(bytes.clone())).map_err(|_| DecoderError)?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        if channels == 0 || sample_rate == 0 {
+            return Err(DecoderError);
+        }
+        let bytes = bytes.as_ref();
+        let duration = match wav_frames(bytes).or_else(|| ogg_frames(bytes)) {
+            Some(frames) => frames_duration(frames, sample_rate),
+            // Flac files have their duration in their header, mp3 files don't.
+            None => match decoder.total_duration() {
+                Some(duration) => duration,
+                None => frames_duration(decoder.count() as u64 / u64::from(channels), sample_rate),
+            },
+        };
+        Ok(AudioMetadata {
+            channels,
+            sample_rate,
+            duration,
+            loop_points: wav_loop_points(bytes).or_else(|| ogg_loop_points(bytes)),
+        })
+    }
+}
+
+/// Returns the duration of `frames` sample frames.
+pub(crate) fn frames_duration(frames: u64, sample_rate: u32) -> Duration {
+    let rate = u64::from(sample_rate.max(1));
+    Duration::new(frames / rate, (frames % rate * 1_000_000_000 / rate) as u32)
+}
+
+fn u32_le(bytes: &[u8]) -> u32 {
+    let mut le = [0; 4];
+    le.copy_from_slice(&bytes[..4]);
+    u32::from_le_bytes(le)
+}
+
+/// Returns the data of the first chunk of a WAV file with this id.
+fn wav_chunk<'a>(bytes: &'a [u8], id: &[u8]) -> Option<&'a [u8]> {
+    if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let mut chunks = &bytes[12..];
+    while chunks.len() >= 8 {
+        let len = u32_le(&chunks[4..8]) as usize;
+        let data = chunks.get(8..8 + len)?;
+        if &chunks[0..4] == id {
+            return Some(data);
+        }
+        // Chunks are padded to an even length.
+        chunks = chunks.get(8 + len + len % 2..).unwrap_or(&[]);
+    }
+    None
+}
+
+/// Reads the number of sample frames of a WAV file from the length of its `data` chunk.
+fn wav_frames(bytes: &[u8]) -> Option<u64> {
+    let block_align = wav_chunk(bytes, b"fmt ")?.get(12..14)?;
+    let block_align = u64::from(u16::from_le_bytes([block_align[0], block_align[1]]));
+    if block_align == 0 {
+        return None;
+    }
+    Some(wav_chunk(bytes, b"data")?.len() as u64 / block_align)
+}
+
+/// Reads the first loop of the `smpl` chunk of a WAV file.
+fn wav_loop_points(bytes: &[u8]) -> Option<LoopPoints> {
+    let data = wav_chunk(bytes, b"smpl")?;
+    // The loops follow the 36 bytes of the chunk header, their end is inclusive.
+    if u32_le(data.get(28..32)?) == 0 {
+        return None;
+    }
+    let first = data.get(36..60)?;
+    Some(LoopPoints {
+        start: u64::from(u32_le(&first[8..12])),
+        end: Some(u64::from(u32_le(&first[12..16])) + 1),
+    })
+}
+
+/// Reads the number of sample frames of an Ogg file from the granule position of its last page.
+fn ogg_frames(bytes: &[u8]) -> Option<u64> {
+    if !bytes.starts_with(b"OggS") {
+        return None;
+    }
+    let page = bytes.windows(5).rposition(|capture| capture == b"OggS\0")?;
+    let mut le = [0; 8];
+    le.copy_from_slice(bytes.get(page + 6..page + 14)?);
+    match u64::from_le_bytes(le) {
+        // The granule position of pages without a packet ending in them.
+        std::u64::MAX => None,
+        frames => Some(frames),
+    }
+}
+
+/// Reads the loop comments of an Ogg Vorbis file.
+fn ogg_loop_points(bytes: &[u8]) -> Option<LoopPoints> {
+    if !bytes.starts_with(b"OggS") {
+        return None;
+    }
+    let reader = OggStreamReader::new(Cursor::new(bytes)).ok()?;
+    comment_loop_points(&reader.comment_hdr.comment_list)
+}
+
+/// Reads the `LOOPSTART` and `LOOPEND` or `LOOPLENGTH` comments, in sample frames.
+fn comment_loop_points(comments: &[(String, String)]) -> Option<LoopPoints> {
+    let value = |key: &str| {
+        comments
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .and_then(|(_, value)| value.trim().parse::<u64>().ok())
+    };
+    let start = value("LOOPSTART")?;
+    let end = value("LOOPEND").or_else(|| value("LOOPLENGTH").map(|length| start + length));
+    Some(LoopPoints { start, end })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        metadata::{comment_loop_points, AudioMetadata},
+        playback::LoopPoints,
+    };
+
+    fn chunk(bytes: &mut Vec<u8>, id: &[u8], data: &[u8]) {
+        bytes.extend_from_slice(id);
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+    }
+
+    #[test]
+    fn test_wav_metadata() {
+        let mut format = Vec::new();
+        for field in &[1u16, 2] {
+            format.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in &[1000u32, 4000] {
+            format.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in &[4u16, 16] {
+            format.extend_from_slice(&field.to_le_bytes());
+        }
+        let mut sampler = vec![0; 36];
+        sampler[28] = 1;
+        for field in &[0u32, 0, 100, 199, 0, 0] {
+            sampler.extend_from_slice(&field.to_le_bytes());
+        }
+        let mut wave = b"WAVE".to_vec();
+        chunk(&mut wave, b"fmt ", &format);
+        chunk(&mut wave, b"smpl", &sampler);
+        chunk(&mut wave, b"data", &[0; 2000]);
+        let mut bytes = Vec::new();
+        chunk(&mut bytes, b"RIFF", &wave);
+
+        let metadata = AudioMetadata::read(&bytes).unwrap();
+        assert_eq!(metadata.channels, 2);
+        assert_eq!(metadata.sample_rate, 1000);
+        assert_eq!(metadata.duration, Duration::from_millis(500));
+        assert_eq!(
+            metadata.loop_points,
+            Some(LoopPoints {
+                start: 100,
+                end: Some(200),
+            })
+        );
+    }
+
+    #[test]
+    fn test_comment_loop_points() {
+        let comments = |list: &[(&str, &str)]| {
+            list.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            comment_loop_points(&comments(&[("LOOPSTART", "10"), ("LOOPLENGTH", "20")])),
+            Some(LoopPoints {
+                start: 10,
+                end: Some(30),
+            })
+        );
+        assert_eq!(
+            comment_loop_points(&comments(&[("loopstart", "10"), ("LOOPEND", "15")])),
+            Some(LoopPoints {
+                start: 10,
+                end: Some(15),
+            })
+        );
+        assert_eq!(comment_loop_points(&comments(&[("TITLE", "Theme")])), None);
+    }
+}
@@ -0,0 +1,427 @@
+//! Mixer buses controlling the volume of groups of sounds.
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+
+use log::error;
+use rodio::{Sample, Sink, Source as RSource};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    effects::{Effect, EffectChain, EffectsSource},
+    output::Output,
+    source::Source,
+    DecoderError,
+};
+
+/// A mixer bus, its volume applies to the sounds routed to it and to its child buses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bus {
+    /// The bus this one is mixed into, `None` for the master bus.
+    pub parent: Option<String>,
+    /// Volume of the bus. A volume of 1.0 is unchanged, while 0.0 is silent.
+    pub volume: f32,
+    /// Silences the bus and its children without changing their volume.
+    pub muted: bool,
+    /// Effects applied to the sounds of the bus and of its children.
+    ///
+    /// They're applied to each sound separately, after the effects of the sound.
+    pub effects: Vec<Effect>,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Bus {
+            parent: None,
+            volume: 1.0,
+            muted: false,
+            effects: Vec::new(),
+        }
+    }
+}
+
+impl Bus {
+    /// Creates a bus mixed into `parent`.
+    pub fn child_of(parent: &str) -> Self {
+        Bus {
+            parent: Some(parent.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Lowers the volume of a bus while sounds play on another, like music while a voice plays.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuckingRule {
+    /// The bus whose sounds trigger the ducking.
+    pub trigger: String,
+    /// The bus whose volume is lowered.
+    pub target: String,
+    /// Multiplies the volume of the target while ducked.
+    pub volume: f32,
+    /// Seconds to lower the volume from 1.0 to 0.0, or 0.0 to duck instantly.
+    pub attack: f32,
+    /// Seconds to raise the volume from 0.0 to 1.0 once the trigger is silent.
+    pub release: f32,
+}
+
+/// The volume and mute of a bus, see `MixerSettings`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BusSettings {
+    /// Volume of the bus.
+    pub volume: f32,
+    /// Whether the bus is muted.
+    pub muted: bool,
+}
+
+/// The volumes and mutes of the buses chosen by the player.
+///
+/// This implements `amethyst_config::Config`, so it can be saved with the settings of the game and
+/// applied with `Mixer::apply_settings`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MixerSettings {
+    /// The settings of each bus, by name.
+    pub buses: HashMap<String, BusSettings>,
+}
+
+/// A sound played by the mixer with `Mixer::play_once`.
+struct MixedSound {
+    bus: String,
+    volume: f32,
+    sink: Sink,
+}
+
+/// World resource mixing sounds through a hierarchy of named buses.
+///
+/// The default mixer has a `master` bus, and the `music`, `sfx`, `voice` and `ui` buses mixed into
+/// it. Sounds routed to a bus which doesn't exist play on the `master` bus.
+///
+/// Sounds are routed with `AudioEmitter::set_bus`, `AudioSink::set_bus`, `DjSystemDesc::with_bus`
+/// and `Mixer::play_once`, and the volumes are updated every frame by the `MixerSystem`.
+pub struct Mixer {
+    buses: HashMap<String, Bus>,
+    /// Rules lowering the volume of buses while others play.
+    pub ducking: Vec<DuckingRule>,
+    /// Current multiplier of each ducked bus.
+    ducks: HashMap<String, f32>,
+    /// Number of sounds playing on each bus.
+    playing: HashMap<String, usize>,
+    sounds: Vec<MixedSound>,
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        let mut mixer = Mixer::new();
+        for bus in &[Mixer::MUSIC, Mixer::SFX, Mixer::VOICE, Mixer::UI] {
+            mixer.add_bus(bus, Bus::child_of(Mixer::MASTER));
+        }
+        mixer
+    }
+}
+
+impl Mixer {
+    /// Name of the bus every other bus is mixed into.
+    pub const MASTER: &'static str = "master";
+    /// Name of the bus for music, used by the `DjSystem`.
+    pub const MUSIC: &'static str = "music";
+    /// Name of the bus for sound effects.
+    pub const SFX: &'static str = "sfx";
+    /// Name of the bus for dialogues.
+    pub const VOICE: &'static str = "voice";
+    /// Name of the bus for user interface sounds, used by the `UiSoundSystem` of `amethyst_ui`.
+    pub const UI: &'static str = "ui";
+
+    /// Creates a mixer with only the `master` bus.
+    pub fn new() -> Self {
+        let mut buses = HashMap::new();
+        buses.insert(Mixer::MASTER.to_string(), Bus::default());
+        Mixer {
+            buses,
+            ducking: Vec::new(),
+            ducks: HashMap::new(),
+            playing: HashMap::new(),
+            sounds: Vec::new(),
+        }
+    }
+
+    /// Adds a bus, replacing any bus with the same name.
+    pub fn add_bus(&mut self, name: &str, bus: Bus) {
+        self.buses.insert(name.to_string(), bus);
+    }
+
+    /// Removes a bus, its sounds and children then play on the `master` bus.
+    pub fn remove_bus(&mut self, name: &str) -> Option<Bus> {
+        self.buses.remove(name)
+    }
+
+    /// Returns a bus.
+    pub fn bus(&self, name: &str) -> Option<&Bus> {
+        self.buses.get(name)
+    }
+
+    /// Returns a bus, to change its volume, mute or parent.
+    pub fn bus_mut(&mut self, name: &str) -> Option<&mut Bus> {
+        self.buses.get_mut(name)
+    }
+
+    /// Returns the names of the buses.
+    pub fn bus_names(&self) -> impl Iterator<Item = &str> {
+        self.buses.keys().map(String::as_str)
+    }
+
+    /// Sets the volume of a bus, returning false if it doesn't exist.
+    pub fn set_volume(&mut self, name: &str, volume: f32) -> bool {
+        match self.buses.get_mut(name) {
+            Some(bus) => {
+                bus.volume = volume;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mutes or unmutes a bus, returning false if it doesn't exist.
+    pub fn set_muted(&mut self, name: &str, muted: bool) -> bool {
+        match self.buses.get_mut(name) {
+            Some(bus) => {
+                bus.muted = muted;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the volume applied to the sounds of a bus: the product of the volumes of the bus and
+    /// its parents, including ducking, or 0.0 if one of them is muted.
+    pub fn volume(&self, name: &str) -> f32 {
+        let mut volume = 1.0;
+        let mut current = Some(name);
+        // Bounded by the number of buses, in case the parents form a cycle.
+        for _ in 0..=self.buses.len() {
+            let name = match current {
+                Some(name) => name,
+                None => break,
+            };
+            match self.buses.get(name) {
+                Some(bus) => {
+                    if bus.muted {
+                        return 0.0;
+                    }
+                    volume *= bus.volume * self.ducks.get(name).cloned().unwrap_or(1.0);
+                    current = bus.parent.as_ref().map(String::as_str);
+                }
+                None if name != Mixer::MASTER => current = Some(Mixer::MASTER),
+                None => current = None,
+            }
+        }
+        volume
+    }
+
+    /// Sets the effects of a bus, returning false if the bus doesn't exist.
+    pub fn set_effects(&mut self, name: &str, effects: Vec<Effect>) -> bool {
+        match self.buses.get_mut(name) {
+            Some(bus) => {
+                bus.effects = effects;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the effects applied to the sounds of a bus: the effects of the bus followed by the
+    /// effects of its parents.
+    pub fn effects(&self, name: &str) -> Vec<Effect> {
+        let mut effects = Vec::new();
+        let mut current = Some(name);
+        // Bounded by the number of buses, in case the parents form a cycle.
+        for _ in 0..=self.buses.len() {
+            let name = match current {
+                Some(name) => name,
+                None => break,
+            };
+            match self.buses.get(name) {
+                Some(bus) => {
+                    effects.extend_from_slice(&bus.effects);
+                    current = bus.parent.as_ref().map(String::as_str);
+                }
+                None if name != Mixer::MASTER => current = Some(Mixer::MASTER),
+                None => current = None,
+            }
+        }
+        effects
+    }
+
+    /// Returns true if sounds played on the bus during the last update of the `MixerSystem`.
+    pub fn is_playing(&self, name: &str) -> bool {
+        self.playing
+            .get(name)
+            .map(|count| *count > 0)
+            .unwrap_or(false)
+    }
+
+    /// Returns the volumes and mutes of the buses.
+    pub fn settings(&self) -> MixerSettings {
+        MixerSettings {
+            buses: self
+                .buses
+                .iter()
+                .map(|(name, bus)| {
+                    let settings = BusSettings {
+                        volume: bus.volume,
+                        muted: bus.muted,
+                    };
+                    (name.clone(), settings)
+                })
+                .collect(),
+        }
+    }
+
+    /// Applies saved volumes and mutes to the buses, ignoring buses which don't exist.
+    pub fn apply_settings(&mut self, settings: &MixerSettings) {
+        for (name, settings) in &settings.buses {
+            if let Some(bus) = self.buses.get_mut(name) {
+                bus.volume = settings.volume;
+                bus.muted = settings.muted;
+            }
+        }
+    }
+
+    /// Plays a sound once on a bus. A volume of 1.0 is unchanged, while 0.0 is silent.
+    ///
+    /// This will return an Error if the loaded audio file in source could not be decoded.
+    pub fn try_play_once(
+        &mut self,
+        output: &Output,
+        source: &Source,
+        volume: f32,
+        bus: &str,
+    ) -> Result<(), DecoderError> {
+        self.play(output, source.samples()?, volume, bus);
+        Ok(())
+    }
+
+    /// Plays decoded samples once on a bus.
+    pub(crate) fn play<S>(&mut self, output: &Output, samples: S, volume: f32, bus: &str)
+    where
+        S: RSource + Send + 'static,
+        S::Item: Sample + Send,
+    {
+        let sink = output.sink();
+        let chain = Arc::new(EffectChain::new(self.effects(bus)));
+        sink.append(EffectsSource::new(samples, chain));
+        sink.set_volume(volume * self.volume(bus));
+        self.sounds.push(MixedSound {
+            bus: bus.to_string(),
+            volume,
+            sink,
+        });
+    }
+
+    /// Plays a sound once on a bus. A volume of 1.0 is unchanged, while 0.0 is silent.
+    ///
+    /// This may silently fail, in order to get error information use `try_play_once`.
+    pub fn play_once(&mut self, output: &Output, source: &Source, volume: f32, bus: &str) {
+        if let Err(err) = self.try_play_once(output, source, volume, bus) {
+            error!("An error occurred while trying to play a sound: {:?}", err);
+        }
+    }
+
+    /// Updates the ducking and the volume of the sounds played by the mixer.
+    ///
+    /// `playing` counts the sounds playing on each bus outside of the mixer.
+    pub(crate) fn update(&mut self, mut playing: HashMap<String, usize>, delta_seconds: f32) {
+        self.sounds.retain(|sound| !sound.sink.empty());
+        for sound in &self.sounds {
+            *playing.entry(sound.bus.clone()).or_insert(0) += 1;
+        }
+        self.playing = playing;
+        self.update_ducking(delta_seconds);
+        for sound in &self.sounds {
+            sound
+                .sink
+                .set_volume(sound.volume * self.volume(&sound.bus));
+        }
+    }
+
+    fn update_ducking(&mut self, delta_seconds: f32) {
+        // The level each target moves to, and the seconds to move over the full range.
+        let mut levels: HashMap<String, (f32, f32)> = HashMap::new();
+        for rule in &self.ducking {
+            let active = self.is_playing(&rule.trigger);
+            let level = levels.entry(rule.target.clone()).or_insert((1.0, 0.0));
+            if active && rule.volume <= level.0 {
+                *level = (rule.volume, rule.attack);
+            } else if !active && level.0 >= 1.0 {
+                level.1 = level.1.max(rule.release);
+            }
+        }
+        self.ducks.retain(|target, _| levels.contains_key(target));
+        for (target, (level, seconds)) in levels {
+            let duck = self.ducks.entry(target).or_insert(1.0);
+            let step = if seconds > 0.0 {
+                delta_seconds / seconds
+            } else {
+                1.0
+            };
+            *duck = if (level - *duck).abs() <= step {
+                level
+            } else {
+                *duck + step * (level - *duck).signum()
+            };
+        }
+    }
+}
+
+impl Debug for Mixer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Mixer")
+            .field("buses", &self.buses)
+            .field("ducking", &self.ducking)
+            .field("ducks", &self.ducks)
+            .field("playing", &self.playing)
+            .field("sounds", &self.sounds.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::mixer::{DuckingRule, Mixer};
+
+    #[test]
+    fn test_volume() {
+        let mut mixer = Mixer::default();
+        mixer.set_volume(Mixer::MASTER, 0.5);
+        mixer.set_volume(Mixer::MUSIC, 0.5);
+        assert_eq!(mixer.volume(Mixer::MUSIC), 0.25);
+        assert_eq!(mixer.volume("unknown"), 0.5);
+        mixer.set_muted(Mixer::MASTER, true);
+        assert_eq!(mixer.volume(Mixer::SFX), 0.0);
+    }
+
+    #[test]
+    fn test_ducking() {
+        let mut mixer = Mixer::default();
+        mixer.ducking.push(DuckingRule {
+            trigger: Mixer::VOICE.to_string(),
+            target: Mixer::MUSIC.to_string(),
+            volume: 0.5,
+            attack: 1.0,
+            release: 2.0,
+        });
+        let mut playing = HashMap::new();
+        playing.insert(Mixer::VOICE.to_string(), 1);
+        mixer.update(playing.clone(), 0.25);
+        assert_eq!(mixer.volume(Mixer::MUSIC), 0.75);
+        mixer.update(playing, 1.0);
+        assert_eq!(mixer.volume(Mixer::MUSIC), 0.5);
+        mixer.update(HashMap::new(), 0.5);
+        assert_eq!(mixer.volume(Mixer::MUSIC), 0.75);
+    }
+}
@@ -0,0 +1,290 @@
+//! Adaptive music made of layered stems, see `Music`.
+
+use std::collections::HashMap;
+
+use amethyst_assets::{Asset, Handle};
+use amethyst_core::ecs::prelude::VecStorage;
+use serde::{Deserialize, Serialize};
+
+/// A handle to a playlist asset.
+pub type PlaylistHandle = Handle<Playlist>;
+
+/// A layer of a `MusicTrack`, faded in and out by a music parameter.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stem {
+    /// Path of the audio file, loaded with the format matching its extension.
+    pub path: String,
+    /// The parameter controlling the volume of the stem, it's always played at full volume if
+    /// `None`.
+    #[serde(default)]
+    pub parameter: Option<String>,
+    /// Value of the parameter from which the stem is heard.
+    #[serde(default)]
+    pub threshold: f32,
+    /// How much the parameter must increase above `threshold` for the stem to reach full volume.
+    #[serde(default)]
+    pub fade_range: f32,
+}
+
+impl Stem {
+    /// Returns the volume of the stem for the given parameter values, between 0.0 and 1.0.
+    pub fn volume(&self, parameters: &HashMap<String, f32>) -> f32 {
+        let parameter = match &self.parameter {
+            Some(parameter) => parameter,
+            None => return 1.0,
+        };
+        let value = parameters.get(parameter).cloned().unwrap_or(0.0);
+        if value < self.threshold {
+            0.0
+        } else if self.fade_range > 0.0 {
+            ((value - self.threshold) / self.fade_range).min(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// When a transition to another track starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransitionSync {
+    /// As soon as the track is requested.
+    Immediate,
+    /// On the next beat of the current track.
+    Beat,
+    /// On the next bar of the current track.
+    Bar,
+}
+
+/// How the music moves from a track to another.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transition {
+    /// When the transition starts.
+    pub sync: TransitionSync,
+    /// Duration of the crossfade between the tracks in seconds, 0.0 to switch instantly.
+    pub crossfade: f32,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Transition {
+            sync: TransitionSync::Bar,
+            crossfade: 0.0,
+        }
+    }
+}
+
+fn default_beats_per_bar() -> u32 {
+    4
+}
+
+/// A piece of music made of stems played together in a loop.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MusicTrack {
+    /// The name of the track, passed to `Music::play`.
+    pub name: String,
+    /// The tempo of the track, in beats per minute.
+    pub tempo: f32,
+    /// The number of beats in a bar.
+    #[serde(default = "default_beats_per_bar")]
+    pub beats_per_bar: u32,
+    /// Time of the first beat from the start of the stems, in seconds.
+    #[serde(default)]
+    pub first_beat: f32,
+    /// The stems of the track, their files should have the same length.
+    pub stems: Vec<Stem>,
+}
+
+impl MusicTrack {
+    /// Returns the duration of a beat, in seconds.
+    pub fn beat_duration(&self) -> f32 {
+        60.0 / self.tempo.max(std::f32::EPSILON)
+    }
+
+    /// Returns the duration of a bar, in seconds.
+    pub fn bar_duration(&self) -> f32 {
+        self.beat_duration() * self.beats_per_bar.max(1) as f32
+    }
+
+    /// Returns the time from `position` in the track to the next boundary of `sync`, in seconds.
+    pub fn time_to_boundary(&self, position: f32, sync: TransitionSync) -> f32 {
+        let period = match sync {
+            TransitionSync::Immediate => return 0.0,
+            TransitionSync::Beat => self.beat_duration(),
+            TransitionSync::Bar => self.bar_duration(),
+        };
+        let position = position - self.first_beat;
+        if position < 0.0 {
+            return -position;
+        }
+        let remaining = period - position % period;
+        if remaining >= period {
+            0.0
+        } else {
+            remaining
+        }
+    }
+}
+
+/// The tracks of an adaptive music, loaded from RON files.
+///
+/// ```ron
+/// (
+///     transition: (sync: Bar, crossfade: 2.0),
+///     tracks: [
+///         (
+///             name: "explore",
+///             tempo: 96.0,
+///             stems: [
+///                 (path: "music/explore_pads.ogg"),
+///                 (path: "music/explore_drums.ogg", parameter: Some("intensity"), threshold: 0.5),
+///             ],
+///         ),
+///     ],
+/// )
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Playlist {
+    /// The tracks of the playlist.
+    pub tracks: Vec<MusicTrack>,
+    /// How the music moves from a track to another.
+    #[serde(default)]
+    pub transition: Transition,
+    /// Time for the stems to reach their volume after a parameter change, in seconds.
+    #[serde(default)]
+    pub parameter_fade: f32,
+}
+
+impl Playlist {
+    /// Returns the track with this name.
+    pub fn track(&self, name: &str) -> Option<&MusicTrack> {
+        self.tracks.iter().find(|track| track.name == name)
+    }
+}
+
+impl Asset for Playlist {
+    const NAME: &'static str = "audio::Playlist";
+    type Data = Self;
+    type HandleStorage = VecStorage<PlaylistHandle>;
+}
+
+/// Resource controlling the adaptive music played by the `MusicSystem`.
+///
+/// Systems drive the music from the game state by setting parameters, for example raising the
+/// `Music::INTENSITY` parameter during fights to fade in the drums.
+#[derive(Debug, Default)]
+pub struct Music {
+    pub(crate) playlist: Option<PlaylistHandle>,
+    pub(crate) requested: Option<String>,
+    pub(crate) current: Option<String>,
+    pub(crate) parameters: HashMap<String, f32>,
+    pub(crate) bus: Option<String>,
+}
+
+impl Music {
+    /// The conventional name of the parameter for the intensity of the music.
+    pub const INTENSITY: &'static str = "intensity";
+
+    /// Creates a music resource playing tracks of `playlist`.
+    pub fn new(playlist: PlaylistHandle) -> Self {
+        Music {
+            playlist: Some(playlist),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the playlist of the music.
+    pub fn playlist(&self) -> Option<&PlaylistHandle> {
+        self.playlist.as_ref()
+    }
+
+    /// Sets the playlist of the music, the current track keeps playing until another is played.
+    pub fn set_playlist(&mut self, playlist: PlaylistHandle) {
+        self.playlist = Some(playlist);
+    }
+
+    /// Moves to the track with this name, following the transition of the playlist.
+    pub fn play(&mut self, track: &str) {
+        self.requested = Some(track.to_string());
+    }
+
+    /// Stops the music, following the transition of the playlist.
+    pub fn stop(&mut self) {
+        self.requested = None;
+    }
+
+    /// Returns the track requested with `Music::play`.
+    pub fn requested_track(&self) -> Option<&str> {
+        self.requested.as_ref().map(String::as_str)
+    }
+
+    /// Returns the track currently heard, which differs from the requested one until the
+    /// transition starts.
+    pub fn current_track(&self) -> Option<&str> {
+        self.current.as_ref().map(String::as_str)
+    }
+
+    /// Returns the value of a parameter, 0.0 if it was never set.
+    pub fn parameter(&self, name: &str) -> f32 {
+        self.parameters.get(name).cloned().unwrap_or(0.0)
+    }
+
+    /// Sets the value of a parameter, fading the stems it controls in or out.
+    pub fn set_parameter(&mut self, name: &str, value: f32) {
+        self.parameters.insert(name.to_string(), value);
+    }
+
+    /// Sets the `Music::INTENSITY` parameter.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.set_parameter(Music::INTENSITY, intensity);
+    }
+
+    /// Returns the mixer bus of the music, `None` for the master bus.
+    pub fn bus(&self) -> Option<&str> {
+        self.bus.as_ref().map(String::as_str)
+    }
+
+    /// Routes the music to a mixer bus, see `Mixer::MUSIC`.
+    pub fn set_bus(&mut self, bus: Option<String>) {
+        self.bus = bus;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::music::{MusicTrack, Stem, TransitionSync};
+
+    #[test]
+    fn test_stem_volume() {
+        let stem = Stem {
+            path: "drums.ogg".to_string(),
+            parameter: Some("intensity".to_string()),
+            threshold: 0.5,
+            fade_range: 0.25,
+        };
+        let mut parameters = HashMap::new();
+        assert_eq!(stem.volume(&parameters), 0.0);
+        parameters.insert("intensity".to_string(), 0.625);
+        assert_eq!(stem.volume(&parameters), 0.5);
+        parameters.insert("intensity".to_string(), 1.0);
+        assert_eq!(stem.volume(&parameters), 1.0);
+    }
+
+    #[test]
+    fn test_time_to_boundary() {
+        let track = MusicTrack {
+            name: "explore".to_string(),
+            tempo: 120.0,
+            beats_per_bar: 4,
+            first_beat: 1.0,
+            stems: Vec::new(),
+        };
+        assert_eq!(track.time_to_boundary(0.25, TransitionSync::Bar), 0.75);
+        assert_eq!(track.time_to_boundary(1.25, TransitionSync::Beat), 0.25);
+        assert_eq!(track.time_to_boundary(1.5, TransitionSync::Bar), 1.5);
+        assert_eq!(track.time_to_boundary(3.0, TransitionSync::Bar), 0.0);
+        assert_eq!(track.time_to_boundary(3.0, TransitionSync::Immediate), 0.0);
+    }
+}
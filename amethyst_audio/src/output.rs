@@ -3,12 +3,20 @@
 // We have to use types from this to provide an output iterator type.
 use std::{
     fmt::{Debug, Formatter, Result as FmtResult},
-    io::Cursor,
+    ops::Range,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use cpal::OutputDevices;
 use log::error;
-use rodio::{default_output_device, output_devices, Decoder, Device, Sink, Source as RSource};
+use rodio::{
+    default_output_device,
+    dynamic_mixer::{mixer, DynamicMixer, DynamicMixerController},
+    output_devices,
+    source::Spatial,
+    Device, Sample, Sink, Source as RSource,
+};
 
 use amethyst_core::ecs::World;
 
@@ -17,28 +25,276 @@ use crate::{sink::AudioSink, source::Source, DecoderError};
 /// A speaker(s) through which audio can be played.
 ///
 /// By convention, the default output is stored as a resource in the `World`.
-#[derive(Clone, Eq, PartialEq)]
+///
+/// Besides devices, sounds can be played to a `Output::null` output on machines without audio
+/// hardware, or recorded by a `Output::capture` output in tests.
+#[derive(Clone)]
 pub struct Output {
-    pub(crate) device: Device,
+    backend: Backend,
+}
+
+#[derive(Clone)]
+enum Backend {
+    Device(Device),
+    /// Mixes the sounds in memory when `Output::render` is called.
+    Rendered(Arc<RenderedOutput>),
+}
+
+struct RenderedOutput {
+    controller: Arc<DynamicMixerController<f32>>,
+    state: Mutex<RenderState>,
+    /// Records the rendered samples if set.
+    capture: Option<Capture>,
+}
+
+struct RenderState {
+    mixer: DynamicMixer<f32>,
+    /// Frames of the previous renders not rendered yet, to avoid drifting.
+    remainder: f64,
 }
 
+/// The samples recorded by a capture `Output`, see `Output::capture`.
+///
+/// Each call to `Output::render` records a render frame, which is a game frame when the
+/// `OutputSystem` renders the output.
+#[derive(Clone)]
+pub struct Capture {
+    channels: u16,
+    sample_rate: u32,
+    frame_length: Option<Duration>,
+    recording: Arc<Mutex<Recording>>,
+}
+
+#[derive(Default)]
+struct Recording {
+    samples: Vec<f32>,
+    frames: Vec<Range<usize>>,
+}
+
+impl Capture {
+    fn recording<R>(&self, f: impl FnOnce(&mut Recording) -> R) -> R {
+        f(&mut self.recording.lock().expect("Mutex poisoned"))
+    }
+
+    /// Returns the number of channels of the recorded samples.
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Returns the sample rate of the recorded samples.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Returns the duration the `OutputSystem` renders each game frame, `Time::fixed_time` if
+    /// `None`.
+    pub fn frame_length(&self) -> Option<Duration> {
+        self.frame_length
+    }
+
+    /// Returns all the recorded samples, interleaved by channel.
+    pub fn samples(&self) -> Vec<f32> {
+        self.recording(|recording| recording.samples.clone())
+    }
+
+    /// Returns the number of render frames recorded.
+    pub fn frame_count(&self) -> usize {
+        self.recording(|recording| recording.frames.len())
+    }
+
+    /// Returns the samples recorded during a render frame, interleaved by channel.
+    pub fn frame(&self, frame: usize) -> Option<Vec<f32>> {
+        self.recording(|recording| {
+            let range = recording.frames.get(frame)?.clone();
+            Some(recording.samples[range].to_vec())
+        })
+    }
+
+    /// Returns the highest amplitude recorded during a render frame, 0.0 for silence.
+    pub fn peak(&self, frame: usize) -> Option<f32> {
+        self.frame(frame)
+            .map(|samples| samples.iter().fold(0.0, |peak, s| s.abs().max(peak)))
+    }
+
+    /// Returns the first render frame in which a sample reaches `amplitude`.
+    pub fn first_frame_above(&self, amplitude: f32) -> Option<usize> {
+        (0..self.frame_count()).find(|frame| self.peak(*frame).unwrap_or(0.0) >= amplitude)
+    }
+
+    /// Forgets the recorded samples and frames.
+    pub fn clear(&self) {
+        self.recording(|recording| {
+            recording.samples.clear();
+            recording.frames.clear();
+        });
+    }
+}
+
+impl Debug for Capture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Capture")
+            .field("channels", &self.channels)
+            .field("sample_rate", &self.sample_rate)
+            .field("frame_length", &self.frame_length)
+            .field("frame_count", &self.frame_count())
+            .finish()
+    }
+}
+
+impl PartialEq for Output {
+    fn eq(&self, other: &Output) -> bool {
+        match (&self.backend, &other.backend) {
+            (Backend::Device(a), Backend::Device(b)) => a == b,
+            (Backend::Rendered(a), Backend::Rendered(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Output {}
+
 /// Convenience method for opening the default output device.
 ///
 /// Since most modern hardware features audio output, this implementation fails if a device can't
 /// be initialized. Use an alternative initialization scheme if running on hardware without an
-/// integrated audio chip.
+/// integrated audio chip, like `Output::null`.
 impl Default for Output {
     fn default() -> Self {
         default_output_device()
-            .map(|re| Output { device: re })
+            .map(Output::from_device)
             .expect("No default output device")
     }
 }
 
 impl Output {
+    fn from_device(device: Device) -> Self {
+        Output {
+            backend: Backend::Device(device),
+        }
+    }
+
+    fn rendered(channels: u16, sample_rate: u32, capture: Option<Capture>) -> Self {
+        let (controller, mixer) = mixer(channels, sample_rate);
+        Output {
+            backend: Backend::Rendered(Arc::new(RenderedOutput {
+                controller,
+                state: Mutex::new(RenderState {
+                    mixer,
+                    remainder: 0.0,
+                }),
+                capture,
+            })),
+        }
+    }
+
+    /// Creates an output discarding the sounds, for servers and machines without audio hardware.
+    ///
+    /// Sounds still play, so sinks empty and emitters pick new sounds, as long as the output is
+    /// rendered by the `OutputSystem`.
+    pub fn null() -> Self {
+        Output::rendered(2, 44100, None)
+    }
+
+    /// Creates an output recording the mixed sounds in memory, to test what is played.
+    ///
+    /// The samples are rendered by the `OutputSystem` or `Output::render`, and read with
+    /// `Output::captured`. The `OutputSystem` renders `Time::fixed_time` each frame, so the
+    /// recording doesn't depend on the speed of the machine.
+    pub fn capture(channels: u16, sample_rate: u32) -> Self {
+        Output::capture_frames(channels, sample_rate, None)
+    }
+
+    /// Creates a capture output the `OutputSystem` renders for `frame_length` each frame, see
+    /// `Output::capture`.
+    pub fn capture_with_frame_length(
+        channels: u16,
+        sample_rate: u32,
+        frame_length: Duration,
+    ) -> Self {
+        Output::capture_frames(channels, sample_rate, Some(frame_length))
+    }
+
+    fn capture_frames(channels: u16, sample_rate: u32, frame_length: Option<Duration>) -> Self {
+        let capture = Capture {
+            channels: channels.max(1),
+            sample_rate: sample_rate.max(1),
+            frame_length,
+            recording: Default::default(),
+        };
+        Output::rendered(capture.channels, capture.sample_rate, Some(capture))
+    }
+
+    /// Returns the recorded samples of a capture output.
+    pub fn captured(&self) -> Option<&Capture> {
+        match &self.backend {
+            Backend::Rendered(rendered) => rendered.capture.as_ref(),
+            Backend::Device(_) => None,
+        }
+    }
+
     /// Gets the name of the output
     pub fn name(&self) -> String {
-        self.device.name()
+        match &self.backend {
+            Backend::Device(device) => device.name(),
+            Backend::Rendered(rendered) if rendered.capture.is_some() => "capture".to_string(),
+            Backend::Rendered(_) => "null".to_string(),
+        }
+    }
+
+    /// Mixes `duration` of the sounds of a null or capture output, recording them for a capture
+    /// output. Device outputs play in real time and ignore this.
+    pub fn render(&self, duration: Duration) {
+        let rendered = match &self.backend {
+            Backend::Rendered(rendered) => rendered,
+            Backend::Device(_) => return,
+        };
+        let mut state = rendered.state.lock().expect("Mutex poisoned");
+        let channels = usize::from(state.mixer.channels());
+        let frames = state.remainder
+            + (duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0)
+                * f64::from(state.mixer.sample_rate());
+        state.remainder = frames.fract();
+        let len = frames as usize * channels;
+        let samples = (0..len).map(|_| state.mixer.next().unwrap_or(0.0));
+        match &rendered.capture {
+            Some(capture) => capture.recording(|recording| {
+                let start = recording.samples.len();
+                recording.samples.extend(samples);
+                let end = recording.samples.len();
+                recording.frames.push(start..end);
+            }),
+            None => samples.for_each(drop),
+        }
+    }
+
+    /// Creates a sink playing on this output.
+    pub(crate) fn sink(&self) -> Sink {
+        match &self.backend {
+            Backend::Device(device) => Sink::new(device),
+            Backend::Rendered(rendered) => {
+                let (sink, output) = Sink::new_idle();
+                rendered.controller.add(output);
+                sink
+            }
+        }
+    }
+
+    /// Creates a sink playing on this output, panning and attenuating its sounds from their
+    /// positions.
+    pub(crate) fn spatial_sink(
+        &self,
+        emitter_position: [f32; 3],
+        left_ear: [f32; 3],
+        right_ear: [f32; 3],
+    ) -> SpatialSink {
+        SpatialSink {
+            sink: self.sink(),
+            positions: Arc::new(Mutex::new(SoundPositions {
+                emitter_position,
+                left_ear,
+                right_ear,
+            })),
+        }
     }
 
     /// Play a sound once.  A volume of 1.0 is unchanged, while 0.0 is silent.
@@ -73,13 +329,9 @@ impl Output {
         volume: f32,
         n: u16,
     ) -> Result<(), DecoderError> {
-        let sink = Sink::new(&self.device);
+        let sink = self.sink();
         for _ in 0..n {
-            sink.append(
-                Decoder::new(Cursor::new(source.clone()))
-                    .map_err(|_| DecoderError)?
-                    .amplify(volume),
-            );
+            sink.append(source.samples()?.amplify(volume));
         }
         sink.detach();
         Ok(())
@@ -94,6 +346,72 @@ impl Debug for Output {
     }
 }
 
+struct SoundPositions {
+    emitter_position: [f32; 3],
+    left_ear: [f32; 3],
+    right_ear: [f32; 3],
+}
+
+/// A sink panning and attenuating its sounds from the positions of an emitter and two ears, like
+/// rodio's `SpatialSink` but playing on any `Output`.
+pub(crate) struct SpatialSink {
+    sink: Sink,
+    positions: Arc<Mutex<SoundPositions>>,
+}
+
+impl SpatialSink {
+    fn positions(&self) -> std::sync::MutexGuard<'_, SoundPositions> {
+        self.positions.lock().expect("Mutex poisoned")
+    }
+
+    pub(crate) fn set_emitter_position(&self, position: [f32; 3]) {
+        self.positions().emitter_position = position;
+    }
+
+    pub(crate) fn set_left_ear_position(&self, position: [f32; 3]) {
+        self.positions().left_ear = position;
+    }
+
+    pub(crate) fn set_right_ear_position(&self, position: [f32; 3]) {
+        self.positions().right_ear = position;
+    }
+
+    pub(crate) fn set_volume(&self, volume: f32) {
+        self.sink.set_volume(volume);
+    }
+
+    /// Drops the sink, letting its sounds play until they end.
+    pub(crate) fn detach(self) {
+        self.sink.detach();
+    }
+
+    pub(crate) fn append<S>(&self, source: S)
+    where
+        S: RSource + Send + 'static,
+        S::Item: Sample + Send + Debug,
+    {
+        let positions = self.positions.clone();
+        let source = {
+            let current = self.positions();
+            Spatial::new(
+                source,
+                current.emitter_position,
+                current.left_ear,
+                current.right_ear,
+            )
+        }
+        .periodic_access(Duration::from_millis(10), move |spatial| {
+            let positions = positions.lock().expect("Mutex poisoned");
+            spatial.set_positions(
+                positions.emitter_position,
+                positions.left_ear,
+                positions.right_ear,
+            );
+        });
+        self.sink.append(source);
+    }
+}
+
 /// An iterator over outputs
 #[allow(missing_debug_implementations)]
 pub struct OutputIterator {
@@ -104,13 +422,13 @@ impl Iterator for OutputIterator {
     type Item = Output;
 
     fn next(&mut self) -> Option<Output> {
-        self.input.next().map(|re| Output { device: re })
+        self.input.next().map(Output::from_device)
     }
 }
 
 /// Get the default output, returns none if no outputs are available.
 pub fn default_output() -> Option<Output> {
-    default_output_device().map(|re| Output { device: re })
+    default_output_device().map(Output::from_device)
 }
 
 /// Get a list of outputs available to the system.
@@ -121,8 +439,15 @@ pub fn outputs() -> OutputIterator {
 }
 
 /// Initialize default output
+///
+/// An `Output` already in the `World`, like a null or capture output, is used instead of the
+/// default output device.
 pub fn init_output(world: &mut World) {
-    if let Some(o) = default_output() {
+    let output = world
+        .try_fetch::<Output>()
+        .map(|output| (*output).clone())
+        .or_else(default_output);
+    if let Some(o) = output {
         world
             .entry::<AudioSink>()
             .or_insert_with(|| AudioSink::new(&o));
@@ -141,6 +466,69 @@ mod tests {
         std::{fs::File, io::Read, vec::Vec},
     };
 
+    use std::time::Duration;
+
+    use rodio::{buffer::SamplesBuffer, Source as RSource};
+
+    #[test]
+    fn test_capture() {
+        use crate::output::Output;
+
+        let output = Output::capture(1, 1000);
+        output.render(Duration::from_secs(1));
+        let sink = output.sink();
+        sink.append(SamplesBuffer::new(1, 1000, vec![0.5f32; 500]).amplify(0.5));
+        sink.detach();
+        output.render(Duration::from_millis(500));
+        output.render(Duration::from_millis(500));
+        let capture = output.captured().unwrap();
+        assert_eq!(capture.frame_count(), 3);
+        assert_eq!(capture.frame(1).map(|samples| samples.len()), Some(500));
+        assert_eq!(capture.peak(0), Some(0.0));
+        assert_eq!(capture.peak(1), Some(0.25));
+        assert_eq!(capture.peak(2), Some(0.0));
+        assert_eq!(capture.first_frame_above(0.2), Some(1));
+    }
+
+    #[test]
+    fn test_capture_frame_length() {
+        use amethyst_core::{
+            ecs::{RunNow, World, WorldExt},
+            timing::Time,
+        };
+
+        use crate::{output::Output, systems::OutputSystem};
+
+        let mut world = World::new();
+        let mut time = Time::default();
+        time.set_fixed_time(Duration::from_millis(20));
+        time.set_delta_time(Duration::from_millis(35));
+        world.insert(time);
+
+        // Captures render the same length whatever the duration of the frame.
+        let fixed = Output::capture(1, 1000);
+        world.insert(fixed.clone());
+        OutputSystem.run_now(&world);
+        let configured = Output::capture_with_frame_length(1, 1000, Duration::from_millis(10));
+        world.insert(configured.clone());
+        OutputSystem.run_now(&world);
+
+        let frame_len = |output: &Output| output.captured().unwrap().frame(0).unwrap().len();
+        assert_eq!(frame_len(&fixed), 20);
+        assert_eq!(frame_len(&configured), 10);
+    }
+
+    #[test]
+    fn test_null() {
+        use crate::output::Output;
+
+        let output = Output::null();
+        assert!(output.captured().is_none());
+        assert_eq!(output.name(), "null");
+        assert_ne!(output, Output::null());
+        assert_eq!(output, output.clone());
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     fn test_play_wav() {
@@ -184,7 +572,7 @@ mod tests {
         f.read_to_end(&mut buffer).unwrap();
 
         // Create a Source from those bytes
-        let src = Source { bytes: buffer };
+        let src = Source::new(buffer);
 
         // Set volume and number of times to play
         let vol: f32 = 4.0;
@@ -0,0 +1,711 @@
+//! Control of the sounds played by an `AudioEmitter`.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc::{channel, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use rodio::{Sample, Source};
+
+use crate::metadata::frames_duration;
+
+/// Number of samples played between two reads of the controls of a playback.
+const POLL_PERIOD: u32 = 256;
+
+/// A range of a sound played in a loop, in sample frames (one sample per channel).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopPoints {
+    /// The frame playback jumps back to.
+    pub start: u64,
+    /// The frame at which playback jumps back to `start`, or `None` for the end of the sound.
+    pub end: Option<u64>,
+}
+
+impl LoopPoints {
+    /// Loops the whole sound.
+    pub fn whole() -> Self {
+        LoopPoints {
+            start: 0,
+            end: None,
+        }
+    }
+}
+
+/// A sound a `Playback` can play again from any position, to jump back to the loop start.
+pub(crate) trait Rewind: Source + Sized + Send + 'static
+where
+    Self::Item: Sample,
+{
+    /// Returns the sound from `position`, in samples of all channels.
+    fn rewind(&self, position: u64) -> Self;
+
+    /// Returns true if rewinding doesn't decode the sound again, so it can rewind while it plays.
+    /// Other sounds are rewound with a `Seek`.
+    fn rewinds_instantly(&self) -> bool;
+}
+
+/// A sound decoded up to a position on another thread, so the threads playing sounds don't
+/// decode the skipped samples.
+pub(crate) struct Seek<I> {
+    position: u64,
+    /// Locked so that voices holding a `Seek` can be shared between threads.
+    receiver: Mutex<Receiver<I>>,
+}
+
+impl<I> Seek<I>
+where
+    I: Rewind,
+    I::Item: Sample,
+{
+    /// Skips the samples of `input`, at the start of the sound, up to `position`.
+    pub(crate) fn new(mut input: I, position: u64) -> Self {
+        let (sender, receiver) = channel();
+        if position == 0 {
+            let _ = sender.send(input);
+        } else {
+            thread::spawn(move || {
+                for _ in 0..position {
+                    if input.next().is_none() {
+                        break;
+                    }
+                }
+                let _ = sender.send(input);
+            });
+        }
+        Seek {
+            position,
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    /// Returns the position the sound is rewound to, in samples of all channels.
+    pub(crate) fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the rewound sound if it's ready.
+    pub(crate) fn try_take(&self) -> Option<I> {
+        self.receiver.lock().ok()?.try_recv().ok()
+    }
+
+    /// Waits for the rewound sound.
+    pub(crate) fn wait(self) -> Option<I> {
+        self.receiver.into_inner().ok()?.recv().ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Controls {
+    volume: f32,
+    /// Time to reach `volume` from the current volume.
+    fade: Duration,
+    /// Starts the fade from silence.
+    from_silence: bool,
+    /// Stops the sound once `volume` is reached.
+    stop_at_volume: bool,
+    /// Incremented on each change of the volume, so the playback starts a new fade.
+    generation: u64,
+    pitch: f32,
+    /// Pitch multiplier of the Doppler effect, set by the `AudioSystem`.
+    doppler: f32,
+    loop_points: Option<LoopPoints>,
+}
+
+#[derive(Debug)]
+struct PlaybackState {
+    stopped: AtomicBool,
+    paused: AtomicBool,
+    finished: AtomicBool,
+    controls: Mutex<Controls>,
+    /// Identifies the playback currently playing the sound, the others stop without finishing it.
+    instance: AtomicUsize,
+    /// Position of the playback in the sound, in samples of all channels.
+    position: AtomicU64,
+    /// Position of the playback in the sound, in nanoseconds.
+    time: AtomicU64,
+}
+
+/// Controls a sound played with `AudioEmitter::play`.
+///
+/// Changes apply within a few milliseconds, once the sound reads them.
+#[derive(Debug, Clone)]
+pub struct PlaybackHandle {
+    state: Arc<PlaybackState>,
+}
+
+impl PlaybackHandle {
+    pub(crate) fn new() -> Self {
+        PlaybackHandle {
+            state: Arc::new(PlaybackState {
+                stopped: AtomicBool::new(false),
+                paused: AtomicBool::new(false),
+                finished: AtomicBool::new(false),
+                controls: Mutex::new(Controls {
+                    volume: 1.0,
+                    fade: Duration::from_secs(0),
+                    from_silence: false,
+                    stop_at_volume: false,
+                    generation: 0,
+                    pitch: 1.0,
+                    doppler: 1.0,
+                    loop_points: None,
+                }),
+                instance: AtomicUsize::new(0),
+                position: AtomicU64::new(0),
+                time: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    fn controls<R>(&self, f: impl FnOnce(&mut Controls) -> R) -> R {
+        f(&mut self.state.controls.lock().expect("Mutex poisoned"))
+    }
+
+    /// Stops the sound, it can't be resumed.
+    pub fn stop(&self) {
+        self.state.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Pauses the sound, this can be resumed with `PlaybackHandle::resume`.
+    pub fn pause(&self) {
+        self.state.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Resumes the sound if it was paused.
+    pub fn resume(&self) {
+        self.state.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns true if the sound is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if the sound ended, was stopped or was dropped with its emitter.
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::Relaxed)
+    }
+
+    /// Returns the position of the sound, which jumps back to the loop start when it loops.
+    ///
+    /// The position is counted in samples while the sound plays, so it follows the pitch and
+    /// pauses of the sound.
+    pub fn position(&self) -> Duration {
+        Duration::from_nanos(self.state.time.load(Ordering::Relaxed))
+    }
+
+    /// Returns the volume of the sound, or the volume it fades to.
+    pub fn volume(&self) -> f32 {
+        self.controls(|controls| controls.volume)
+    }
+
+    /// Sets the volume of the sound. A volume of 1.0 is unchanged, while 0.0 is silent.
+    pub fn set_volume(&self, volume: f32) {
+        self.fade(volume, Duration::from_secs(0), false, false);
+    }
+
+    /// Returns the pitch of the sound.
+    pub fn pitch(&self) -> f32 {
+        self.controls(|controls| controls.pitch)
+    }
+
+    /// Sets the pitch of the sound, which also changes its speed. 1.0 is unchanged, 2.0 is an
+    /// octave higher.
+    pub fn set_pitch(&self, pitch: f32) {
+        self.controls(|controls| controls.pitch = pitch.max(0.01));
+    }
+
+    pub(crate) fn set_doppler(&self, doppler: f32) {
+        self.controls(|controls| controls.doppler = doppler.max(0.01));
+    }
+
+    /// Returns the range of the sound played in a loop.
+    pub fn loop_points(&self) -> Option<LoopPoints> {
+        self.controls(|controls| controls.loop_points)
+    }
+
+    /// Plays a range of the sound in a loop, or plays it to the end if `None`.
+    pub fn set_loop(&self, loop_points: Option<LoopPoints>) {
+        self.controls(|controls| controls.loop_points = loop_points);
+    }
+
+    /// Changes the volume progressively over `duration`.
+    pub fn fade_to(&self, volume: f32, duration: Duration) {
+        self.fade(volume, duration, false, false);
+    }
+
+    /// Fades the sound from silence to its volume over `duration`.
+    pub fn fade_in(&self, duration: Duration) {
+        let volume = self.volume();
+        self.fade(volume, duration, true, false);
+    }
+
+    /// Fades the sound to silence over `duration`, then stops it.
+    pub fn fade_out(&self, duration: Duration) {
+        self.fade(0.0, duration, false, true);
+    }
+
+    /// Returns true if the sound was stopped or is fading out to stop.
+    pub(crate) fn is_stopping(&self) -> bool {
+        self.state.stopped.load(Ordering::Relaxed) || self.controls(|c| c.stop_at_volume)
+    }
+
+    pub(crate) fn finish(&self) {
+        self.state.finished.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops the current playback of the sound without finishing it, returning its position.
+    pub(crate) fn detach_playback(&self) -> u64 {
+        self.state.instance.fetch_add(1, Ordering::SeqCst);
+        self.state.position.load(Ordering::Relaxed)
+    }
+
+    fn fade(&self, volume: f32, duration: Duration, from_silence: bool, stop_at_volume: bool) {
+        self.controls(|controls| {
+            controls.volume = volume;
+            controls.fade = duration;
+            controls.from_silence = from_silence;
+            controls.stop_at_volume = stop_at_volume;
+            controls.generation += 1;
+        });
+    }
+}
+
+/// Plays a source controlled by a `PlaybackHandle`.
+pub(crate) struct Playback<I>
+where
+    I: Rewind,
+    I::Item: Sample,
+{
+    input: I,
+    /// Position in the source, in samples of all channels.
+    position: u64,
+    /// The sound rewound to the loop start ahead of time, if it doesn't rewind instantly.
+    loop_start: Option<Seek<I>>,
+    state: Arc<PlaybackState>,
+    generation: u64,
+    pitch: f32,
+    loop_points: Option<LoopPoints>,
+    volume: f32,
+    target_volume: f32,
+    volume_step: f32,
+    stop_at_volume: bool,
+    until_poll: u32,
+    instance: usize,
+}
+
+impl<I> Playback<I>
+where
+    I: Rewind,
+    I::Item: Sample,
+{
+    pub(crate) fn new(input: I, handle: &PlaybackHandle) -> Self {
+        Playback::resume(input, handle, 0)
+    }
+
+    /// Plays the sound from `position`, in samples of all channels, replacing the previous
+    /// playback of the handle.
+    ///
+    /// `input` is already at `position`, rewind it with a `Seek` if it doesn't rewind instantly.
+    pub(crate) fn resume(input: I, handle: &PlaybackHandle, position: u64) -> Self {
+        Playback {
+            input,
+            position,
+            loop_start: None,
+            state: handle.state.clone(),
+            // Makes the first poll apply the volume.
+            generation: std::u64::MAX,
+            pitch: 1.0,
+            loop_points: None,
+            volume: 1.0,
+            target_volume: 1.0,
+            volume_step: 0.0,
+            stop_at_volume: false,
+            until_poll: 0,
+            instance: handle.state.instance.fetch_add(1, Ordering::SeqCst) + 1,
+        }
+    }
+
+    fn is_current(&self) -> bool {
+        self.state.instance.load(Ordering::Relaxed) == self.instance
+    }
+
+    /// Reads the controls without blocking, they're read again later if the handle holds them.
+    fn poll(&mut self) {
+        if self.loop_start.is_none() {
+            self.loop_start = self.seek_loop_start();
+        }
+        self.state.position.store(self.position, Ordering::Relaxed);
+        let frames = self.position / u64::from(self.input.channels().max(1));
+        let time = frames_duration(frames, self.input.sample_rate());
+        self.state
+            .time
+            .store(time.as_nanos() as u64, Ordering::Relaxed);
+        let controls = match self.state.controls.try_lock() {
+            Ok(controls) => controls,
+            Err(_) => return,
+        };
+        self.pitch = controls.pitch * controls.doppler;
+        if controls.loop_points != self.loop_points {
+            self.loop_points = controls.loop_points;
+            self.loop_start = None;
+        }
+        if controls.generation != self.generation {
+            self.generation = controls.generation;
+            if controls.from_silence {
+                self.volume = 0.0;
+            }
+            self.target_volume = controls.volume;
+            self.stop_at_volume = controls.stop_at_volume;
+            let samples = controls.fade.as_secs() as f32
+                + controls.fade.subsec_nanos() as f32 / 1_000_000_000.0;
+            let samples = samples * self.input.sample_rate() as f32 * self.input.channels() as f32;
+            self.volume_step = if samples >= 1.0 {
+                (self.target_volume - self.volume).abs() / samples
+            } else {
+                std::f32::INFINITY
+            };
+        }
+    }
+
+    /// Rewinds the sound to the loop start on another thread, if it doesn't rewind instantly.
+    fn seek_loop_start(&self) -> Option<Seek<I>> {
+        match self.loop_points {
+            Some(loop_points) if !self.input.rewinds_instantly() => Some(Seek::new(
+                self.input.rewind(0),
+                loop_points.start * u64::from(self.input.channels()),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Jumps to the loop start, returning false if the sound doesn't loop.
+    ///
+    /// Sounds which don't rewind instantly continue from the loop start rewound ahead of time. A
+    /// short loop far into the sound can wait for it.
+    fn restart(&mut self) -> bool {
+        let loop_points = match self.loop_points {
+            Some(loop_points) => loop_points,
+            None => return false,
+        };
+        let start = loop_points.start * u64::from(self.input.channels());
+        self.input = if self.input.rewinds_instantly() {
+            self.input.rewind(start)
+        } else {
+            // The loop start is only rewound here if the loop changed since the last poll.
+            match self.loop_start.take() {
+                Some(seek) if seek.position() == start => {
+                    seek.wait().unwrap_or_else(|| self.input.rewind(start))
+                }
+                _ => self.input.rewind(start),
+            }
+        };
+        self.position = start;
+        self.loop_start = self.seek_loop_start();
+        true
+    }
+
+    /// Returns the next sample of the input.
+    fn next_sample(&mut self) -> Option<I::Item> {
+        self.input.next()
+    }
+
+    fn loop_end(&self) -> Option<u64> {
+        self.loop_points
+            .and_then(|loop_points| loop_points.end)
+            .map(|end| end * u64::from(self.input.channels()))
+    }
+
+    fn finish(&mut self) -> Option<I::Item> {
+        self.state.finished.store(true, Ordering::Relaxed);
+        None
+    }
+}
+
+impl<I> Iterator for Playback<I>
+where
+    I: Rewind,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if !self.is_current() {
+            return None;
+        }
+        if self.until_poll == 0 {
+            self.poll();
+            self.until_poll = POLL_PERIOD;
+        }
+        self.until_poll -= 1;
+
+        if self.state.stopped.load(Ordering::Relaxed) || self.state.finished.load(Ordering::Relaxed)
+        {
+            return self.finish();
+        }
+        if self.state.paused.load(Ordering::Relaxed) {
+            return Some(I::Item::zero_value());
+        }
+
+        if self
+            .loop_end()
+            .map(|end| self.position >= end)
+            .unwrap_or(false)
+        {
+            self.restart();
+        }
+        let sample = match self.next_sample() {
+            Some(sample) => Some(sample),
+            None if self.restart() => self.next_sample(),
+            None => None,
+        };
+        let sample = match sample {
+            Some(sample) => sample,
+            None => return self.finish(),
+        };
+        self.position += 1;
+
+        if self.volume != self.target_volume {
+            if (self.target_volume - self.volume).abs() <= self.volume_step {
+                self.volume = self.target_volume;
+            } else if self.target_volume > self.volume {
+                self.volume += self.volume_step;
+            } else {
+                self.volume -= self.volume_step;
+            }
+        } else if self.stop_at_volume {
+            self.state.stopped.store(true, Ordering::Relaxed);
+        }
+        Some(sample.amplify(self.volume))
+    }
+}
+
+impl<I> Source for Playback<I>
+where
+    I: Rewind,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        let until_poll = self.until_poll.max(1) as usize;
+        // Short frames make the pitch read again after each poll.
+        let len = match self.input.current_frame_len() {
+            // The source ended, but jumps back to the loop start.
+            Some(0) if self.loop_points.is_some() => until_poll,
+            Some(len) => len.min(until_poll),
+            None => until_poll,
+        };
+        match self.loop_end() {
+            Some(end) if end > self.position => Some(len.min((end - self.position) as usize)),
+            _ => Some(len),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        ((self.input.sample_rate() as f32 * self.pitch) as u32).max(1)
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<I> Drop for Playback<I>
+where
+    I: Rewind,
+    I::Item: Sample,
+{
+    fn drop(&mut self) {
+        if self.is_current() {
+            self.state.finished.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use rodio::Source;
+
+    use crate::playback::{LoopPoints, Playback, PlaybackHandle, Rewind, Seek};
+
+    /// A mono sound at 4 Hz, counting how many times it rewinds.
+    struct Sound {
+        samples: Arc<[i16]>,
+        position: usize,
+        instant: bool,
+        rewinds: Arc<AtomicUsize>,
+    }
+
+    impl Sound {
+        fn new(samples: Vec<i16>, instant: bool) -> Self {
+            Sound {
+                samples: samples.into(),
+                position: 0,
+                instant,
+                rewinds: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+    }
+
+    impl Iterator for Sound {
+        type Item = i16;
+
+        fn next(&mut self) -> Option<i16> {
+            let sample = self.samples.get(self.position).cloned();
+            self.position += 1;
+            sample
+        }
+    }
+
+    impl Source for Sound {
+        fn current_frame_len(&self) -> Option<usize> {
+            Some(self.samples.len().saturating_sub(self.position))
+        }
+
+        fn channels(&self) -> u16 {
+            1
+        }
+
+        fn sample_rate(&self) -> u32 {
+            4
+        }
+
+        fn total_duration(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    impl Rewind for Sound {
+        fn rewind(&self, position: u64) -> Self {
+            self.rewinds.fetch_add(1, Ordering::Relaxed);
+            Sound {
+                samples: self.samples.clone(),
+                position: position as usize,
+                instant: self.instant,
+                rewinds: self.rewinds.clone(),
+            }
+        }
+
+        fn rewinds_instantly(&self) -> bool {
+            self.instant
+        }
+    }
+
+    fn playback(handle: &PlaybackHandle) -> Playback<Sound> {
+        Playback::new(Sound::new(vec![1000i16; 4], true), handle)
+    }
+
+    #[test]
+    fn test_volume_and_stop() {
+        let handle = PlaybackHandle::new();
+        handle.set_volume(0.5);
+        let mut playback = playback(&handle);
+        assert_eq!(playback.next(), Some(500));
+        handle.stop();
+        playback.until_poll = 0;
+        assert_eq!(playback.next(), None);
+        assert!(handle.is_finished());
+    }
+
+    #[test]
+    fn test_loop() {
+        let handle = PlaybackHandle::new();
+        handle.set_loop(Some(LoopPoints {
+            start: 1,
+            end: Some(3),
+        }));
+        let mut playback = playback(&handle);
+        assert_eq!(playback.by_ref().take(6).count(), 6);
+        assert!(!handle.is_finished());
+    }
+
+    #[test]
+    fn test_position() {
+        let handle = PlaybackHandle::new();
+        handle.set_loop(Some(LoopPoints {
+            start: 1,
+            end: Some(3),
+        }));
+        let mut playback = Playback::new(Sound::new(vec![1, 2, 3, 4], true), &handle);
+        assert_eq!(playback.by_ref().take(4).count(), 4);
+        playback.until_poll = 0;
+        assert_eq!(playback.next(), Some(3));
+        // Wrapped at the loop end, 2 samples at 4 Hz.
+        assert_eq!(handle.position(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_streamed_loop() {
+        let handle = PlaybackHandle::new();
+        handle.set_loop(Some(LoopPoints {
+            start: 1,
+            end: Some(3),
+        }));
+        let playback = Playback::new(Sound::new(vec![1, 2, 3, 4], false), &handle);
+        assert_eq!(
+            playback.take(8).collect::<Vec<_>>(),
+            vec![1, 2, 3, 2, 3, 2, 3, 2]
+        );
+
+        let handle = PlaybackHandle::new();
+        handle.set_loop(Some(LoopPoints::whole()));
+        let sound = Sound::new(vec![1, 2, 3], false);
+        let rewinds = sound.rewinds.clone();
+        let playback = Playback::new(sound, &handle);
+        assert_eq!(
+            playback.take(7).collect::<Vec<_>>(),
+            vec![1, 2, 3, 1, 2, 3, 1]
+        );
+        // The loop start is rewound ahead of each loop, nothing is recorded.
+        assert_eq!(rewinds.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_seek() {
+        let seek = Seek::new(Sound::new(vec![1, 2, 3, 4], false), 2);
+        assert_eq!(seek.position(), 2);
+        let mut sound = seek.wait().unwrap();
+        assert_eq!(sound.next(), Some(3));
+        let seek = Seek::new(Sound::new(vec![1, 2], false), 0);
+        assert_eq!(seek.try_take().and_then(|mut sound| sound.next()), Some(1));
+    }
+
+    #[test]
+    fn test_fade_in() {
+        let handle = PlaybackHandle::new();
+        // Over the 4 samples of the sound.
+        handle.fade_in(Duration::from_secs(1));
+        let samples = playback(&handle).collect::<Vec<_>>();
+        assert_eq!(samples, vec![250, 500, 750, 1000]);
+        assert!(handle.is_finished());
+    }
+
+    #[test]
+    fn test_resume() {
+        let handle = PlaybackHandle::new();
+        let mut first = Playback::new(Sound::new(vec![1, 2, 3, 4], false), &handle);
+        assert_eq!(first.next(), Some(1));
+        let second = Playback::resume(first.input.rewind(2), &handle, 2);
+        assert_eq!(first.next(), None);
+        drop(first);
+        assert!(!handle.is_finished());
+        assert_eq!(second.collect::<Vec<_>>(), vec![3, 4]);
+        assert!(handle.is_finished());
+    }
+}
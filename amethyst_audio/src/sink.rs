@@ -1,8 +1,13 @@
-use std::io::Cursor;
+use rodio::Sink;
 
-use rodio::{Decoder, Sink};
+use std::sync::Arc;
 
-use crate::{output::Output, source::Source, DecoderError};
+use crate::{
+    effects::{Effect, EffectChain, EffectsSource},
+    output::Output,
+    source::Source,
+    DecoderError,
+};
 
 /// This structure provides a way to programmatically pick and play music.
 // TODO: This needs a proper debug implementeation. This should probably propigate up to a TODO
@@ -10,20 +15,28 @@ use crate::{output::Output, source::Source, DecoderError};
 #[allow(missing_debug_implementations)]
 pub struct AudioSink {
     sink: Sink,
+    volume: f32,
+    bus: Option<String>,
+    effect_chain: Arc<EffectChain>,
 }
 
 impl AudioSink {
     /// Creates a new `AudioSink` using the given audio output.
     pub fn new(output: &Output) -> AudioSink {
         AudioSink {
-            sink: Sink::new(&output.device),
+            sink: output.sink(),
+            volume: 1.0,
+            bus: None,
+            effect_chain: Default::default(),
         }
     }
 
     /// Adds a source to the sink's queue of music to play.
     pub fn append(&self, source: &Source) -> Result<(), DecoderError> {
-        self.sink
-            .append(Decoder::new(Cursor::new(source.clone())).map_err(|_| DecoderError)?);
+        self.sink.append(EffectsSource::new(
+            source.samples()?,
+            self.effect_chain.clone(),
+        ));
         Ok(())
     }
 
@@ -33,15 +46,38 @@ impl AudioSink {
     }
 
     /// Retrieves the volume of the sink, between 0.0 and 1.0;
+    ///
+    /// The volume of the bus of the sink is applied on top of it, see `Mixer`.
     pub fn volume(&self) -> f32 {
-        self.sink.volume()
+        self.volume
     }
 
     /// Sets the volume of the sink.
     pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
         self.sink.set_volume(volume);
     }
 
+    /// Returns the mixer bus of the sink, `None` for the master bus.
+    pub fn bus(&self) -> Option<&str> {
+        self.bus.as_ref().map(String::as_str)
+    }
+
+    /// Routes the sink to a mixer bus, `None` for the master bus.
+    pub fn set_bus(&mut self, bus: Option<String>) {
+        self.bus = bus;
+    }
+
+    /// Applies the volume of the bus of the sink.
+    pub(crate) fn apply_bus_volume(&self, bus_volume: f32) {
+        self.sink.set_volume(self.volume * bus_volume);
+    }
+
+    /// Applies the effects of the bus of the sink.
+    pub(crate) fn apply_bus_effects(&self, effects: Vec<Effect>) {
+        self.effect_chain.set(effects);
+    }
+
     /// Resumes playback of a paused sink. Has no effect if this sink was never paused.
     pub fn play(&self) {
         self.sink.play();
@@ -85,7 +121,7 @@ mod tests {
         f.read_to_end(&mut buffer).unwrap();
 
         // Create a Source from those bytes
-        let src = Source { bytes: buffer };
+        let src = Source::new(buffer);
 
         // Create a Output and AudioSink
         let output = Output::default();
@@ -1,26 +1,167 @@
 //! Provides structures used to load audio files.
 //!
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    io::Cursor,
+    sync::Arc,
+    time::Duration,
+};
+
 use amethyst_assets::{
     Asset, AssetStorage, Handle, Loader, PrefabData, ProcessableAsset, ProcessingState,
 };
 use amethyst_core::ecs::prelude::{Entity, Read, ReadExpect, VecStorage};
 use amethyst_error::Error;
+use rodio::{Decoder, Source as RSource};
+use serde::{Deserialize, Serialize};
 
-use crate::formats::AudioData;
+use crate::{
+    formats::AudioData,
+    metadata::{frames_duration, AudioMetadata},
+    playback::Rewind,
+    DecoderError,
+};
 
 /// A handle to a source asset.
 pub type SourceHandle = Handle<Source>;
 
+/// When the samples of a `Source` are decoded, chosen when loading it with `WithDecodeMode`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Default)]
+pub enum DecodeMode {
+    /// Decodes the sound each time it plays, while it plays. Best for long sounds like music.
+    #[default]
+    Stream,
+    /// Decodes the whole sound once when it's loaded. Best for short sounds played often.
+    Preload,
+    /// Preloads sounds up to this number of seconds long, and streams longer ones.
+    Auto(f32),
+}
+
+impl DecodeMode {
+    /// Returns how long sounds can be to be preloaded, in seconds, `None` if they're streamed.
+    pub(crate) fn max_preload_seconds(self) -> Option<f32> {
+        match self {
+            DecodeMode::Stream => None,
+            DecodeMode::Preload => Some(std::f32::INFINITY),
+            DecodeMode::Auto(seconds) => Some(seconds.max(0.0)),
+        }
+    }
+}
+
+/// The samples of a preloaded `Source`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DecodedSamples {
+    channels: u16,
+    sample_rate: u32,
+    samples: Arc<[i16]>,
+}
+
+impl DecodedSamples {
+    /// Decodes the samples of an audio file, if it's up to `max_seconds` long.
+    pub(crate) fn decode(
+        bytes: &SharedBytes,
+        max_seconds: Option<f32>,
+    ) -> Result<Option<Self>, DecoderError> {
+        let decoder = Decoder::new(Cursor::new(bytes.clone())).map_err(|_| DecoderError)?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        let max_samples = max_seconds.map(|seconds| {
+            ((seconds * sample_rate as f32) as usize).saturating_mul(usize::from(channels))
+        });
+        let mut samples = Vec::new();
+        for sample in decoder {
+            if max_samples.map_or(false, |max| samples.len() >= max) {
+                return Ok(None);
+            }
+            samples.push(sample);
+        }
+        Ok(Some(DecodedSamples {
+            channels,
+            sample_rate,
+            samples: samples.into(),
+        }))
+    }
+}
+
 /// A loaded audio file
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Source {
-    /// The bytes of this audio source.
-    pub bytes: Vec<u8>,
+    /// The bytes of this audio source, shared by the sounds playing it.
+    bytes: SharedBytes,
+    decoded: Option<DecodedSamples>,
+    metadata: Option<AudioMetadata>,
+}
+
+impl Source {
+    /// Creates a source from the bytes of an audio file, decoded while it plays.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Source::streamed(bytes.into())
+    }
+
+    fn streamed(bytes: SharedBytes) -> Self {
+        Source {
+            bytes,
+            decoded: None,
+            metadata: None,
+        }
+    }
+
+    /// Creates a source from the bytes of an audio file, decoding them according to `mode`.
+    ///
+    /// This will return an Error if the bytes can't be decoded.
+    pub fn with_decode_mode(bytes: Vec<u8>, mode: DecodeMode) -> Result<Self, DecoderError> {
+        Source::decode(bytes.into(), mode)
+    }
+
+    fn decode(bytes: SharedBytes, mode: DecodeMode) -> Result<Self, DecoderError> {
+        let decoded = match mode.max_preload_seconds() {
+            Some(max_seconds) => DecodedSamples::decode(&bytes, Some(max_seconds))?,
+            None => None,
+        };
+        Ok(Source {
+            bytes,
+            decoded,
+            metadata: None,
+        })
+    }
+
+    /// Returns the bytes of the audio file.
+    pub fn bytes(&self) -> &[u8] {
+        self.bytes.as_ref()
+    }
+
+    /// Returns the metadata read when the source was loaded as an asset.
+    pub fn metadata(&self) -> Option<&AudioMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Returns true if the samples of this source were decoded when it was loaded.
+    pub fn is_preloaded(&self) -> bool {
+        self.decoded.is_some()
+    }
+
+    /// Returns the samples of this source, from memory if it's preloaded or decoded from its bytes
+    /// otherwise.
+    pub(crate) fn samples(&self) -> Result<SourceSamples, DecoderError> {
+        Ok(match &self.decoded {
+            Some(decoded) => SourceSamples::Preloaded {
+                decoded: decoded.clone(),
+                position: 0,
+            },
+            None => SourceSamples::Stream {
+                decoder: Box::new(
+                    Decoder::new(Cursor::new(self.bytes.clone())).map_err(|_| DecoderError)?,
+                ),
+                bytes: self.bytes.clone(),
+                duration: self.metadata.as_ref().map(|metadata| metadata.duration),
+            },
+        })
+    }
 }
 
 impl AsRef<[u8]> for Source {
     fn as_ref(&self) -> &[u8] {
-        &self.bytes
+        self.bytes()
     }
 }
 
@@ -31,8 +172,166 @@ impl Asset for Source {
 }
 
 impl ProcessableAsset for Source {
-    fn process(data: AudioData) -> Result<ProcessingState<Source>, Error> {
-        Ok(ProcessingState::Loaded(Source { bytes: data.0 }))
+    fn process(mut data: AudioData) -> Result<ProcessingState<Source>, Error> {
+        data.preload()?;
+        Ok(ProcessingState::Loaded(Source {
+            bytes: data.bytes,
+            decoded: data.samples,
+            metadata: data.metadata,
+        }))
+    }
+}
+
+/// The bytes of a `Source`, shared by the decoders playing it.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct SharedBytes(Arc<[u8]>);
+
+impl From<Vec<u8>> for SharedBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SharedBytes(bytes.into())
+    }
+}
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Debug for SharedBytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "SharedBytes({} bytes)", self.0.len())
+    }
+}
+
+/// The samples played from a `Source`.
+pub(crate) enum SourceSamples {
+    Preloaded {
+        decoded: DecodedSamples,
+        position: usize,
+    },
+    Stream {
+        bytes: SharedBytes,
+        decoder: Box<Decoder<Cursor<SharedBytes>>>,
+        /// The duration read from the metadata of the source, if it was loaded as an asset.
+        duration: Option<Duration>,
+    },
+}
+
+impl SourceSamples {
+    /// Returns the sound of these samples, to play it again without keeping a decoder.
+    pub(crate) fn origin(&self) -> SourceOrigin {
+        match self {
+            SourceSamples::Preloaded { decoded, .. } => SourceOrigin::Preloaded(decoded.clone()),
+            SourceSamples::Stream {
+                bytes, duration, ..
+            } => SourceOrigin::Stream(bytes.clone(), *duration),
+        }
+    }
+}
+
+/// The sound of `SourceSamples`, sharing the samples or bytes of its `Source`.
+#[derive(Clone, Debug)]
+pub(crate) enum SourceOrigin {
+    Preloaded(DecodedSamples),
+    Stream(SharedBytes, Option<Duration>),
+}
+
+impl SourceOrigin {
+    /// Returns the samples of the sound from its start.
+    pub(crate) fn samples(&self) -> SourceSamples {
+        match self {
+            SourceOrigin::Preloaded(decoded) => SourceSamples::Preloaded {
+                decoded: decoded.clone(),
+                position: 0,
+            },
+            SourceOrigin::Stream(bytes, duration) => SourceSamples::Stream {
+                decoder: Box::new(
+                    Decoder::new(Cursor::new(bytes.clone()))
+                        .expect("Unreachable: the bytes were decoded before"),
+                ),
+                bytes: bytes.clone(),
+                duration: *duration,
+            },
+        }
+    }
+}
+
+impl Iterator for SourceSamples {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        match self {
+            SourceSamples::Preloaded { decoded, position } => {
+                let sample = decoded.samples.get(*position).cloned();
+                *position += 1;
+                sample
+            }
+            SourceSamples::Stream { decoder, .. } => decoder.next(),
+        }
+    }
+}
+
+impl RSource for SourceSamples {
+    fn current_frame_len(&self) -> Option<usize> {
+        match self {
+            SourceSamples::Preloaded { decoded, position } => {
+                Some(decoded.samples.len().saturating_sub(*position))
+            }
+            SourceSamples::Stream { decoder, .. } => decoder.current_frame_len(),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self {
+            SourceSamples::Preloaded { decoded, .. } => decoded.channels,
+            SourceSamples::Stream { decoder, .. } => decoder.channels(),
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            SourceSamples::Preloaded { decoded, .. } => decoded.sample_rate,
+            SourceSamples::Stream { decoder, .. } => decoder.sample_rate(),
+        }
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        match self {
+            SourceSamples::Preloaded { decoded, .. } => {
+                let frames = decoded.samples.len() as u64 / u64::from(decoded.channels.max(1));
+                Some(frames_duration(frames, decoded.sample_rate))
+            }
+            SourceSamples::Stream {
+                decoder, duration, ..
+            } => decoder.total_duration().or(*duration),
+        }
+    }
+}
+
+impl Rewind for SourceSamples {
+    fn rewind(&self, position: u64) -> Self {
+        match self.origin().samples() {
+            SourceSamples::Preloaded { decoded, .. } => SourceSamples::Preloaded {
+                decoded,
+                position: position as usize,
+            },
+            mut samples => {
+                for _ in 0..position {
+                    if samples.next().is_none() {
+                        break;
+                    }
+                }
+                samples
+            }
+        }
+    }
+
+    fn rewinds_instantly(&self) -> bool {
+        match self {
+            SourceSamples::Preloaded { .. } => true,
+            SourceSamples::Stream { .. } => false,
+        }
     }
 }
 
@@ -52,3 +351,33 @@ impl<'a> PrefabData<'a> for AudioData {
             .load_from_data(self.clone(), (), &system_data.1))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rodio::Source as RSource;
+
+    use crate::source::{DecodedSamples, SharedBytes, Source};
+
+    #[test]
+    fn test_preloaded_samples() {
+        let source = Source {
+            bytes: SharedBytes(Vec::new().into()),
+            decoded: Some(DecodedSamples {
+                channels: 2,
+                sample_rate: 4,
+                samples: Arc::from(vec![1i16, 2, 3, 4]),
+            }),
+            metadata: None,
+        };
+        let samples = source.samples().unwrap();
+        assert_eq!(samples.channels(), 2);
+        assert_eq!(
+            samples.total_duration(),
+            Some(std::time::Duration::from_millis(500))
+        );
+        assert_eq!(samples.collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(Source::new(Vec::new()).samples().is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Distance attenuation, Doppler effect and occlusion of 3D sounds.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+use amethyst_core::{ecs::prelude::Entity, math::Vector3};
+use serde::{Deserialize, Serialize};
+
+/// How the volume of a sound decreases between the `min_distance` and `max_distance` of its
+/// `Attenuation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rolloff {
+    /// The volume decreases linearly, reaching silence at the maximum distance.
+    Linear,
+    /// The volume is inversely proportional to the distance, like real sounds.
+    Inverse,
+    /// The volume decreases linearly with the logarithm of the distance, reaching silence at the
+    /// maximum distance.
+    Logarithmic,
+}
+
+/// Distance attenuation of the sounds of an `AudioEmitter`, see `AudioEmitter::set_attenuation`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Attenuation {
+    /// Distance under which the sound plays at full volume.
+    pub min_distance: f32,
+    /// Distance beyond which the volume stops decreasing.
+    pub max_distance: f32,
+    /// How the volume decreases with the distance.
+    pub rolloff: Rolloff,
+    /// Scales how fast the volume decreases, 1.0 is unchanged.
+    pub rolloff_factor: f32,
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation {
+            min_distance: 1.0,
+            max_distance: 100.0,
+            rolloff: Rolloff::Inverse,
+            rolloff_factor: 1.0,
+        }
+    }
+}
+
+impl Attenuation {
+    /// Returns the volume of a sound at `distance` from the listener, between 0.0 and 1.0.
+    pub fn gain(&self, distance: f32) -> f32 {
+        let min = self.min_distance.max(std::f32::EPSILON);
+        let max = self.max_distance.max(min);
+        let distance = distance.max(min).min(max);
+        let gain = match self.rolloff {
+            Rolloff::Linear if max > min => {
+                1.0 - self.rolloff_factor * (distance - min) / (max - min)
+            }
+            Rolloff::Logarithmic if max > min => {
+                1.0 - self.rolloff_factor * (distance / min).ln() / (max / min).ln()
+            }
+            Rolloff::Linear | Rolloff::Logarithmic => 1.0,
+            Rolloff::Inverse => min / (min + self.rolloff_factor * (distance - min)),
+        };
+        gain.max(0.0).min(1.0)
+    }
+}
+
+/// Doppler effect of the sounds of an `AudioEmitter`, see `AudioEmitter::set_doppler`.
+///
+/// The velocities of the emitter and the listener are computed from the motion of their
+/// `Transform`s.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Doppler {
+    /// Scales the effect, 1.0 is realistic.
+    pub factor: f32,
+    /// The speed of sound, in units per second.
+    pub speed_of_sound: f32,
+}
+
+impl Default for Doppler {
+    fn default() -> Self {
+        Doppler {
+            factor: 1.0,
+            speed_of_sound: 343.0,
+        }
+    }
+}
+
+impl Doppler {
+    /// Returns the pitch multiplier of a sound, given the positions and velocities of the listener
+    /// and the emitter.
+    pub fn pitch(
+        &self,
+        listener_position: [f32; 3],
+        listener_velocity: [f32; 3],
+        emitter_position: [f32; 3],
+        emitter_velocity: [f32; 3],
+    ) -> f32 {
+        let direction = Vector3::from(emitter_position) - Vector3::from(listener_position);
+        let distance = direction.norm();
+        if distance <= std::f32::EPSILON || self.speed_of_sound <= 0.0 {
+            return 1.0;
+        }
+        let direction = direction / distance;
+        // Speeds towards the other, clamped so the pitch stays positive.
+        let max_speed = self.speed_of_sound * 0.99;
+        let listener_speed = (Vector3::from(listener_velocity).dot(&direction) * self.factor)
+            .max(-max_speed)
+            .min(max_speed);
+        let emitter_speed = (-Vector3::from(emitter_velocity).dot(&direction) * self.factor)
+            .max(-max_speed)
+            .min(max_speed);
+        (self.speed_of_sound + listener_speed) / (self.speed_of_sound - emitter_speed)
+    }
+}
+
+/// Resource attenuating the sounds of emitters hidden from the listener by obstacles.
+///
+/// The callback typically casts a ray from the listener to the emitter, and returns the volume
+/// left after crossing the obstacles, from 0.0 for fully occluded to 1.0 for no obstacle. It's only
+/// called for emitters with `AudioEmitter::set_occludable`.
+pub struct AudioOcclusion {
+    callback: Box<dyn Fn(Entity, [f32; 3], [f32; 3]) -> f32 + Send + Sync>,
+}
+
+impl AudioOcclusion {
+    /// Creates an occlusion resource calling `callback` with the emitter entity, the listener
+    /// position and the emitter position.
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(Entity, [f32; 3], [f32; 3]) -> f32 + Send + Sync + 'static,
+    {
+        AudioOcclusion {
+            callback: Box::new(callback),
+        }
+    }
+
+    /// Returns the volume left after occlusion, between 0.0 and 1.0.
+    pub fn gain(
+        &self,
+        emitter: Entity,
+        listener_position: [f32; 3],
+        emitter_position: [f32; 3],
+    ) -> f32 {
+        (self.callback)(emitter, listener_position, emitter_position)
+            .max(0.0)
+            .min(1.0)
+    }
+}
+
+impl Debug for AudioOcclusion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("AudioOcclusion").finish()
+    }
+}
+
+/// Returns positions of the emitter and ears giving the same panning as the real positions, but
+/// close enough for the `SpatialSink` not to attenuate the sound with the distance.
+pub(crate) fn unattenuated_positions(
+    emitter: [f32; 3],
+    left_ear: [f32; 3],
+    right_ear: [f32; 3],
+) -> ([f32; 3], [f32; 3], [f32; 3]) {
+    let left = Vector3::from(left_ear);
+    let right = Vector3::from(right_ear);
+    let center = (left + right) / 2.0;
+    let ear_offset = (right - left)
+        .try_normalize(std::f32::EPSILON)
+        .unwrap_or_else(Vector3::x)
+        * 0.25;
+    let emitter_offset = (Vector3::from(emitter) - center)
+        .try_normalize(std::f32::EPSILON)
+        .unwrap_or_else(Vector3::zeros)
+        * 0.5;
+    let point = |v: Vector3<f32>| [v.x, v.y, v.z];
+    (
+        point(center + emitter_offset),
+        point(center - ear_offset),
+        point(center + ear_offset),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spatial::{Attenuation, Doppler, Rolloff};
+
+    #[test]
+    fn test_attenuation() {
+        let mut attenuation = Attenuation {
+            min_distance: 2.0,
+            max_distance: 10.0,
+            rolloff: Rolloff::Linear,
+            rolloff_factor: 1.0,
+        };
+        assert_eq!(attenuation.gain(1.0), 1.0);
+        assert_eq!(attenuation.gain(6.0), 0.5);
+        assert_eq!(attenuation.gain(20.0), 0.0);
+        attenuation.rolloff = Rolloff::Inverse;
+        assert_eq!(attenuation.gain(4.0), 0.5);
+        assert_eq!(attenuation.gain(20.0), 0.2);
+    }
+
+    #[test]
+    fn test_doppler() {
+        let doppler = Doppler {
+            factor: 1.0,
+            speed_of_sound: 100.0,
+        };
+        let still = [0.0; 3];
+        assert_eq!(doppler.pitch(still, still, [10.0, 0.0, 0.0], still), 1.0);
+        // The emitter comes towards the listener.
+        assert_eq!(
+            doppler.pitch(still, still, [10.0, 0.0, 0.0], [-50.0, 0.0, 0.0]),
+            2.0
+        );
+        // The listener moves away from the emitter.
+        assert_eq!(
+            doppler.pitch(still, [-50.0, 0.0, 0.0], [10.0, 0.0, 0.0], still),
+            0.5
+        );
+    }
+}
@@ -1,31 +1,27 @@
-use std::{
-    iter::Iterator,
-    mem::replace,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-};
+use std::{cmp::Ordering as CmpOrdering, iter::Iterator, mem::replace};
 
 use derive_new::new;
-use rodio::SpatialSink;
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
 use amethyst_core::{
     ecs::prelude::{
-        Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, WriteStorage,
+        Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage,
     },
-    math::convert,
+    math::{convert, Vector3},
+    timing::Time,
     transform::Transform,
     SystemDesc,
 };
 
 use crate::{
-    components::{AudioEmitter, AudioListener},
-    end_signal::EndSignalSource,
+    components::{AudioEmitter, AudioListener, ReverbZone},
+    effects::Effect,
+    mixer::Mixer,
     output::Output,
+    spatial::{unattenuated_positions, AudioOcclusion},
+    voice::Voices,
 };
 
 /// Builds an `AudioSystem`.
@@ -46,8 +42,19 @@ impl<'a, 'b> SystemDesc<'a, 'b, AudioSystem> for AudioSystemDesc {
 }
 
 /// Syncs 3D transform data with the audio engine to provide 3D audio.
+///
+/// This also applies the distance attenuation, Doppler effect, occlusion and effects of the
+/// emitters, the reverb of the `ReverbZone` around the listener, and the volume and effects of the
+/// mixer buses.
+///
+/// The sounds of the emitters beyond the limit of the `Voices` resource are virtualized.
 #[derive(Debug, Default, new)]
-pub struct AudioSystem(Output);
+pub struct AudioSystem(
+    Output,
+    /// Position of the listener at the previous frame, to compute its velocity.
+    #[new(default)]
+    Option<[f32; 3]>,
+);
 
 /// Add this structure to world as a resource with ID 0 to select an entity whose AudioListener
 /// component will be used.  If this resource isn't found then the system will arbitrarily select
@@ -59,15 +66,32 @@ impl<'a> System<'a> for AudioSystem {
     type SystemData = (
         Option<Read<'a, Output>>,
         Option<Read<'a, SelectedListener>>,
+        Option<Read<'a, Mixer>>,
+        Option<Read<'a, AudioOcclusion>>,
+        Write<'a, Voices>,
+        Read<'a, Time>,
         Entities<'a>,
         ReadStorage<'a, Transform>,
         ReadStorage<'a, AudioListener>,
+        ReadStorage<'a, ReverbZone>,
         WriteStorage<'a, AudioEmitter>,
     );
 
     fn run(
         &mut self,
-        (output, select_listener, entities, transform, listener, mut audio_emitter): Self::SystemData,
+        (
+            output,
+            select_listener,
+            mixer,
+            occlusion,
+            mut voices,
+            time,
+            entities,
+            transform,
+            listener,
+            reverb_zones,
+            mut audio_emitter,
+        ): Self::SystemData,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("audio_system");
@@ -97,44 +121,175 @@ impl<'a> System<'a> for AudioSystem {
                         .xyz();
                     [convert(pos.x), convert(pos.y), convert(pos.z)]
                 };
-                for (transform, mut audio_emitter) in (&transform, &mut audio_emitter).join() {
+                let listener_position = {
+                    let pos = (Vector3::from(left_ear_position)
+                        + Vector3::from(right_ear_position))
+                        / 2.0;
+                    [pos.x, pos.y, pos.z]
+                };
+                let delta = time.delta_seconds();
+                let velocity = |position: [f32; 3], last: Option<[f32; 3]>| match last {
+                    Some(last) if delta > 0.0 => {
+                        let v = (Vector3::from(position) - Vector3::from(last)) / delta;
+                        [v.x, v.y, v.z]
+                    }
+                    _ => [0.0; 3],
+                };
+                let listener_velocity = velocity(listener_position, self.1);
+                self.1 = Some(listener_position);
+
+                let reverb = (&reverb_zones, &transform)
+                    .join()
+                    .filter(|(zone, transform)| {
+                        let center = transform.global_matrix().column(3).xyz();
+                        let center = [convert(center.x), convert(center.y), convert(center.z)];
+                        zone.shape.contains(center, listener_position)
+                    })
+                    .max_by_key(|(zone, _)| zone.priority)
+                    .map(|(zone, _)| zone.reverb);
+
+                // The emitters' sink positions, volume and Doppler pitch.
+                let mut mixes = Vec::new();
+                // The audible voices: priority, volume, emitter and index in the emitter.
+                let mut candidates = Vec::new();
+                for (entity, transform, mut audio_emitter) in
+                    (&*entities, &transform, &mut audio_emitter).join()
+                {
                     let emitter_position: [f32; 3] = {
                         let x = transform.global_matrix()[(0, 3)];
                         let y = transform.global_matrix()[(1, 3)];
                         let z = transform.global_matrix()[(2, 3)];
                         [convert(x), convert(y), convert(z)]
                     };
-                    // Remove all sinks whose sounds have ended.
-                    audio_emitter.sinks.retain(|s| !s.1.load(Ordering::Relaxed));
-                    for &mut (ref mut sink, _) in &mut audio_emitter.sinks {
-                        sink.set_emitter_position(emitter_position);
-                        sink.set_left_ear_position(left_ear_position);
-                        sink.set_right_ear_position(right_ear_position);
+                    let emitter_velocity = velocity(emitter_position, audio_emitter.last_position);
+                    audio_emitter.last_position = Some(emitter_position);
+
+                    let mut volume = mixer.as_ref().map_or(1.0, |mixer| {
+                        mixer.volume(audio_emitter.bus().unwrap_or(Mixer::MASTER))
+                    });
+                    let distance =
+                        (Vector3::from(emitter_position) - Vector3::from(listener_position)).norm();
+                    // With an attenuation, the sink only pans the sound and the volume is computed
+                    // here. Without one, the sink attenuates the sound, and the voices are ranked
+                    // by the volume it would hear.
+                    let mut sink_gain = 1.0;
+                    let (sink_emitter, sink_left_ear, sink_right_ear) =
+                        match audio_emitter.attenuation {
+                            Some(attenuation) => {
+                                volume *= attenuation.gain(distance);
+                                unattenuated_positions(
+                                    emitter_position,
+                                    left_ear_position,
+                                    right_ear_position,
+                                )
+                            }
+                            None => {
+                                sink_gain = (1.0 / distance.powi(2)).min(1.0);
+                                (emitter_position, left_ear_position, right_ear_position)
+                            }
+                        };
+                    if audio_emitter.occludable {
+                        if let Some(occlusion) = &occlusion {
+                            volume *= occlusion.gain(entity, listener_position, emitter_position);
+                        }
+                    }
+                    let pitch = audio_emitter.doppler.map_or(1.0, |doppler| {
+                        doppler.pitch(
+                            listener_position,
+                            listener_velocity,
+                            emitter_position,
+                            emitter_velocity,
+                        )
+                    });
+
+                    let mut effects = audio_emitter.effects.clone();
+                    effects.extend(reverb.map(Effect::Reverb));
+                    if let Some(mixer) = &mixer {
+                        effects.extend(mixer.effects(audio_emitter.bus().unwrap_or(Mixer::MASTER)));
                     }
-                    if audio_emitter.sinks.is_empty() {
+                    audio_emitter.effect_chain.set(effects);
+
+                    // Forget the voices whose sounds have ended.
+                    audio_emitter.voices.retain(|voice| !voice.is_finished());
+                    if audio_emitter.voices.is_empty() {
                         if let Some(mut picker) = replace(&mut audio_emitter.picker, None) {
                             if picker(&mut audio_emitter) {
                                 audio_emitter.picker = Some(picker);
                             }
                         }
                     }
-                    while let Some(source) = audio_emitter.sound_queue.pop() {
-                        if let Some(output) = &output {
-                            let sink = SpatialSink::new(
-                                &output.device,
-                                emitter_position,
-                                left_ear_position,
-                                right_ear_position,
-                            );
-                            let atomic_bool = Arc::new(AtomicBool::new(false));
-                            let clone = atomic_bool.clone();
-                            sink.append(EndSignalSource::new(source, move || {
-                                clone.store(true, Ordering::Relaxed);
-                            }));
-                            audio_emitter.sinks.push((sink, atomic_bool));
+                    for (index, voice) in audio_emitter.voices.iter_mut().enumerate() {
+                        voice.audible = false;
+                        let gain = if voice.handle.is_paused() {
+                            0.0
+                        } else {
+                            volume * sink_gain * voice.handle.volume()
+                        };
+                        if gain >= voices.min_volume {
+                            candidates.push((voice.priority, gain, entity, index));
+                        }
+                    }
+                    mixes.push((
+                        entity,
+                        [sink_emitter, sink_left_ear, sink_right_ear],
+                        volume,
+                        pitch,
+                    ));
+                }
+
+                // Only the loudest voices with the highest priority are played.
+                candidates.sort_by(|a, b| {
+                    b.0.cmp(&a.0)
+                        .then(b.1.partial_cmp(&a.1).unwrap_or(CmpOrdering::Equal))
+                });
+                for (_, _, entity, index) in candidates.iter().take(voices.max_voices) {
+                    if let Some(audio_emitter) = audio_emitter.get_mut(*entity) {
+                        audio_emitter.voices[*index].audible = true;
+                    }
+                }
+
+                let delta = time.delta_real_seconds();
+                let (mut real, mut virtual_voices) = (0, 0);
+                for (entity, [sink_emitter, sink_left_ear, sink_right_ear], volume, pitch) in mixes
+                {
+                    let audio_emitter = match audio_emitter.get_mut(entity) {
+                        Some(audio_emitter) => audio_emitter,
+                        None => continue,
+                    };
+                    let effect_chain = &audio_emitter.effect_chain;
+                    for voice in &mut audio_emitter.voices {
+                        voice.handle.set_doppler(pitch);
+                        if voice.audible && voice.sink().is_none() {
+                            if let Some(output) = &output {
+                                let sink = output.spatial_sink(
+                                    sink_emitter,
+                                    sink_left_ear,
+                                    sink_right_ear,
+                                );
+                                sink.set_volume(volume);
+                                voice.realize(sink, effect_chain.clone());
+                            }
+                        } else if !voice.audible && voice.sink().is_some() {
+                            voice.virtualize();
+                        }
+                        voice.poll_seek();
+                        match voice.sink() {
+                            Some(sink) => {
+                                sink.set_emitter_position(sink_emitter);
+                                sink.set_left_ear_position(sink_left_ear);
+                                sink.set_right_ear_position(sink_right_ear);
+                                sink.set_volume(volume);
+                                real += 1;
+                            }
+                            None => {
+                                voice.advance(delta, voice.handle.pitch() * pitch);
+                                virtual_voices += 1;
+                            }
                         }
                     }
                 }
+                voices.real = real;
+                voices.virtual_voices = virtual_voices;
             }
         }
     }
@@ -26,6 +26,18 @@ use crate::{
 pub struct DjSystemDesc<F, R> {
     f: F,
     marker: PhantomData<R>,
+    #[new(default)]
+    bus: Option<String>,
+}
+
+impl<F, R> DjSystemDesc<F, R> {
+    /// Routes the music to a mixer bus, see `Mixer::MUSIC`.
+    ///
+    /// This sets the bus of the `AudioSink` resource.
+    pub fn with_bus(mut self, bus: &str) -> Self {
+        self.bus = Some(bus.to_string());
+        self
+    }
 }
 
 impl<'a, 'b, F, R> SystemDesc<'a, 'b, DjSystem<F, R>> for DjSystemDesc<F, R>
@@ -37,6 +49,11 @@ where
         <DjSystem<F, R> as System<'_>>::SystemData::setup(world);
 
         init_output(world);
+        if let Some(bus) = self.bus {
+            if let Some(mut sink) = world.try_fetch_mut::<AudioSink>() {
+                sink.set_bus(Some(bus));
+            }
+        }
 
         DjSystem::new(self.f)
     }
@@ -0,0 +1,245 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    mem::replace,
+};
+
+use log::error;
+use rand::{thread_rng, Rng};
+use smallvec::SmallVec;
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use amethyst_assets::{AssetStorage, Loader, ProgressCounter};
+use amethyst_core::{
+    ecs::prelude::{Join, Read, ReadExpect, System, SystemData, World, Write, WriteStorage},
+    timing::Time,
+    SystemDesc,
+};
+
+use crate::{
+    components::AudioEmitter,
+    event::{AudioEvent, AudioEventBank, AudioEventBankHandle, AudioEvents},
+    formats::load_source,
+    mixer::Mixer,
+    output::{init_output, Output},
+    playback::{Playback, PlaybackHandle},
+    source::{Source, SourceHandle, SourceSamples},
+};
+
+/// Builds an `AudioEventSystem`.
+#[derive(Debug, Default)]
+pub struct AudioEventSystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, AudioEventSystem> for AudioEventSystemDesc {
+    fn build(self, world: &mut World) -> AudioEventSystem {
+        <AudioEventSystem as System<'_>>::SystemData::setup(world);
+
+        init_output(world);
+
+        AudioEventSystem::default()
+    }
+}
+
+#[derive(Debug, Default)]
+struct EventState {
+    /// The sound picked the last time the event played.
+    last: Option<usize>,
+    /// Time before the event can play again, in seconds.
+    cooldown: f32,
+    instances: Vec<PlaybackHandle>,
+}
+
+/// A sound of the events, tracking whether it failed to load.
+struct EventSound {
+    handle: SourceHandle,
+    progress: ProgressCounter,
+}
+
+impl fmt::Debug for EventSound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventSound")
+            .field("handle", &self.handle)
+            .field("failed", &(self.progress.num_failed() > 0))
+            .finish()
+    }
+}
+
+enum Trigger<'e> {
+    Play(SourceSamples, PlaybackHandle, &'e AudioEvent),
+    /// The sounds of the event are still loading.
+    Pending,
+    Ignored,
+}
+
+/// Plays the audio events triggered with `AudioEvents::trigger` and `AudioEmitter::trigger`.
+///
+/// Add it before the `AudioSystem` for the sounds of emitters to start on the frame they're
+/// triggered.
+#[derive(Debug, Default)]
+pub struct AudioEventSystem {
+    loaded_bank: Option<AudioEventBankHandle>,
+    sources: HashMap<String, EventSound>,
+    states: HashMap<String, EventState>,
+}
+
+impl AudioEventSystem {
+    /// Picks the sound of an event, unless its cooldown or instance limit prevents it.
+    fn trigger<'e, R: Rng>(
+        &mut self,
+        bank: &'e AudioEventBank,
+        name: &str,
+        sources: &AssetStorage<Source>,
+        rng: &mut R,
+    ) -> Trigger<'e> {
+        let event = match bank.event(name) {
+            Some(event) => event,
+            None => {
+                error!("The audio event bank has no event named {}", name);
+                return Trigger::Ignored;
+            }
+        };
+        // Sounds that failed to load are skipped when they're picked.
+        let loaded = event.sounds.iter().all(|sound| {
+            self.sources.get(&sound.path).map_or(true, |sound| {
+                sources.get(&sound.handle).is_some() || sound.progress.num_failed() > 0
+            })
+        });
+        if !loaded {
+            return Trigger::Pending;
+        }
+        let state = self.states.entry(name.to_string()).or_default();
+        let limited = event
+            .max_instances
+            .map_or(false, |max| state.instances.len() >= max);
+        if state.cooldown > 0.0 || limited {
+            return Trigger::Ignored;
+        }
+        let index = match event.pick(state.last, rng) {
+            Some(index) => index,
+            None => return Trigger::Ignored,
+        };
+        let path = &event.sounds[index].path;
+        let source = match self
+            .sources
+            .get(path)
+            .and_then(|sound| sources.get(&sound.handle))
+        {
+            Some(source) => source,
+            None => return Trigger::Ignored,
+        };
+        let samples = match source.samples() {
+            Ok(samples) => samples,
+            Err(e) => {
+                error!("Cannot play audio event {}. {}", name, e);
+                return Trigger::Ignored;
+            }
+        };
+        let handle = PlaybackHandle::new();
+        handle.set_volume(event.random_volume(rng));
+        handle.set_pitch(event.random_pitch(rng));
+        state.last = Some(index);
+        state.cooldown = event.cooldown;
+        state.instances.push(handle.clone());
+        Trigger::Play(samples, handle, event)
+    }
+}
+
+impl<'a> System<'a> for AudioEventSystem {
+    type SystemData = (
+        Option<Read<'a, Output>>,
+        Write<'a, AudioEvents>,
+        WriteStorage<'a, AudioEmitter>,
+        Option<Write<'a, Mixer>>,
+        Read<'a, AssetStorage<AudioEventBank>>,
+        Read<'a, AssetStorage<Source>>,
+        ReadExpect<'a, Loader>,
+        Read<'a, Time>,
+    );
+
+    fn run(
+        &mut self,
+        (output, mut events, mut emitters, mut mixer, banks, sources, loader, time): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("audio_event_system");
+
+        let delta = time.delta_real_seconds();
+        for state in self.states.values_mut() {
+            state.cooldown -= delta;
+            state.instances.retain(|handle| !handle.is_finished());
+        }
+
+        let bank = match events.bank.as_ref().and_then(|h| banks.get(h)) {
+            Some(bank) => bank,
+            None => {
+                // Events triggered without a bank can never play.
+                if events.bank.is_none() {
+                    events.triggered.clear();
+                    for emitter in (&mut emitters).join() {
+                        emitter.events.clear();
+                    }
+                } else {
+                    let mut queued = HashSet::new();
+                    events.triggered.retain(|name| queued.insert(name.clone()));
+                    for emitter in (&mut emitters).join() {
+                        queued.clear();
+                        emitter.events.retain(|name| queued.insert(name.clone()));
+                    }
+                }
+                return;
+            }
+        };
+        if self.loaded_bank != events.bank {
+            for sound in bank.events.iter().flat_map(|event| &event.sounds) {
+                if !self.sources.contains_key(&sound.path) {
+                    let mut progress = ProgressCounter::new();
+                    if let Some(handle) = load_source(&loader, &sound.path, &mut progress, &sources)
+                    {
+                        self.sources
+                            .insert(sound.path.clone(), EventSound { handle, progress });
+                    }
+                }
+            }
+            self.loaded_bank = events.bank.clone();
+        }
+
+        let mut rng = thread_rng();
+        for name in replace(&mut events.triggered, Vec::new()) {
+            match self.trigger(bank, &name, &sources, &mut rng) {
+                Trigger::Play(samples, handle, event) => {
+                    let playback = Playback::new(samples, &handle);
+                    if let Some(output) = &output {
+                        let bus = event.bus.as_ref().map_or(Mixer::MASTER, String::as_str);
+                        if let Some(mixer) = &mut mixer {
+                            mixer.play(output, playback, 1.0, bus);
+                        } else {
+                            let sink = output.sink();
+                            sink.append(playback);
+                            sink.detach();
+                        }
+                    }
+                }
+                // Triggers of a loading event play once, however many were queued.
+                Trigger::Pending if !events.triggered.contains(&name) => {
+                    events.triggered.push(name)
+                }
+                Trigger::Pending | Trigger::Ignored => {}
+            }
+        }
+
+        for emitter in (&mut emitters).join() {
+            for name in replace(&mut emitter.events, SmallVec::new()) {
+                match self.trigger(bank, &name, &sources, &mut rng) {
+                    Trigger::Play(samples, handle, event) => {
+                        emitter.play_samples(samples, handle, event.priority)
+                    }
+                    Trigger::Pending if !emitter.events.contains(&name) => {
+                        emitter.events.push(name)
+                    }
+                    Trigger::Pending | Trigger::Ignored => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use amethyst_core::{
+    ecs::prelude::{Join, Read, ReadStorage, System, Write},
+    timing::Time,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use crate::{components::AudioEmitter, mixer::Mixer, music::Music, sink::AudioSink};
+
+/// Updates the ducking of the `Mixer`, and applies the volume and effects of its buses to the
+/// `AudioSink`.
+///
+/// The `AudioSystem` applies the volume and effects of the buses to the sounds of the `AudioEmitter`s.
+#[derive(Debug, Default)]
+pub struct MixerSystem;
+
+impl<'a> System<'a> for MixerSystem {
+    type SystemData = (
+        Write<'a, Mixer>,
+        Option<Read<'a, AudioSink>>,
+        Option<Read<'a, Music>>,
+        ReadStorage<'a, AudioEmitter>,
+        Read<'a, Time>,
+    );
+
+    fn run(&mut self, (mut mixer, sink, music, emitters, time): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("mixer_system");
+
+        let mut playing = HashMap::new();
+        if let Some(sink) = &sink {
+            if !sink.empty() && !sink.is_paused() {
+                let bus = sink.bus().unwrap_or(Mixer::MASTER);
+                *playing.entry(bus.to_string()).or_insert(0) += 1;
+            }
+        }
+        if let Some(music) = &music {
+            if music.current_track().is_some() {
+                let bus = music.bus().unwrap_or(Mixer::MASTER);
+                *playing.entry(bus.to_string()).or_insert(0) += 1;
+            }
+        }
+        for emitter in (&emitters).join() {
+            // Virtual voices aren't heard, so they don't duck other buses.
+            let real = emitter.voices.iter().filter(|v| v.sink().is_some()).count();
+            if real > 0 {
+                let bus = emitter.bus().unwrap_or(Mixer::MASTER);
+                *playing.entry(bus.to_string()).or_insert(0) += real;
+            }
+        }
+        mixer.update(playing, time.delta_real_seconds());
+
+        if let Some(sink) = &sink {
+            let bus = sink.bus().unwrap_or(Mixer::MASTER);
+            sink.apply_bus_volume(mixer.volume(bus));
+            sink.apply_bus_effects(mixer.effects(bus));
+        }
+    }
+}
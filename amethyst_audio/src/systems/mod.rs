@@ -3,7 +3,15 @@
 pub use self::{
     audio::{AudioSystem, AudioSystemDesc},
     dj::{DjSystem, DjSystemDesc},
+    event::{AudioEventSystem, AudioEventSystemDesc},
+    mixer::MixerSystem,
+    music::{MusicSystem, MusicSystemDesc},
+    output::OutputSystem,
 };
 
 mod audio;
 mod dj;
+mod event;
+mod mixer;
+mod music;
+mod output;
@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::Arc,
+    time::Duration,
+};
+
+use log::error;
+use rodio::Sink;
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use amethyst_assets::{AssetStorage, Loader};
+use amethyst_core::{
+    ecs::prelude::{Read, ReadExpect, System, SystemData, World, Write},
+    SystemDesc,
+};
+
+use crate::{
+    effects::{EffectChain, EffectsSource},
+    formats::load_source,
+    mixer::Mixer,
+    music::{Music, MusicTrack, Playlist, PlaylistHandle},
+    output::{init_output, Output},
+    playback::{LoopPoints, Playback, PlaybackHandle},
+    source::{Source, SourceHandle},
+};
+
+/// Builds a `MusicSystem`.
+#[derive(Debug, Default)]
+pub struct MusicSystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, MusicSystem> for MusicSystemDesc {
+    fn build(self, world: &mut World) -> MusicSystem {
+        <MusicSystem as System<'_>>::SystemData::setup(world);
+
+        init_output(world);
+
+        MusicSystem::default()
+    }
+}
+
+struct PlayingStem {
+    sink: Sink,
+    handle: PlaybackHandle,
+    volume: f32,
+}
+
+struct PlayingTrack {
+    track: MusicTrack,
+    stems: Vec<PlayingStem>,
+    /// Position in the loop of the track, in seconds.
+    position: f32,
+}
+
+impl PlayingTrack {
+    /// Reads the position from the samples played by the stems, returning true if the track
+    /// looped since the last update.
+    fn update_position(&mut self) -> bool {
+        let position = match self.stems.first() {
+            Some(stem) => {
+                let position = stem.handle.position();
+                position.as_secs() as f32 + position.subsec_nanos() as f32 / 1_000_000_000.0
+            }
+            None => return false,
+        };
+        let looped = position < self.position;
+        self.position = position;
+        looped
+    }
+}
+
+/// Plays the adaptive music of the `Music` resource.
+///
+/// The stems of the current track loop together, and their volume follows the parameters of the
+/// `Music`. Tracks are switched following the `Transition` of their `Playlist`.
+#[derive(Default)]
+pub struct MusicSystem {
+    loaded_playlist: Option<PlaylistHandle>,
+    sources: HashMap<String, SourceHandle>,
+    current: Option<PlayingTrack>,
+    /// Tracks fading out after a crossfade.
+    fading: Vec<PlayingTrack>,
+    /// Position in the current track at which the requested track starts.
+    switch_at: Option<f32>,
+    /// The effects of the bus of the music.
+    effect_chain: Arc<EffectChain>,
+}
+
+impl Debug for MusicSystem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("MusicSystem")
+            .field(
+                "current",
+                &self.current.as_ref().map(|playing| &playing.track.name),
+            )
+            .field("fading", &self.fading.len())
+            .field("switch_at", &self.switch_at)
+            .finish()
+    }
+}
+
+impl<'a> System<'a> for MusicSystem {
+    type SystemData = (
+        Option<Read<'a, Output>>,
+        Write<'a, Music>,
+        Option<Read<'a, Mixer>>,
+        Read<'a, AssetStorage<Playlist>>,
+        Read<'a, AssetStorage<Source>>,
+        ReadExpect<'a, Loader>,
+    );
+
+    fn run(&mut self, (output, mut music, mixer, playlists, sources, loader): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("music_system");
+
+        let playlist = music.playlist.as_ref().and_then(|h| playlists.get(h));
+        if let Some(playlist) = playlist {
+            if self.loaded_playlist != music.playlist {
+                for stem in playlist.tracks.iter().flat_map(|track| &track.stems) {
+                    if !self.sources.contains_key(&stem.path) {
+                        if let Some(handle) = load_source(&loader, &stem.path, (), &sources) {
+                            self.sources.insert(stem.path.clone(), handle);
+                        }
+                    }
+                }
+                self.loaded_playlist = music.playlist.clone();
+            }
+        }
+
+        if let Some(current) = &mut self.current {
+            if current.update_position() && self.switch_at.is_some() {
+                // The stems looped, the loop end is a boundary of the track.
+                self.switch_at = Some(current.position);
+            }
+        }
+
+        let current_name = self.current.as_ref().map(|c| c.track.name.clone());
+        if music.requested != current_name {
+            let transition = playlist.map(|p| p.transition).unwrap_or_default();
+            let switch_at = match (self.switch_at, &self.current) {
+                (Some(switch_at), _) => switch_at,
+                (None, Some(current)) => {
+                    current.position
+                        + current
+                            .track
+                            .time_to_boundary(current.position, transition.sync)
+                }
+                (None, None) => 0.0,
+            };
+            self.switch_at = Some(switch_at);
+            let ready = self.current.as_ref().map_or(true, |current| {
+                current.stems.is_empty() || current.position >= switch_at
+            });
+            let requested = music.requested.clone();
+            let next = match &requested {
+                Some(name) => match playlist.map(|p| p.track(name)) {
+                    Some(Some(track)) => Some(track),
+                    Some(None) => {
+                        error!("The music playlist has no track named {}", name);
+                        music.requested = current_name.clone();
+                        self.switch_at = None;
+                        None
+                    }
+                    // The playlist is still loading.
+                    None => None,
+                },
+                None => None,
+            };
+            let loaded = next.map_or(true, |track| {
+                track.stems.iter().all(|stem| {
+                    self.sources
+                        .get(&stem.path)
+                        .map_or(false, |h| sources.get(h).is_some())
+                })
+            });
+            let resolved = requested.is_none() || next.is_some();
+            if ready && resolved && loaded && music.requested != current_name {
+                let crossfade = seconds(transition.crossfade);
+                let fading_out = self.current.is_some();
+                if let Some(previous) = self.current.take() {
+                    for stem in &previous.stems {
+                        if transition.crossfade > 0.0 {
+                            stem.handle.fade_out(crossfade);
+                        } else {
+                            stem.handle.stop();
+                        }
+                    }
+                    self.fading.push(previous);
+                }
+                if let (Some(track), Some(output)) = (next, &output) {
+                    let stems = track
+                        .stems
+                        .iter()
+                        .filter_map(|stem| {
+                            let source =
+                                self.sources.get(&stem.path).and_then(|h| sources.get(h))?;
+                            let samples = match source.samples() {
+                                Ok(samples) => samples,
+                                Err(e) => {
+                                    error!("Cannot play music stem {}. {}", stem.path, e);
+                                    return None;
+                                }
+                            };
+                            let handle = PlaybackHandle::new();
+                            let volume = stem.volume(&music.parameters);
+                            handle.set_volume(volume);
+                            // Stems loop on the loop points of their file, or as a whole.
+                            let loop_points = source.metadata().and_then(|m| m.loop_points);
+                            handle.set_loop(Some(loop_points.unwrap_or_else(LoopPoints::whole)));
+                            if fading_out && transition.crossfade > 0.0 {
+                                handle.fade_in(crossfade);
+                            }
+                            let sink = output.sink();
+                            sink.append(EffectsSource::new(
+                                Playback::new(samples, &handle),
+                                self.effect_chain.clone(),
+                            ));
+                            Some(PlayingStem {
+                                sink,
+                                handle,
+                                volume,
+                            })
+                        })
+                        .collect();
+                    self.current = Some(PlayingTrack {
+                        track: track.clone(),
+                        stems,
+                        position: 0.0,
+                    });
+                }
+                music.current = self.current.as_ref().map(|c| c.track.name.clone());
+                self.switch_at = None;
+            }
+        } else {
+            self.switch_at = None;
+        }
+
+        if let Some(current) = &mut self.current {
+            let fade = seconds(playlist.map_or(0.0, |p| p.parameter_fade));
+            for (stem, playing) in current.track.stems.iter().zip(&mut current.stems) {
+                let volume = stem.volume(&music.parameters);
+                if (volume - playing.volume).abs() > std::f32::EPSILON {
+                    playing.volume = volume;
+                    playing.handle.fade_to(volume, fade);
+                }
+            }
+        }
+
+        for track in &mut self.fading {
+            track.stems.retain(|stem| !stem.handle.is_finished());
+        }
+        self.fading.retain(|track| !track.stems.is_empty());
+
+        let bus = music.bus().unwrap_or(Mixer::MASTER);
+        let bus_volume = mixer.as_ref().map_or(1.0, |mixer| mixer.volume(bus));
+        if let Some(mixer) = &mixer {
+            self.effect_chain.set(mixer.effects(bus));
+        }
+        for track in self.current.iter().chain(&self.fading) {
+            for stem in &track.stems {
+                stem.sink.set_volume(bus_volume);
+            }
+        }
+    }
+}
+
+fn seconds(seconds: f32) -> Duration {
+    Duration::from_millis((seconds.max(0.0) * 1000.0) as u64)
+}
@@ -0,0 +1,34 @@
+use amethyst_core::{
+    ecs::prelude::{Read, System},
+    timing::Time,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+use crate::output::Output;
+
+/// Renders the sounds of a null or capture `Output` every frame, see `Output::render`.
+///
+/// Null outputs render the real time of the frame, and capture outputs their
+/// `Capture::frame_length` or `Time::fixed_time`. Device outputs play in real time, so this does
+/// nothing for them.
+#[derive(Debug, Default)]
+pub struct OutputSystem;
+
+impl<'a> System<'a> for OutputSystem {
+    type SystemData = (Option<Read<'a, Output>>, Read<'a, Time>);
+
+    fn run(&mut self, (output, time): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("output_system");
+
+        if let Some(output) = output {
+            let duration = match output.captured() {
+                Some(capture) => capture.frame_length().unwrap_or_else(|| time.fixed_time()),
+                None => time.delta_real_time(),
+            };
+            output.render(duration);
+        }
+    }
+}
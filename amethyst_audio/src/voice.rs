@@ -0,0 +1,269 @@
+//! Limits the number of sounds of `AudioEmitter`s playing at once, see `Voices`.
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+
+use rodio::Source as RSource;
+
+use crate::{
+    effects::{EffectChain, EffectsSource},
+    output::SpatialSink,
+    playback::{Playback, PlaybackHandle, Rewind, Seek},
+    source::{SourceOrigin, SourceSamples},
+};
+
+/// Resource limiting the number of sounds of `AudioEmitter`s playing at once.
+///
+/// Every frame, the `AudioSystem` plays the `max_voices` sounds with the highest priority, then
+/// the highest volume. The other sounds are virtual: they're not mixed, but their position keeps
+/// advancing, so they resume at the right position once they're played again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Voices {
+    /// The maximum number of sounds mixed at once.
+    pub max_voices: usize,
+    /// Sounds quieter than this volume are virtual, like sounds beyond the `max_distance` of
+    /// their `Attenuation`.
+    pub min_volume: f32,
+    pub(crate) real: usize,
+    pub(crate) virtual_voices: usize,
+}
+
+impl Default for Voices {
+    fn default() -> Self {
+        Voices::new(32)
+    }
+}
+
+impl Voices {
+    /// Creates a voice limit mixing up to `max_voices` sounds at once.
+    pub fn new(max_voices: usize) -> Self {
+        Voices {
+            max_voices,
+            min_volume: 0.001,
+            real: 0,
+            virtual_voices: 0,
+        }
+    }
+
+    /// Returns the number of sounds mixed during the last frame.
+    pub fn real_count(&self) -> usize {
+        self.real
+    }
+
+    /// Returns the number of virtual sounds during the last frame.
+    pub fn virtual_count(&self) -> usize {
+        self.virtual_voices
+    }
+}
+
+/// A sound of an `AudioEmitter`, playing on a sink while it's real.
+pub(crate) struct Voice {
+    pub(crate) handle: PlaybackHandle,
+    pub(crate) priority: i32,
+    /// The sound, to play it again from the position of the voice.
+    origin: SourceOrigin,
+    channels: u16,
+    sample_rate: u32,
+    sink: Option<SpatialSink>,
+    /// The sink of a voice becoming real, waiting for its sound to be rewound to its position.
+    seek: Option<(SpatialSink, Arc<EffectChain>, Seek<SourceSamples>)>,
+    /// Position in the sound while virtual, in samples of all channels.
+    position: f64,
+    /// Length of the sound in samples of all channels, unknown for streamed sources that weren't
+    /// loaded as assets.
+    length: Option<u64>,
+    /// Set by the `AudioSystem` if the voice is within the voice limit.
+    pub(crate) audible: bool,
+}
+
+impl Voice {
+    /// Creates a virtual voice at the start of the sound.
+    pub(crate) fn new(samples: SourceSamples, handle: PlaybackHandle, priority: i32) -> Self {
+        let channels = samples.channels().max(1);
+        let sample_rate = samples.sample_rate();
+        let length = samples.total_duration().map(|duration| {
+            let seconds = duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9;
+            (seconds * f64::from(sample_rate)).round() as u64 * u64::from(channels)
+        });
+        Voice {
+            handle,
+            priority,
+            origin: samples.origin(),
+            channels,
+            sample_rate,
+            sink: None,
+            seek: None,
+            position: 0.0,
+            length,
+            audible: false,
+        }
+    }
+
+    /// Returns the sink of the voice if it's real, even if its sound is still rewinding.
+    pub(crate) fn sink(&self) -> Option<&SpatialSink> {
+        self.sink
+            .as_ref()
+            .or_else(|| self.seek.as_ref().map(|(sink, _, _)| sink))
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Plays the sound from the position of the voice on `sink`.
+    ///
+    /// Streamed sounds are decoded up to the position on another thread, they play once
+    /// `poll_seek` finds them rewound.
+    pub(crate) fn realize(&mut self, sink: SpatialSink, effect_chain: Arc<EffectChain>) {
+        let channels = u64::from(self.channels);
+        let position = self.position as u64 / channels * channels;
+        let samples = self.origin.samples();
+        if samples.rewinds_instantly() {
+            self.play(sink, samples.rewind(position), position, effect_chain);
+        } else {
+            self.seek = Some((sink, effect_chain, Seek::new(samples, position)));
+            self.poll_seek();
+        }
+    }
+
+    /// Plays the sound of a voice becoming real once it's rewound.
+    pub(crate) fn poll_seek(&mut self) {
+        if let Some((sink, effect_chain, seek)) = self.seek.take() {
+            match seek.try_take() {
+                Some(samples) => self.play(sink, samples, seek.position(), effect_chain),
+                None => self.seek = Some((sink, effect_chain, seek)),
+            }
+        }
+    }
+
+    fn play(
+        &mut self,
+        sink: SpatialSink,
+        samples: SourceSamples,
+        position: u64,
+        effect_chain: Arc<EffectChain>,
+    ) {
+        let playback = Playback::resume(samples, &self.handle, position);
+        sink.append(EffectsSource::new(playback, effect_chain));
+        self.sink = Some(sink);
+    }
+
+    /// Stops mixing the sound, keeping its position.
+    pub(crate) fn virtualize(&mut self) {
+        // The position of a voice still rewinding didn't change.
+        if self.seek.take().is_none() {
+            self.position = self.handle.detach_playback() as f64;
+        }
+        self.sink = None;
+    }
+
+    /// Advances a virtual voice as if it played for `seconds` at `pitch`.
+    ///
+    /// Voices of unknown length only finish once they're real again, when they reach their end.
+    pub(crate) fn advance(&mut self, seconds: f32, pitch: f32) {
+        if self.handle.is_stopping() {
+            self.handle.finish();
+            return;
+        }
+        if self.handle.is_paused() {
+            return;
+        }
+        let channels = u64::from(self.channels);
+        self.position +=
+            f64::from(seconds) * f64::from(pitch) * f64::from(self.sample_rate) * channels as f64;
+        let length = self.length.unwrap_or(u64::max_value());
+        match self.handle.loop_points() {
+            Some(loop_points) => {
+                let start = (loop_points.start * channels) as f64;
+                let end = loop_points
+                    .end
+                    .map_or(length, |end| (end * channels).min(length))
+                    as f64;
+                if self.position >= end && end > start {
+                    self.position = start + (self.position - start) % (end - start);
+                }
+            }
+            None if self.position >= length as f64 => self.handle.finish(),
+            None => {}
+        }
+    }
+}
+
+impl Drop for Voice {
+    fn drop(&mut self) {
+        match self.sink.take() {
+            // The sound ended, its effects play until they fade out.
+            Some(sink) if self.handle.is_finished() => sink.detach(),
+            // The playback of a real voice finishes the sound when the sink drops it.
+            Some(_) => {}
+            None => self.handle.finish(),
+        }
+    }
+}
+
+impl Debug for Voice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Voice")
+            .field("priority", &self.priority)
+            .field("real", &self.sink.is_some())
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        playback::{LoopPoints, PlaybackHandle},
+        source::Source,
+        voice::Voice,
+    };
+
+    fn voice(handle: &PlaybackHandle) -> Voice {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36u32 + 8).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+        let samples = Source::new(bytes).samples().unwrap();
+        Voice::new(samples, handle.clone(), 0)
+    }
+
+    #[test]
+    fn test_advance() {
+        let handle = PlaybackHandle::new();
+        let mut voice = voice(&handle);
+        voice.advance(0.5, 1.0);
+        assert_eq!(voice.position, 2.0);
+        assert!(!handle.is_finished());
+        voice.advance(0.5, 1.0);
+        assert!(handle.is_finished());
+    }
+
+    #[test]
+    fn test_advance_loop() {
+        let handle = PlaybackHandle::new();
+        handle.set_loop(Some(LoopPoints {
+            start: 1,
+            end: None,
+        }));
+        let mut voice = voice(&handle);
+        voice.advance(1.25, 1.0);
+        assert_eq!(voice.position, 2.0);
+        assert!(!handle.is_finished());
+        handle.stop();
+        voice.advance(0.25, 1.0);
+        assert!(handle.is_finished());
+    }
+}
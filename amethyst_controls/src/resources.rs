@@ -15,8 +15,8 @@ impl WindowFocus {
     }
 }
 
-/// Resource indicating if the mouse should be grabbed and hidden by the CursorHideSystem
-/// when the window is focused.
+/// Resource indicating if the mouse should be locked by the CursorHideSystem, which grabs and
+/// hides it when the window is focused.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct HideCursor {
     /// If true this system will take control of the cursor.
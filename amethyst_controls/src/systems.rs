@@ -1,13 +1,11 @@
 use derive_new::new;
-use winit::{DeviceEvent, Event, Window, WindowEvent};
+use winit::{DeviceEvent, Event, WindowEvent};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
 use amethyst_core::{
-    ecs::prelude::{
-        Join, Read, ReadExpect, ReadStorage, System, SystemData, World, Write, WriteStorage,
-    },
+    ecs::prelude::{Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage},
     math::{convert, Unit, Vector3},
     shrev::{EventChannel, ReaderId},
     timing::Time,
@@ -16,6 +14,7 @@ use amethyst_core::{
 };
 use amethyst_derive::SystemDesc;
 use amethyst_input::{get_input_axis_simple, BindingTypes, InputHandler};
+use amethyst_window::{Cursor, CursorMode};
 
 use crate::{
     components::{ArcBallControlTag, FlyControlTag},
@@ -234,38 +233,31 @@ impl<'a, 'b> SystemDesc<'a, 'b, CursorHideSystem> for CursorHideSystemDesc {
     }
 }
 
-/// System which hides the cursor when the window is focused.
-/// Requires the usage MouseFocusUpdateSystem at the same time.
+/// System which locks the cursor, see `CursorMode::Locked`, while the `HideCursor` resource is set.
+///
+/// The cursor is applied to the window by the `CursorSystem` of the `WindowBundle`, which
+/// releases it while the window isn't focused.
 #[derive(Debug, new)]
 pub struct CursorHideSystem {
-    #[new(value = "true")]
-    is_hidden: bool,
+    #[new(default)]
+    is_hidden: Option<bool>,
 }
 
 impl<'a> System<'a> for CursorHideSystem {
-    type SystemData = (
-        ReadExpect<'a, Window>,
-        Read<'a, HideCursor>,
-        Read<'a, WindowFocus>,
-    );
+    type SystemData = (Read<'a, HideCursor>, Write<'a, Cursor>);
 
-    fn run(&mut self, (win, hide, focus): Self::SystemData) {
+    fn run(&mut self, (hide, mut cursor): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("cursor_hide_system");
 
-        let should_be_hidden = focus.is_focused && hide.hide;
-        if !self.is_hidden && should_be_hidden {
-            if let Err(err) = win.grab_cursor(true) {
-                log::error!("Unable to grab the cursor. Error: {:?}", err);
-            }
-            win.hide_cursor(true);
-            self.is_hidden = true;
-        } else if self.is_hidden && !should_be_hidden {
-            if let Err(err) = win.grab_cursor(false) {
-                log::error!("Unable to release the cursor. Error: {:?}", err);
-            }
-            win.hide_cursor(false);
-            self.is_hidden = false;
+        // Only change the mode when `HideCursor` changes, so other systems can set it meanwhile.
+        if self.is_hidden != Some(hide.hide) {
+            cursor.mode = if hide.hide {
+                CursorMode::Locked
+            } else {
+                CursorMode::Normal
+            };
+            self.is_hidden = Some(hide.hide);
         }
     }
 }
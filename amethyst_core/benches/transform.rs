@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use amethyst_core::{
+    ecs::prelude::{Builder, Entity, Join, RunNow, World, WorldExt},
+    transform::{Parent, Transform, TransformSystem, TransformSystemDesc},
+    ArcThreadPool, SystemDesc,
+};
+use specs_hierarchy::HierarchySystem;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const ROOTS: usize = 1_000;
+const CHILDREN: usize = 10;
+const GRANDCHILDREN: usize = 9;
+
+// Builds `ROOTS` trees of 100 entities each, for a total of 100_000 entities.
+fn setup() -> (World, HierarchySystem<Parent>, TransformSystem, Vec<Entity>) {
+    let mut world = World::new();
+    let pool: ArcThreadPool = Arc::new(rayon::ThreadPoolBuilder::new().build().unwrap());
+    world.insert(pool);
+
+    let mut hierarchy_system = HierarchySystem::<Parent>::new(&mut world);
+    let mut transform_system = TransformSystemDesc::default().build(&mut world);
+    RunNow::setup(&mut hierarchy_system, &mut world);
+    RunNow::setup(&mut transform_system, &mut world);
+
+    let mut roots = Vec::with_capacity(ROOTS);
+    for i in 0..ROOTS {
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(i as f32, 0.0, 0.0);
+        let root = world.create_entity().with(transform).build();
+        roots.push(root);
+
+        for _ in 0..CHILDREN {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(0.0, 1.0, 0.0);
+            transform.set_rotation_y_axis(0.5);
+            let child = world
+                .create_entity()
+                .with(transform)
+                .with(Parent { entity: root })
+                .build();
+
+            for _ in 0..GRANDCHILDREN {
+                let mut transform = Transform::default();
+                transform.set_translation_xyz(0.0, 0.0, 1.0);
+                world
+                    .create_entity()
+                    .with(transform)
+                    .with(Parent { entity: child })
+                    .build();
+            }
+        }
+    }
+
+    hierarchy_system.run_now(&world);
+    transform_system.run_now(&world);
+    world.maintain();
+
+    (world, hierarchy_system, transform_system, roots)
+}
+
+pub fn transform_system_all_dirty(c: &mut Criterion) {
+    let (world, mut hierarchy_system, mut transform_system, _) = setup();
+
+    c.bench_function("transform_system_all_dirty_100k", move |b| {
+        b.iter(|| {
+            {
+                let mut transforms = world.write_storage::<Transform>();
+                for transform in (&mut transforms).join() {
+                    transform.prepend_translation_x(0.1);
+                }
+            }
+            hierarchy_system.run_now(&world);
+            transform_system.run_now(&world);
+        });
+    });
+}
+
+pub fn transform_system_few_dirty(c: &mut Criterion) {
+    let (world, mut hierarchy_system, mut transform_system, roots) = setup();
+
+    c.bench_function("transform_system_ten_dirty_trees_100k", move |b| {
+        b.iter(|| {
+            {
+                let mut transforms = world.write_storage::<Transform>();
+                for root in roots.iter().take(10) {
+                    transforms
+                        .get_mut(*root)
+                        .unwrap()
+                        .prepend_translation_x(0.1);
+                }
+            }
+            hierarchy_system.run_now(&world);
+            transform_system.run_now(&world);
+        });
+    });
+}
+
+pub fn transform_system_clean(c: &mut Criterion) {
+    let (world, mut hierarchy_system, mut transform_system, _) = setup();
+
+    c.bench_function("transform_system_clean_100k", move |b| {
+        b.iter(|| {
+            hierarchy_system.run_now(&world);
+            transform_system.run_now(&world);
+        });
+    });
+}
+
+criterion_group!(
+    transform,
+    transform_system_all_dirty,
+    transform_system_few_dirty,
+    transform_system_clean,
+);
+criterion_main!(transform);
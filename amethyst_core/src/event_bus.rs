@@ -0,0 +1,465 @@
+//! Typed event bus built on top of `EventChannel`s.
+//!
+//! Events of a type `E` are sent through an [`EventQueue<E>`], which can delay them by a number
+//! of frames or by a duration. The [`EventQueueSystem<E>`] publishes due events on the
+//! `EventChannel<E>`, and systems read them through an [`EventSubscriber`], which registers and
+//! stores its own `ReaderId` when it is set up.
+//!
+//! The [`EventBusBundle`] registers event types, and publishes their queued events at the point
+//! of the dispatcher it is added to.
+//!
+//! [`EventQueue<E>`]: struct.EventQueue.html
+//! [`EventQueueSystem<E>`]: struct.EventQueueSystem.html
+//! [`EventSubscriber`]: struct.EventSubscriber.html
+//! [`EventBusBundle`]: struct.EventBusBundle.html
+
+use std::{marker::PhantomData, time::Duration};
+
+use derivative::Derivative;
+
+use amethyst_error::Error;
+
+use crate::{
+    bundle::SystemBundle,
+    ecs::prelude::{DispatcherBuilder, Read, System, World, Write, WriteExpect},
+    shred::{ResourceId, SystemData},
+    shrev::{EventChannel, EventIterator, ReaderId},
+    timing::Time,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Delay before a queued event is published.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Delay {
+    Frames(u64),
+    Time(Duration),
+}
+
+impl Delay {
+    /// Returns the remaining delay after a frame of `delta`, or `None` if the event is due.
+    fn elapse(self, delta: Duration) -> Option<Delay> {
+        match self {
+            Delay::Frames(0) => None,
+            Delay::Frames(frames) => Some(Delay::Frames(frames - 1)),
+            Delay::Time(time) if time <= delta => None,
+            Delay::Time(time) => Some(Delay::Time(time - delta)),
+        }
+    }
+}
+
+/// Resource queuing events of type `E` until the `EventQueueSystem<E>` publishes them on the
+/// `EventChannel<E>`.
+#[derive(Debug)]
+pub struct EventQueue<E> {
+    queued: Vec<E>,
+    delayed: Vec<(Delay, E)>,
+}
+
+impl<E> Default for EventQueue<E> {
+    fn default() -> Self {
+        EventQueue {
+            queued: Vec::new(),
+            delayed: Vec::new(),
+        }
+    }
+}
+
+impl<E> EventQueue<E> {
+    /// Queues an event, it is published the next time the queue system runs.
+    pub fn send(&mut self, event: E) {
+        self.queued.push(event);
+    }
+
+    /// Queues an event which is published `frames` frames later than with `send`.
+    pub fn send_after_frames(&mut self, frames: u64, event: E) {
+        if frames == 0 {
+            self.send(event);
+        } else {
+            self.delayed.push((Delay::Frames(frames), event));
+        }
+    }
+
+    /// Queues an event which is published once `delay` of game time has elapsed.
+    ///
+    /// Elapsed time is measured with `Time::delta_time`, so it is affected by the time scale.
+    pub fn send_after(&mut self, delay: Duration, event: E) {
+        self.delayed.push((Delay::Time(delay), event));
+    }
+
+    /// Returns the number of events waiting to be published.
+    pub fn len(&self) -> usize {
+        self.queued.len() + self.delayed.len()
+    }
+
+    /// Returns true if no events are waiting to be published.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Advances delays by one frame of `delta` and removes the events that are due.
+    fn drain_due(&mut self, delta: Duration) -> Vec<E> {
+        let mut due = Vec::with_capacity(self.queued.len());
+        let delayed = std::mem::take(&mut self.delayed);
+        for (delay, event) in delayed {
+            match delay.elapse(delta) {
+                Some(delay) => self.delayed.push((delay, event)),
+                None => due.push(event),
+            }
+        }
+        due.append(&mut self.queued);
+        due
+    }
+}
+
+/// Publishes the due events of the `EventQueue<E>` on the `EventChannel<E>`.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct EventQueueSystem<E> {
+    marker: PhantomData<E>,
+}
+
+impl<E> EventQueueSystem<E> {
+    /// Creates a new event queue system.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<'a, E> System<'a> for EventQueueSystem<E>
+where
+    E: Send + Sync + 'static,
+{
+    type SystemData = (
+        Read<'a, Time>,
+        Write<'a, EventQueue<E>>,
+        Write<'a, EventChannel<E>>,
+    );
+
+    fn run(&mut self, (time, mut queue, mut channel): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("event_queue_system");
+
+        if !queue.is_empty() {
+            channel.iter_write(queue.drain_due(time.delta_time()));
+        }
+    }
+}
+
+/// Reads the events of type `E` on behalf of the subscriber `S`.
+///
+/// The `ReaderId` is registered when the system data is set up, and stored in the `World`
+/// under the subscriber type `S`, so systems don't need to keep a `ReaderId` themselves. `S` is
+/// usually the type of the system reading the events, every subscriber type receives every
+/// event once.
+///
+/// # Examples
+///
+/// ```rust
+/// use amethyst_core::{
+///     ecs::prelude::{System, World, WorldExt, RunNow},
+///     event_bus::{EventQueue, EventQueueSystem, EventSubscriber},
+/// };
+///
+/// #[derive(Debug)]
+/// struct Explosion;
+///
+/// struct ScreenShake;
+///
+/// impl<'a> System<'a> for ScreenShake {
+///     type SystemData = EventSubscriber<'a, Explosion, Self>;
+///
+///     fn run(&mut self, mut explosions: Self::SystemData) {
+///         for explosion in explosions.read() {
+///             println!("Shaking for {:?}", explosion);
+///         }
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// let mut queue_system = EventQueueSystem::<Explosion>::new();
+/// let mut shake_system = ScreenShake;
+/// RunNow::setup(&mut queue_system, &mut world);
+/// RunNow::setup(&mut shake_system, &mut world);
+///
+/// world.write_resource::<EventQueue<Explosion>>().send(Explosion);
+/// queue_system.run_now(&world);
+/// shake_system.run_now(&world);
+/// ```
+#[allow(missing_debug_implementations)]
+pub struct EventSubscriber<'a, E, S>
+where
+    E: Send + Sync + 'static,
+    S: 'static,
+{
+    channel: Read<'a, EventChannel<E>>,
+    reader: WriteExpect<'a, SubscriberReader<E, S>>,
+}
+
+impl<'a, E, S> EventSubscriber<'a, E, S>
+where
+    E: Send + Sync + 'static,
+    S: 'static,
+{
+    /// Reads the events published since the last read.
+    pub fn read(&mut self) -> EventIterator<'_, E> {
+        self.channel.read(&mut self.reader.reader)
+    }
+}
+
+impl<'a, E, S> SystemData<'a> for EventSubscriber<'a, E, S>
+where
+    E: Send + Sync + 'static,
+    S: 'static,
+{
+    fn setup(world: &mut World) {
+        <Read<'a, EventChannel<E>> as SystemData<'a>>::setup(world);
+        if !world.has_value::<SubscriberReader<E, S>>() {
+            let reader = world.fetch_mut::<EventChannel<E>>().register_reader();
+            world.insert(SubscriberReader::<E, S> {
+                reader,
+                marker: PhantomData,
+            });
+        }
+    }
+
+    fn fetch(world: &'a World) -> Self {
+        EventSubscriber {
+            channel: SystemData::fetch(world),
+            reader: SystemData::fetch(world),
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        vec![ResourceId::new::<EventChannel<E>>()]
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        vec![ResourceId::new::<SubscriberReader<E, S>>()]
+    }
+}
+
+/// `ReaderId` of the subscriber `S` to the events `E`.
+struct SubscriberReader<E, S>
+where
+    E: 'static,
+{
+    reader: ReaderId<E>,
+    marker: PhantomData<fn() -> S>,
+}
+
+/// Registration of an event type in the `EventBusBundle`.
+trait EventRegistration {
+    fn register(&self, world: &mut World, builder: &mut DispatcherBuilder<'_, '_>);
+}
+
+struct Registration<E>(PhantomData<fn() -> E>);
+
+impl<E> EventRegistration for Registration<E>
+where
+    E: Send + Sync + 'static,
+{
+    fn register(&self, world: &mut World, builder: &mut DispatcherBuilder<'_, '_>) {
+        world
+            .entry::<EventChannel<E>>()
+            .or_insert_with(Default::default);
+        world
+            .entry::<EventQueue<E>>()
+            .or_insert_with(Default::default);
+        builder.add(
+            EventQueueSystem::<E>::new(),
+            &EventBusBundle::system_name::<E>(),
+            &[],
+        );
+    }
+}
+
+/// Registers event types on the event bus.
+///
+/// Adds an `EventQueueSystem` for every registered event type, followed by a barrier. Queued
+/// events are therefore published before any system added after this bundle runs, add it first
+/// to publish them at the start of the frame.
+///
+/// The `EventQueueSystem<E>` is registered with the name returned by
+/// `EventBusBundle::system_name::<E>()`, "event_queue_" followed by the type name of `E`.
+///
+/// ## Errors
+///
+/// No errors will be returned by this bundle.
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+pub struct EventBusBundle {
+    #[derivative(Debug = "ignore")]
+    registrations: Vec<Box<dyn EventRegistration>>,
+}
+
+impl EventBusBundle {
+    /// Creates a new event bus bundle with no event types.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers the event type `E`.
+    pub fn with_event<E>(mut self) -> Self
+    where
+        E: Send + Sync + 'static,
+    {
+        self.registrations
+            .push(Box::new(Registration::<E>(PhantomData)));
+        self
+    }
+
+    /// Returns the name of the `EventQueueSystem` of the event type `E`, to depend on it.
+    pub fn system_name<E>() -> String {
+        format!("event_queue_{}", std::any::type_name::<E>())
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for EventBusBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        for registration in &self.registrations {
+            registration.register(world, builder);
+        }
+        builder.add_barrier();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{EventBusBundle, EventQueue, EventQueueSystem, EventSubscriber};
+    use crate::{
+        bundle::SystemBundle,
+        ecs::prelude::{DispatcherBuilder, RunNow, System, World, WorldExt, Write},
+        Time,
+    };
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestEvent(u32);
+
+    #[derive(Default)]
+    struct Collect(Vec<u32>);
+
+    impl<'a> System<'a> for Collect {
+        type SystemData = EventSubscriber<'a, TestEvent, Self>;
+
+        fn run(&mut self, mut events: Self::SystemData) {
+            self.0.extend(events.read().map(|event| event.0));
+        }
+    }
+
+    struct Other(usize);
+
+    impl<'a> System<'a> for Other {
+        type SystemData = EventSubscriber<'a, TestEvent, Self>;
+
+        fn run(&mut self, mut events: Self::SystemData) {
+            self.0 += events.read().count();
+        }
+    }
+
+    /// Collects the events into a resource, to read them after dispatching.
+    struct Record;
+
+    impl<'a> System<'a> for Record {
+        type SystemData = (EventSubscriber<'a, TestEvent, Self>, Write<'a, Vec<u32>>);
+
+        fn run(&mut self, (mut events, mut received): Self::SystemData) {
+            received.extend(events.read().map(|event| event.0));
+        }
+    }
+
+    fn setup() -> (World, EventQueueSystem<TestEvent>, Collect) {
+        let mut world = World::new();
+        let mut queue_system = EventQueueSystem::new();
+        let mut collect = Collect::default();
+        RunNow::setup(&mut queue_system, &mut world);
+        RunNow::setup(&mut collect, &mut world);
+        (world, queue_system, collect)
+    }
+
+    fn frame(
+        world: &mut World,
+        queue_system: &mut EventQueueSystem<TestEvent>,
+        collect: &mut Collect,
+    ) -> Vec<u32> {
+        world
+            .write_resource::<Time>()
+            .set_delta_time(Duration::from_millis(100));
+        queue_system.run_now(world);
+        collect.run_now(world);
+        std::mem::take(&mut collect.0)
+    }
+
+    #[test]
+    fn delays_events() {
+        let (mut world, mut queue_system, mut collect) = setup();
+        {
+            let mut queue = world.write_resource::<EventQueue<TestEvent>>();
+            queue.send(TestEvent(0));
+            queue.send_after_frames(2, TestEvent(2));
+            queue.send_after_frames(1, TestEvent(1));
+            queue.send_after(Duration::from_millis(250), TestEvent(3));
+        }
+        let mut frames = Vec::new();
+        for _ in 0..4 {
+            frames.push(frame(&mut world, &mut queue_system, &mut collect));
+        }
+        assert_eq!(frames, vec![vec![0], vec![1], vec![2, 3], vec![]]);
+    }
+
+    #[test]
+    fn every_subscriber_reads_every_event() {
+        let (mut world, mut queue_system, mut collect) = setup();
+        let mut other = Other(0);
+        RunNow::setup(&mut other, &mut world);
+
+        world
+            .write_resource::<EventQueue<TestEvent>>()
+            .send(TestEvent(7));
+        assert_eq!(frame(&mut world, &mut queue_system, &mut collect), vec![7]);
+        other.run_now(&world);
+        assert_eq!(other.0, 1);
+    }
+
+    #[test]
+    fn bundle_names_queue_systems() {
+        let mut world = World::new();
+        let mut builder = DispatcherBuilder::new();
+        EventBusBundle::new()
+            .with_event::<TestEvent>()
+            .with_event::<u32>()
+            .build(&mut world, &mut builder)
+            .unwrap();
+        builder.add(
+            Record,
+            "record",
+            &[&EventBusBundle::system_name::<TestEvent>()],
+        );
+        let mut dispatcher = builder.build();
+        dispatcher.setup(&mut world);
+        world
+            .write_resource::<Time>()
+            .set_delta_time(Duration::from_millis(100));
+
+        {
+            let mut queue = world.write_resource::<EventQueue<TestEvent>>();
+            queue.send(TestEvent(3));
+            queue.send_after(Duration::from_millis(250), TestEvent(4));
+        }
+        let mut frames = Vec::new();
+        for _ in 0..3 {
+            dispatcher.dispatch(&world);
+            frames.push(std::mem::take(&mut *world.write_resource::<Vec<u32>>()));
+        }
+        assert_eq!(frames, vec![vec![3], vec![], vec![4]]);
+        assert!(EventBusBundle::system_name::<u32>().starts_with("event_queue_"));
+    }
+}
@@ -30,7 +30,7 @@ use std::sync::Arc;
 pub use crate::{
     bundle::SystemBundle,
     event::EventReader,
-    system_ext::{Pausable, SystemExt},
+    system_ext::{FixedRate, Pausable, RunEvery, RunEveryNFrames, RunIf, SystemDescExt, SystemExt},
     timing::*,
     transform::*,
 };
@@ -45,6 +45,7 @@ pub use self::{
 
 pub mod bundle;
 pub mod deferred_dispatcher_operation;
+pub mod event_bus;
 pub mod frame_limiter;
 pub mod timing;
 pub mod transform;
@@ -3,9 +3,15 @@
 //! This modules contains an extension trait for the System trait which adds useful transformation
 //! functions.
 
+use std::{marker::PhantomData, time::Duration};
+
+use derivative::Derivative;
+
 use crate::{
-    ecs::prelude::{Read, System},
-    shred::{RunningTime, SystemData},
+    ecs::prelude::{Read, System, World},
+    shred::{ResourceId, RunningTime, SystemData},
+    timing::{duration_to_nanos, nanos_to_duration, Time},
+    SystemDesc,
 };
 
 #[cfg(feature = "profiler")]
@@ -80,6 +86,95 @@ pub trait SystemExt {
     where
         Self: Sized,
         V: Send + Sync + Default + PartialEq;
+
+    /// Make a system run at most once every `interval` of game time.
+    ///
+    /// The system runs on the first dispatch, and then whenever `interval` has elapsed since its
+    /// last run. Elapsed time is measured with `Time::delta_time`, so it is affected by the time
+    /// scale.
+    ///
+    /// # Notes
+    ///
+    /// The same care as for [`SystemExt::pausable`] must be taken with `EventChannel`s.
+    ///
+    /// [`SystemExt::pausable`]: trait.SystemExt.html#tymethod.pausable
+    fn run_every(self, interval: Duration) -> RunEvery<Self>
+    where
+        Self: Sized;
+
+    /// Make a system run once every `n` frames, starting with the first dispatch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    fn run_every_n_frames(self, n: u64) -> RunEveryNFrames<Self>
+    where
+        Self: Sized;
+
+    /// Make a system run only while `predicate` returns true for the resource `R`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use amethyst::{
+    ///     core::SystemExt,
+    ///     ecs::{System, Write},
+    ///     shred::DispatcherBuilder,
+    ///     prelude::*,
+    /// };
+    ///
+    /// #[derive(Default)]
+    /// struct Enemies(u32);
+    ///
+    /// struct Pathfinding;
+    ///
+    /// impl<'s> System<'s> for Pathfinding {
+    ///     type SystemData = Write<'s, u32>;
+    ///
+    ///     fn run(&mut self, mut runs: Self::SystemData) {
+    ///         *runs += 1;
+    ///     }
+    /// }
+    ///
+    /// let mut world = World::new();
+    ///
+    /// let mut dispatcher = DispatcherBuilder::default()
+    ///     .with(
+    ///         Pathfinding.run_if(|enemies: &Enemies| enemies.0 > 0),
+    ///         "pathfinding",
+    ///         &[],
+    ///     )
+    ///     .build();
+    ///
+    /// dispatcher.setup(&mut world);
+    ///
+    /// dispatcher.dispatch(&mut world);
+    /// assert_eq!(0, *world.read_resource::<u32>());
+    ///
+    /// *world.write_resource() = Enemies(3);
+    /// dispatcher.dispatch(&mut world);
+    /// assert_eq!(1, *world.read_resource::<u32>());
+    /// ```
+    fn run_if<R, F>(self, predicate: F) -> RunIf<Self, R, F>
+    where
+        Self: Sized,
+        R: Send + Sync + Default + 'static,
+        F: Fn(&R) -> bool + Send + Sync;
+
+    /// Make a system run at a fixed rate, independently of the frame rate.
+    ///
+    /// Game time is accumulated every frame, and the system runs once for every `step` in the
+    /// accumulator. This means it may run several times in a single frame, or not at all.
+    ///
+    /// The wrapped system still sees the frame's `Time::delta_seconds`, it should use the length of
+    /// `step` instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is zero.
+    fn fixed_rate(self, step: Duration) -> FixedRate<Self>
+    where
+        Self: Sized;
 }
 
 impl<'s, S> SystemExt for S
@@ -96,6 +191,162 @@ where
             value,
         }
     }
+
+    fn run_every(self, interval: Duration) -> RunEvery<Self>
+    where
+        Self: Sized,
+    {
+        RunEvery::new(self, interval)
+    }
+
+    fn run_every_n_frames(self, n: u64) -> RunEveryNFrames<Self>
+    where
+        Self: Sized,
+    {
+        RunEveryNFrames::new(self, n)
+    }
+
+    fn run_if<R, F>(self, predicate: F) -> RunIf<Self, R, F>
+    where
+        Self: Sized,
+        R: Send + Sync + Default + 'static,
+        F: Fn(&R) -> bool + Send + Sync,
+    {
+        RunIf::new(self, predicate)
+    }
+
+    fn fixed_rate(self, step: Duration) -> FixedRate<Self>
+    where
+        Self: Sized,
+    {
+        FixedRate::new(self, step)
+    }
+}
+
+/// Extension functionality associated with system descriptors.
+///
+/// Provides the same functions as [`SystemExt`], the returned types are themselves `SystemDesc`s
+/// which build the matching wrapped system.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use amethyst::{
+///     core::SystemDescExt,
+///     derive::SystemDesc,
+///     ecs::{System, SystemData, World},
+///     prelude::*,
+/// };
+///
+/// #[derive(SystemDesc)]
+/// #[system_desc(name(AiSystemDesc))]
+/// struct AiSystem;
+///
+/// impl<'s> System<'s> for AiSystem {
+///     type SystemData = ();
+///
+///     fn run(&mut self, _: Self::SystemData) {}
+/// }
+///
+/// GameDataBuilder::default()
+///     .with_system_desc(
+///         AiSystemDesc.run_every(Duration::from_millis(250)),
+///         "ai",
+///         &[],
+///     );
+/// ```
+///
+/// [`SystemExt`]: trait.SystemExt.html
+pub trait SystemDescExt<'a, 'b, S>: SystemDesc<'a, 'b, S>
+where
+    S: System<'a>,
+{
+    /// Builds a system made pausable with [`SystemExt::pausable`].
+    ///
+    /// [`SystemExt::pausable`]: trait.SystemExt.html#tymethod.pausable
+    fn pausable<V>(self, value: V) -> Pausable<Self, V>
+    where
+        Self: Sized,
+        V: 'static + Send + Sync + Default + PartialEq;
+
+    /// Builds a system scheduled with [`SystemExt::run_every`].
+    ///
+    /// [`SystemExt::run_every`]: trait.SystemExt.html#tymethod.run_every
+    fn run_every(self, interval: Duration) -> RunEvery<Self>
+    where
+        Self: Sized;
+
+    /// Builds a system scheduled with [`SystemExt::run_every_n_frames`].
+    ///
+    /// [`SystemExt::run_every_n_frames`]: trait.SystemExt.html#tymethod.run_every_n_frames
+    fn run_every_n_frames(self, n: u64) -> RunEveryNFrames<Self>
+    where
+        Self: Sized;
+
+    /// Builds a system scheduled with [`SystemExt::run_if`].
+    ///
+    /// [`SystemExt::run_if`]: trait.SystemExt.html#tymethod.run_if
+    fn run_if<R, F>(self, predicate: F) -> RunIf<Self, R, F>
+    where
+        Self: Sized,
+        R: Send + Sync + Default + 'static,
+        F: Fn(&R) -> bool + Send + Sync;
+
+    /// Builds a system scheduled with [`SystemExt::fixed_rate`].
+    ///
+    /// [`SystemExt::fixed_rate`]: trait.SystemExt.html#tymethod.fixed_rate
+    fn fixed_rate(self, step: Duration) -> FixedRate<Self>
+    where
+        Self: Sized;
+}
+
+impl<'a, 'b, S, SD> SystemDescExt<'a, 'b, S> for SD
+where
+    SD: SystemDesc<'a, 'b, S>,
+    S: System<'a>,
+{
+    fn pausable<V>(self, value: V) -> Pausable<Self, V>
+    where
+        Self: Sized,
+        V: 'static + Send + Sync + Default + PartialEq,
+    {
+        Pausable {
+            system: self,
+            value,
+        }
+    }
+
+    fn run_every(self, interval: Duration) -> RunEvery<Self>
+    where
+        Self: Sized,
+    {
+        RunEvery::new(self, interval)
+    }
+
+    fn run_every_n_frames(self, n: u64) -> RunEveryNFrames<Self>
+    where
+        Self: Sized,
+    {
+        RunEveryNFrames::new(self, n)
+    }
+
+    fn run_if<R, F>(self, predicate: F) -> RunIf<Self, R, F>
+    where
+        Self: Sized,
+        R: Send + Sync + Default + 'static,
+        F: Fn(&R) -> bool + Send + Sync,
+    {
+        RunIf::new(self, predicate)
+    }
+
+    fn fixed_rate(self, step: Duration) -> FixedRate<Self>
+    where
+        Self: Sized,
+    {
+        FixedRate::new(self, step)
+    }
 }
 
 /// A system that is enabled when `V` has a specific value.
@@ -132,3 +383,394 @@ where
         self.system.running_time()
     }
 }
+
+impl<'a, 'b, SD, S, V> SystemDesc<'a, 'b, Pausable<S, V>> for Pausable<SD, V>
+where
+    SD: SystemDesc<'a, 'b, S>,
+    S: System<'a>,
+    S::SystemData: SystemData<'a>,
+    V: 'static + Send + Sync + Default + PartialEq,
+{
+    fn build(self, world: &mut World) -> Pausable<S, V> {
+        Pausable {
+            system: self.system.build(world),
+            value: self.value,
+        }
+    }
+}
+
+/// A system that runs at most once every given interval of game time.
+///
+/// This is created using the [`SystemExt::run_every`] method.
+///
+/// [`SystemExt::run_every`]: trait.SystemExt.html#tymethod.run_every
+#[derive(Debug)]
+pub struct RunEvery<S> {
+    system: S,
+    interval: Duration,
+    elapsed: Duration,
+}
+
+impl<S> RunEvery<S> {
+    fn new(system: S, interval: Duration) -> Self {
+        RunEvery {
+            system,
+            interval,
+            // Run on the first dispatch.
+            elapsed: interval,
+        }
+    }
+
+    /// Advances the timer by `delta`, returning whether the system should run.
+    fn tick(&mut self, delta: Duration) -> bool {
+        self.elapsed += delta;
+        if self.elapsed < self.interval {
+            return false;
+        }
+
+        let interval = duration_to_nanos(self.interval);
+        self.elapsed = if interval == 0 {
+            Duration::from_secs(0)
+        } else {
+            // Only run once even if several intervals elapsed.
+            nanos_to_duration(duration_to_nanos(self.elapsed) % interval)
+        };
+        true
+    }
+}
+
+impl<'s, S> System<'s> for RunEvery<S>
+where
+    S::SystemData: SystemData<'s>,
+    S: System<'s>,
+{
+    type SystemData = (Read<'s, Time>, S::SystemData);
+
+    fn run(&mut self, (time, data): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run_every_system");
+
+        if self.tick(time.delta_time()) {
+            self.system.run(data);
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+}
+
+impl<'a, 'b, SD, S> SystemDesc<'a, 'b, RunEvery<S>> for RunEvery<SD>
+where
+    SD: SystemDesc<'a, 'b, S>,
+    S: System<'a>,
+    S::SystemData: SystemData<'a>,
+{
+    fn build(self, world: &mut World) -> RunEvery<S> {
+        RunEvery {
+            system: self.system.build(world),
+            interval: self.interval,
+            elapsed: self.elapsed,
+        }
+    }
+}
+
+/// A system that runs once every `n` frames.
+///
+/// This is created using the [`SystemExt::run_every_n_frames`] method.
+///
+/// [`SystemExt::run_every_n_frames`]: trait.SystemExt.html#tymethod.run_every_n_frames
+#[derive(Debug)]
+pub struct RunEveryNFrames<S> {
+    system: S,
+    n: u64,
+    counter: u64,
+}
+
+impl<S> RunEveryNFrames<S> {
+    fn new(system: S, n: u64) -> Self {
+        assert!(
+            n > 0,
+            "`run_every_n_frames` requires a frame count above zero"
+        );
+        RunEveryNFrames {
+            system,
+            n,
+            counter: 0,
+        }
+    }
+}
+
+impl<'s, S> System<'s> for RunEveryNFrames<S>
+where
+    S::SystemData: SystemData<'s>,
+    S: System<'s>,
+{
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run_every_n_frames_system");
+
+        let run = self.counter == 0;
+        self.counter = (self.counter + 1) % self.n;
+        if run {
+            self.system.run(data);
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+}
+
+impl<'a, 'b, SD, S> SystemDesc<'a, 'b, RunEveryNFrames<S>> for RunEveryNFrames<SD>
+where
+    SD: SystemDesc<'a, 'b, S>,
+    S: System<'a>,
+    S::SystemData: SystemData<'a>,
+{
+    fn build(self, world: &mut World) -> RunEveryNFrames<S> {
+        RunEveryNFrames {
+            system: self.system.build(world),
+            n: self.n,
+            counter: self.counter,
+        }
+    }
+}
+
+/// A system that runs only while a predicate on the resource `R` holds.
+///
+/// This is created using the [`SystemExt::run_if`] method.
+///
+/// [`SystemExt::run_if`]: trait.SystemExt.html#tymethod.run_if
+#[derive(Derivative)]
+#[derivative(Debug(bound = "S: std::fmt::Debug"))]
+pub struct RunIf<S, R, F> {
+    system: S,
+    #[derivative(Debug = "ignore")]
+    predicate: F,
+    marker: PhantomData<R>,
+}
+
+impl<S, R, F> RunIf<S, R, F> {
+    fn new(system: S, predicate: F) -> Self {
+        RunIf {
+            system,
+            predicate,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'s, S, R, F> System<'s> for RunIf<S, R, F>
+where
+    S::SystemData: SystemData<'s>,
+    S: System<'s>,
+    R: Send + Sync + Default + 'static,
+    F: Fn(&R) -> bool + Send + Sync,
+{
+    type SystemData = (Read<'s, R>, S::SystemData);
+
+    fn run(&mut self, (resource, data): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("run_if_system");
+
+        if (self.predicate)(&resource) {
+            self.system.run(data);
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+}
+
+impl<'a, 'b, SD, S, R, F> SystemDesc<'a, 'b, RunIf<S, R, F>> for RunIf<SD, R, F>
+where
+    SD: SystemDesc<'a, 'b, S>,
+    S: System<'a>,
+    S::SystemData: SystemData<'a>,
+    R: Send + Sync + Default + 'static,
+    F: Fn(&R) -> bool + Send + Sync,
+{
+    fn build(self, world: &mut World) -> RunIf<S, R, F> {
+        RunIf::new(self.system.build(world), self.predicate)
+    }
+}
+
+/// A system that runs once for every fixed step of accumulated game time.
+///
+/// This is created using the [`SystemExt::fixed_rate`] method.
+///
+/// [`SystemExt::fixed_rate`]: trait.SystemExt.html#tymethod.fixed_rate
+#[derive(Debug)]
+pub struct FixedRate<S> {
+    system: S,
+    step: Duration,
+    accumulator: Duration,
+}
+
+impl<S> FixedRate<S> {
+    fn new(system: S, step: Duration) -> Self {
+        assert!(
+            step > Duration::from_secs(0),
+            "`fixed_rate` requires a step above zero"
+        );
+        FixedRate {
+            system,
+            step,
+            accumulator: Duration::from_secs(0),
+        }
+    }
+}
+
+impl<'s, S> System<'s> for FixedRate<S>
+where
+    S::SystemData: SystemData<'s>,
+    S: System<'s>,
+{
+    type SystemData = Refetch<'s, S::SystemData>;
+
+    fn run(&mut self, data: Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("fixed_rate_system");
+
+        self.accumulator += data.world.fetch::<Time>().delta_time();
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            self.system.run(S::SystemData::fetch(data.world));
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+}
+
+impl<'a, 'b, SD, S> SystemDesc<'a, 'b, FixedRate<S>> for FixedRate<SD>
+where
+    SD: SystemDesc<'a, 'b, S>,
+    S: System<'a>,
+    S::SystemData: SystemData<'a>,
+{
+    fn build(self, world: &mut World) -> FixedRate<S> {
+        FixedRate {
+            system: self.system.build(world),
+            step: self.step,
+            accumulator: self.accumulator,
+        }
+    }
+}
+
+/// System data which declares the resources of `T` and `Time`, but lets the system fetch `T` as
+/// many times as it needs.
+#[doc(hidden)]
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct Refetch<'a, T> {
+    #[derivative(Debug = "ignore")]
+    world: &'a World,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T> SystemData<'a> for Refetch<'a, T>
+where
+    T: SystemData<'a>,
+{
+    fn setup(world: &mut World) {
+        <Read<'a, Time> as SystemData<'a>>::setup(world);
+        T::setup(world);
+    }
+
+    fn fetch(world: &'a World) -> Self {
+        Refetch {
+            world,
+            marker: PhantomData,
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        let mut reads = T::reads();
+        let time = ResourceId::new::<Time>();
+        if !reads.contains(&time) && !T::writes().contains(&time) {
+            reads.push(time);
+        }
+        reads
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        T::writes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::SystemExt;
+    use crate::{
+        ecs::prelude::{RunNow, System, World, WorldExt, Write},
+        Time,
+    };
+
+    struct CountRuns;
+
+    impl<'s> System<'s> for CountRuns {
+        type SystemData = Write<'s, u32>;
+
+        fn run(&mut self, mut runs: Self::SystemData) {
+            *runs += 1;
+        }
+    }
+
+    fn frames<S>(system: &mut S, delta: Duration, frames: u32) -> u32
+    where
+        S: for<'s> RunNow<'s>,
+    {
+        let mut world = World::new();
+        RunNow::setup(system, &mut world);
+        world.insert(0u32);
+        world.insert(Time::default());
+        for _ in 0..frames {
+            world.write_resource::<Time>().set_delta_time(delta);
+            system.run_now(&world);
+        }
+        let runs = *world.read_resource::<u32>();
+        runs
+    }
+
+    #[test]
+    fn run_every() {
+        let mut system = CountRuns.run_every(Duration::from_millis(100));
+        // Runs on the first, fourth and seventh frames.
+        assert_eq!(frames(&mut system, Duration::from_millis(30), 9), 3);
+    }
+
+    #[test]
+    fn run_every_n_frames() {
+        let mut system = CountRuns.run_every_n_frames(3);
+        assert_eq!(frames(&mut system, Duration::from_millis(10), 7), 3);
+    }
+
+    #[test]
+    fn run_if() {
+        let mut system = CountRuns.run_if(|enabled: &bool| *enabled);
+        let mut world = World::new();
+        RunNow::setup(&mut system, &mut world);
+        world.insert(0u32);
+
+        system.run_now(&world);
+        *world.write_resource::<bool>() = true;
+        system.run_now(&world);
+        system.run_now(&world);
+
+        assert_eq!(*world.read_resource::<u32>(), 2);
+    }
+
+    #[test]
+    fn fixed_rate() {
+        let mut system = CountRuns.fixed_rate(Duration::from_millis(10));
+        assert_eq!(frames(&mut system, Duration::from_millis(25), 4), 10);
+    }
+}
@@ -19,6 +19,8 @@ pub struct Time {
     fixed_time: Duration,
     /// The total number of frames that have been played in this session.
     frame_number: u64,
+    /// The total number of fixed updates that have been run in this session.
+    fixed_frame_number: u64,
     ///Time elapsed since game start, ignoring the speed multipler.
     absolute_real_time: Duration,
     ///Time elapsed since game start, taking the speed multiplier into account.
@@ -73,6 +75,14 @@ impl Time {
         self.frame_number
     }
 
+    /// Gets the total number of fixed updates that have been run in this session.
+    ///
+    /// Comparing this against a previously stored value tells whether a fixed update happened
+    /// since then.
+    pub fn fixed_frame_number(&self) -> u64 {
+        self.fixed_frame_number
+    }
+
     /// Gets the time since the start of the game, taking into account the speed multiplier.
     pub fn absolute_time(&self) -> Duration {
         self.absolute_time
@@ -180,6 +190,7 @@ impl Time {
     pub fn step_fixed_update(&mut self) -> bool {
         if self.fixed_time_accumulator >= self.fixed_seconds {
             self.fixed_time_accumulator -= self.fixed_seconds;
+            self.fixed_frame_number += 1;
             true
         } else {
             false
@@ -206,6 +217,7 @@ impl Default for Time {
             fixed_time: Duration::new(0, 16_666_666),
             fixed_time_accumulator: 0.0,
             frame_number: 0,
+            fixed_frame_number: 0,
             interpolation_alpha: 0.0,
             absolute_real_time: Duration::default(),
             absolute_time: Duration::default(),
@@ -405,6 +417,7 @@ mod tests {
         }
 
         assert_eq!(fixed_count, 120);
+        assert_eq!(time.fixed_frame_number(), 120);
     }
 
     // Test that fixed_update methods accumulate and return correctly
@@ -15,6 +15,9 @@ use crate::{
 /// Will register transform components, and the `TransformSystem`.
 /// `TransformSystem` will be registered with name "transform_system".
 ///
+/// When interpolation is enabled, the `TransformInterpolationSystem` is registered as well with
+/// name "transform_interpolation_system".
+///
 /// ## Errors
 ///
 /// No errors will be returned by this bundle.
@@ -26,6 +29,7 @@ use crate::{
 #[derive(Debug, Default)]
 pub struct TransformBundle<'a> {
     dep: &'a [&'a str],
+    interpolation: bool,
 }
 
 impl<'a> TransformBundle<'a> {
@@ -33,6 +37,7 @@ impl<'a> TransformBundle<'a> {
     pub fn new() -> Self {
         TransformBundle {
             dep: Default::default(),
+            interpolation: false,
         }
     }
 
@@ -41,6 +46,14 @@ impl<'a> TransformBundle<'a> {
         self.dep = dep;
         self
     }
+
+    /// Enable interpolation of global matrices between fixed updates
+    ///
+    /// Only entities with a `TransformInterpolation` component are interpolated.
+    pub fn with_interpolation(mut self) -> Self {
+        self.interpolation = true;
+        self
+    }
 }
 
 impl<'a, 'b, 'c> SystemBundle<'a, 'b> for TransformBundle<'c> {
@@ -59,6 +72,13 @@ impl<'a, 'b, 'c> SystemBundle<'a, 'b> for TransformBundle<'c> {
             "transform_system",
             &["parent_hierarchy_system"],
         );
+        if self.interpolation {
+            builder.add(
+                TransformInterpolationSystem::new(),
+                "transform_interpolation_system",
+                &["transform_system"],
+            );
+        }
         Ok(())
     }
 }
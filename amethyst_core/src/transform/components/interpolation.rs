@@ -0,0 +1,178 @@
+//! Fixed timestep interpolation component.
+use crate::{
+    ecs::prelude::{Component, DenseVecStorage},
+    math::{self as na, Matrix4},
+};
+
+use super::transform::decompose;
+
+/// Opt-in component which smooths the rendered position of an entity that is moved during
+/// `State::fixed_update`.
+///
+/// The `TransformInterpolationSystem` keeps the global matrices of the two most recent fixed
+/// updates and writes a blend of them, weighted by `Time::interpolation_alpha`, into the
+/// global matrix of the entity's `Transform`. The rendered result therefore lags one fixed update
+/// behind the simulation, but moves smoothly at any frame rate. Children of the entity follow
+/// its interpolated global matrix.
+///
+/// The system is only registered when `TransformBundle::with_interpolation` is used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransformInterpolation {
+    pub(crate) previous: Option<Matrix4<f32>>,
+    pub(crate) current: Option<Matrix4<f32>>,
+}
+
+impl TransformInterpolation {
+    /// Creates a new interpolation component with no recorded history.
+    pub fn new() -> Self {
+        TransformInterpolation {
+            previous: None,
+            current: None,
+        }
+    }
+
+    /// Discards the recorded history, so the next update renders the entity exactly at its
+    /// current transform.
+    ///
+    /// Call this after teleporting an entity to avoid it visibly sliding to its new position.
+    pub fn reset(&mut self) {
+        self.previous = None;
+        self.current = None;
+    }
+
+    /// Records the global matrix of the latest fixed update, shifting the former one into the
+    /// previous slot when `stepped` is true.
+    pub(crate) fn record(&mut self, global: Matrix4<f32>, stepped: bool) {
+        if stepped || self.previous.is_none() {
+            self.previous = self.current.or(Some(global));
+        }
+        self.current = Some(global);
+    }
+
+    /// Returns the matrix between the previous and the current fixed update at `alpha`.
+    pub(crate) fn interpolate(&self, alpha: f32) -> Option<Matrix4<f32>> {
+        match (self.previous, self.current) {
+            (Some(previous), Some(current)) => Some(interpolate_matrix(&previous, &current, alpha)),
+            (_, current) => current,
+        }
+    }
+}
+
+impl Default for TransformInterpolation {
+    fn default() -> Self {
+        TransformInterpolation::new()
+    }
+}
+
+impl Component for TransformInterpolation {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Interpolates between two affine matrices, assuming they are made of a translation, a
+/// rotation and a non-uniform scale.
+///
+/// Translation and scale are linearly interpolated while rotation is spherically interpolated.
+fn interpolate_matrix(from: &Matrix4<f32>, to: &Matrix4<f32>, alpha: f32) -> Matrix4<f32> {
+    let alpha = na::clamp(alpha, 0.0, 1.0);
+    let (from_translation, from_rotation, from_scale) = decompose(from);
+    let (to_translation, to_rotation, to_scale) = decompose(to);
+
+    let translation = from_translation.lerp(&to_translation, alpha);
+    let scale = from_scale.lerp(&to_scale, alpha);
+    let rotation = from_rotation
+        .try_slerp(&to_rotation, alpha, 1.0e-6)
+        .unwrap_or(to_rotation);
+
+    let mut result = rotation.to_homogeneous().prepend_nonuniform_scaling(&scale);
+    result
+        .fixed_slice_mut::<na::U3, na::U1>(0, 3)
+        .copy_from(&translation);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TransformInterpolation;
+    use crate::{
+        approx::*,
+        math::{UnitQuaternion, Vector3},
+        Transform,
+    };
+
+    #[test]
+    fn interpolates_between_fixed_updates() {
+        let mut from = Transform::default();
+        from.set_translation_xyz(0.0, 0.0, 0.0);
+        let mut to = Transform::default();
+        to.set_translation_xyz(10.0, 0.0, 0.0);
+        to.set_rotation(UnitQuaternion::from_euler_angles(0.0, 0.0, 1.0));
+        to.set_scale(Vector3::new(3.0, 3.0, 3.0));
+
+        let mut interpolation = TransformInterpolation::new();
+        interpolation.record(from.matrix(), true);
+        interpolation.record(to.matrix(), true);
+
+        let mut halfway = Transform::default();
+        halfway.set_translation_xyz(5.0, 0.0, 0.0);
+        halfway.set_rotation(UnitQuaternion::from_euler_angles(0.0, 0.0, 0.5));
+        halfway.set_scale(Vector3::new(2.0, 2.0, 2.0));
+
+        assert_relative_eq!(
+            interpolation.interpolate(0.5).unwrap(),
+            halfway.matrix(),
+            epsilon = 1.0e-5,
+        );
+        assert_relative_eq!(
+            interpolation.interpolate(1.0).unwrap(),
+            to.matrix(),
+            epsilon = 1.0e-5,
+        );
+    }
+
+    #[test]
+    fn interpolates_mirrored_transforms() {
+        let mut from = Transform::default();
+        from.set_scale(Vector3::new(-1.0, 1.0, 1.0));
+        let mut to = Transform::default();
+        to.set_rotation(UnitQuaternion::from_euler_angles(0.0, 0.0, 1.0));
+        to.set_scale(Vector3::new(-3.0, 3.0, 3.0));
+
+        let mut interpolation = TransformInterpolation::new();
+        interpolation.record(from.matrix(), true);
+        interpolation.record(from.matrix(), true);
+        assert_relative_eq!(
+            interpolation.interpolate(0.5).unwrap(),
+            from.matrix(),
+            epsilon = 1.0e-5,
+        );
+
+        interpolation.record(to.matrix(), true);
+        let mut halfway = Transform::default();
+        halfway.set_rotation(UnitQuaternion::from_euler_angles(0.0, 0.0, 0.5));
+        halfway.set_scale(Vector3::new(-2.0, 2.0, 2.0));
+        assert_relative_eq!(
+            interpolation.interpolate(0.5).unwrap(),
+            halfway.matrix(),
+            epsilon = 1.0e-5,
+        );
+    }
+
+    #[test]
+    fn keeps_previous_without_fixed_update() {
+        let mut from = Transform::default();
+        from.set_translation_xyz(1.0, 0.0, 0.0);
+        let mut to = Transform::default();
+        to.set_translation_xyz(3.0, 0.0, 0.0);
+
+        let mut interpolation = TransformInterpolation::new();
+        interpolation.record(from.matrix(), true);
+        interpolation.record(to.matrix(), true);
+        interpolation.record(to.matrix(), false);
+
+        assert_eq!(interpolation.previous, Some(from.matrix()));
+
+        interpolation.reset();
+        interpolation.record(to.matrix(), false);
+        assert_eq!(interpolation.interpolate(0.5), Some(to.matrix()));
+    }
+}
@@ -1,9 +1,13 @@
 //! Components for the transform processor.
 
 pub use self::{
+    interpolation::TransformInterpolation,
     parent::{HierarchyEvent, Parent, ParentHierarchy},
     transform::Transform,
 };
 
+pub(crate) use self::transform::decompose;
+
+mod interpolation;
 mod parent;
 mod transform;
@@ -3,8 +3,8 @@ use crate::{
     alga::general::SubsetOf,
     ecs::prelude::{Component, DenseVecStorage, FlaggedStorage},
     math::{
-        self as na, Isometry3, Matrix4, Quaternion, RealField, Translation3, Unit, UnitQuaternion,
-        Vector3,
+        self as na, Isometry3, Matrix3, Matrix4, Quaternion, RealField, Rotation3, Translation3,
+        Unit, UnitQuaternion, Vector3,
     },
 };
 use serde::{Deserialize, Serialize};
@@ -609,6 +609,33 @@ impl From<Vector3<f64>> for Transform {
     }
 }
 
+/// Splits an affine matrix into its translation, rotation and scale, assuming it is made of a
+/// translation, a rotation and a non-uniform scale applied in this order.
+///
+/// The scale of a mirrored matrix is negative along the X axis.
+pub(crate) fn decompose(
+    matrix: &Matrix4<f32>,
+) -> (Vector3<f32>, UnitQuaternion<f32>, Vector3<f32>) {
+    let translation = matrix.column(3).xyz();
+    let mut basis: Matrix3<f32> = matrix.fixed_slice::<na::U3, na::U3>(0, 0).into_owned();
+    let mut scale = Vector3::new(
+        basis.column(0).norm(),
+        basis.column(1).norm(),
+        basis.column(2).norm(),
+    );
+    // A mirrored basis can't be a rotation, the mirror is kept in the scale of the X axis.
+    if basis.determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+    for (i, s) in scale.iter().enumerate() {
+        if *s != 0.0 {
+            basis.column_mut(i).unscale_mut(*s);
+        }
+    }
+    let rotation = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(basis));
+    (translation, rotation, scale)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename = "Transform", default)]
 struct TransformValues {
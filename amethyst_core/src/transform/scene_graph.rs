@@ -0,0 +1,442 @@
+//! Scene graph queries and world space operations.
+
+use amethyst_error::{format_err, Error};
+
+use std::{iter::Cloned, slice::Iter};
+
+use specs_hierarchy::SubHierarchyIterator;
+
+use crate::{
+    ecs::{
+        prelude::{Entity, ReadExpect, ReadStorage, WriteStorage},
+        shred::{ResourceId, SystemData},
+        World,
+    },
+    math::{Matrix4, Point3, UnitQuaternion, Vector3},
+    transform::{components::decompose, Parent, ParentHierarchy, Transform},
+};
+
+/// Hierarchy queries and world space conversions shared by [`SceneGraph`] and
+/// [`SceneGraphMut`].
+///
+/// [`SceneGraph`]: struct.SceneGraph.html
+/// [`SceneGraphMut`]: struct.SceneGraphMut.html
+pub trait SceneGraphQuery {
+    /// Returns the `ParentHierarchy` children and descendants are read from.
+    fn hierarchy(&self) -> &ParentHierarchy;
+
+    /// Returns the parent of `entity`, read from its `Parent` component.
+    fn parent(&self, entity: Entity) -> Option<Entity>;
+
+    /// Returns the `Transform` of `entity`.
+    fn transform(&self, entity: Entity) -> Option<&Transform>;
+
+    /// Returns an iterator over the immediate children of `entity`.
+    fn children(&self, entity: Entity) -> Cloned<Iter<'_, Entity>> {
+        self.hierarchy().children(entity).iter().cloned()
+    }
+
+    /// Returns an iterator over all the descendants of `entity`, parents come before their
+    /// children.
+    fn descendants(&self, entity: Entity) -> SubHierarchyIterator<'_, Parent> {
+        self.hierarchy().all_children_iter(entity)
+    }
+
+    /// Returns an iterator over the ancestors of `entity`, starting with its parent.
+    ///
+    /// The iteration ends if the `Parent`s form a cycle.
+    fn ancestors(&self, entity: Entity) -> Ancestors<'_, Self>
+    where
+        Self: Sized,
+    {
+        Ancestors {
+            graph: self,
+            next: self.parent(entity),
+            slow: Some(entity),
+            odd: false,
+        }
+    }
+
+    /// Returns the matrix transforming the local space of `entity` into world space.
+    ///
+    /// Returns `None` if `entity` has no `Transform`.
+    fn world_matrix(&self, entity: Entity) -> Option<Matrix4<f32>>
+    where
+        Self: Sized,
+    {
+        let mut matrix = self.transform(entity)?.matrix();
+        for ancestor in self.ancestors(entity) {
+            match self.transform(ancestor) {
+                Some(transform) => matrix = transform.matrix() * matrix,
+                None => break,
+            }
+        }
+        Some(matrix)
+    }
+
+    /// Converts a point from the local space of `entity` into world space.
+    fn local_to_world(&self, entity: Entity, point: Point3<f32>) -> Option<Point3<f32>>
+    where
+        Self: Sized,
+    {
+        self.world_matrix(entity)
+            .map(|matrix| matrix.transform_point(&point))
+    }
+
+    /// Converts a point from world space into the local space of `entity`.
+    fn world_to_local(&self, entity: Entity, point: Point3<f32>) -> Option<Point3<f32>>
+    where
+        Self: Sized,
+    {
+        self.world_matrix(entity)
+            .and_then(|matrix| matrix.try_inverse())
+            .map(|matrix| matrix.transform_point(&point))
+    }
+
+    /// Converts a point from the local space of `from` into the local space of `to`.
+    fn convert_point(&self, from: Entity, to: Entity, point: Point3<f32>) -> Option<Point3<f32>>
+    where
+        Self: Sized,
+    {
+        self.local_to_world(from, point)
+            .and_then(|point| self.world_to_local(to, point))
+    }
+}
+
+/// Iterator over the ancestors of an entity, see `SceneGraphQuery::ancestors`.
+#[allow(missing_debug_implementations)]
+pub struct Ancestors<'s, G> {
+    graph: &'s G,
+    next: Option<Entity>,
+    /// Follows the parents at half the speed, meeting `next` if they form a cycle.
+    slow: Option<Entity>,
+    odd: bool,
+}
+
+impl<'s, G: SceneGraphQuery> Iterator for Ancestors<'s, G> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Entity> {
+        let entity = self.next?;
+        self.next = self.graph.parent(entity);
+        if self.odd {
+            self.slow = self.slow.and_then(|slow| self.graph.parent(slow));
+        }
+        self.odd = !self.odd;
+        if self.next.is_some() && self.next == self.slow {
+            self.next = None;
+        }
+        Some(entity)
+    }
+}
+
+/// Read access to the scene graph formed by `Parent` and `Transform` components.
+///
+/// World space values are computed from the local `Transform`s, so they are correct even when
+/// the `TransformSystem` has not run yet this frame. As in the `TransformSystem`, an ancestor
+/// without a `Transform` ends the chain of transformations.
+///
+/// Children and descendants are read from the `ParentHierarchy`, which is only updated when the
+/// hierarchy system runs.
+///
+/// The queries are provided by the [`SceneGraphQuery`] trait.
+///
+/// [`SceneGraphQuery`]: trait.SceneGraphQuery.html
+#[derive(SystemData)]
+#[allow(missing_debug_implementations)]
+pub struct SceneGraph<'a> {
+    hierarchy: ReadExpect<'a, ParentHierarchy>,
+    parents: ReadStorage<'a, Parent>,
+    transforms: ReadStorage<'a, Transform>,
+}
+
+impl<'a> SceneGraphQuery for SceneGraph<'a> {
+    fn hierarchy(&self) -> &ParentHierarchy {
+        &self.hierarchy
+    }
+
+    fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.parents.get(entity).map(|parent| parent.entity)
+    }
+
+    fn transform(&self, entity: Entity) -> Option<&Transform> {
+        self.transforms.get(entity)
+    }
+}
+
+/// Write access to the scene graph formed by `Parent` and `Transform` components.
+///
+/// Provides the same queries as [`SceneGraph`] through [`SceneGraphQuery`], as well as
+/// operations which modify `Parent` and `Transform` components using world space values.
+///
+/// [`SceneGraph`]: struct.SceneGraph.html
+/// [`SceneGraphQuery`]: trait.SceneGraphQuery.html
+#[derive(SystemData)]
+#[allow(missing_debug_implementations)]
+pub struct SceneGraphMut<'a> {
+    hierarchy: ReadExpect<'a, ParentHierarchy>,
+    parents: WriteStorage<'a, Parent>,
+    transforms: WriteStorage<'a, Transform>,
+}
+
+impl<'a> SceneGraphQuery for SceneGraphMut<'a> {
+    fn hierarchy(&self) -> &ParentHierarchy {
+        &self.hierarchy
+    }
+
+    fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.parents.get(entity).map(|parent| parent.entity)
+    }
+
+    fn transform(&self, entity: Entity) -> Option<&Transform> {
+        self.transforms.get(entity)
+    }
+}
+
+impl<'a> SceneGraphMut<'a> {
+    /// Attaches `entity` to `parent`, or detaches it when `parent` is `None`, while keeping it at
+    /// the same position, rotation and scale in world space.
+    ///
+    /// Scale is only preserved exactly when it is uniform, shear cannot be represented by a
+    /// `Transform`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `entity` has no `Transform`, or if `parent` is `entity` itself or one
+    /// of its descendants.
+    pub fn reparent(&mut self, entity: Entity, parent: Option<Entity>) -> Result<(), Error> {
+        if let Some(parent) = parent {
+            if parent == entity || self.ancestors(parent).any(|e| e == entity) {
+                return Err(format_err!(
+                    "Cannot attach entity {:?} to its own descendant {:?}",
+                    entity,
+                    parent
+                ));
+            }
+        }
+
+        let world = self
+            .world_matrix(entity)
+            .ok_or_else(|| format_err!("Entity {:?} has no `Transform`", entity))?;
+        let parent_world = parent
+            .and_then(|parent| self.world_matrix(parent))
+            .unwrap_or_else(Matrix4::identity);
+        let local = parent_world
+            .try_inverse()
+            .ok_or_else(|| format_err!("The transform of {:?} is not invertible", parent))?
+            * world;
+
+        let (translation, rotation, scale) = decompose(&local);
+        let transform = self
+            .transforms
+            .get_mut(entity)
+            .expect("unreachable: the world matrix was computed from this transform");
+        transform.set_translation(translation);
+        transform.set_rotation(rotation);
+        transform.set_scale(scale);
+
+        match parent {
+            Some(parent) => {
+                self.parents.insert(entity, Parent::new(parent))?;
+            }
+            None => {
+                self.parents.remove(entity);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rotates `entity` so that it faces `target`, given in world space.
+    ///
+    /// `up` is the world space direction the entity should be 'rolled' towards. Unlike
+    /// `Transform::face_towards`, this takes the transforms of the entity's ancestors into
+    /// account.
+    ///
+    /// Returns `false` if `entity` has no `Transform`.
+    pub fn look_at(&mut self, entity: Entity, target: Point3<f32>, up: Vector3<f32>) -> bool {
+        let position = match self.local_to_world(entity, Point3::origin()) {
+            Some(position) => position,
+            None => return false,
+        };
+        let world_rotation = UnitQuaternion::face_towards(&(position - target), &up);
+        let parent_rotation = self
+            .parents
+            .get(entity)
+            .and_then(|parent| self.world_matrix(parent.entity))
+            .map(|matrix| decompose(&matrix).1)
+            .unwrap_or_else(UnitQuaternion::identity);
+
+        if let Some(transform) = self.transforms.get_mut(entity) {
+            transform.set_rotation(parent_rotation.inverse() * world_rotation);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs_hierarchy::HierarchySystem;
+
+    use super::{SceneGraph, SceneGraphMut, SceneGraphQuery};
+    use crate::{
+        approx::*,
+        ecs::prelude::{Builder, RunNow, World, WorldExt},
+        math::{Point3, Vector3},
+        transform::{Parent, Transform},
+    };
+
+    fn scene() -> (World, HierarchySystem<Parent>) {
+        let mut world = World::new();
+        let mut hs = HierarchySystem::<Parent>::new(&mut world);
+        RunNow::setup(&mut hs, &mut world);
+        world.register::<Transform>();
+        (world, hs)
+    }
+
+    #[test]
+    fn walks_hierarchy() {
+        let (mut world, mut hs) = scene();
+        let root = world.create_entity().with(Transform::default()).build();
+        let child = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Parent::new(root))
+            .build();
+        let grandchild = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Parent::new(child))
+            .build();
+        hs.run_now(&world);
+
+        let graph = world.system_data::<SceneGraph<'_>>();
+        assert_eq!(graph.children(root).collect::<Vec<_>>(), vec![child]);
+        assert_eq!(
+            graph.descendants(root).collect::<Vec<_>>(),
+            vec![child, grandchild]
+        );
+        assert_eq!(
+            graph.ancestors(grandchild).collect::<Vec<_>>(),
+            vec![child, root]
+        );
+    }
+
+    #[test]
+    fn ancestors_end_on_cycle() {
+        let (mut world, _) = scene();
+        let a = world.create_entity().with(Transform::default()).build();
+        let b = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Parent::new(a))
+            .build();
+        let c = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Parent::new(b))
+            .build();
+        world
+            .write_storage::<Parent>()
+            .insert(a, Parent::new(c))
+            .unwrap();
+
+        let graph = world.system_data::<SceneGraph<'_>>();
+        assert_eq!(graph.ancestors(c).collect::<Vec<_>>(), vec![b, a, c]);
+        assert!(graph.world_matrix(c).is_some());
+    }
+
+    #[test]
+    fn converts_points_between_entities() {
+        let (mut world, _) = scene();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(1.0, 0.0, 0.0);
+        transform.set_scale(Vector3::new(2.0, 2.0, 2.0));
+        let a = world.create_entity().with(transform).build();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 3.0, 0.0);
+        let b = world
+            .create_entity()
+            .with(transform)
+            .with(Parent::new(a))
+            .build();
+        let c = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(0.0, 0.0, 5.0)))
+            .build();
+
+        let graph = world.system_data::<SceneGraph<'_>>();
+        assert_relative_eq!(
+            graph.local_to_world(b, Point3::origin()).unwrap(),
+            Point3::new(1.0, 6.0, 0.0)
+        );
+        assert_relative_eq!(
+            graph.convert_point(b, c, Point3::origin()).unwrap(),
+            Point3::new(1.0, 6.0, -5.0)
+        );
+    }
+
+    #[test]
+    fn reparent_keeps_world_position() {
+        let (mut world, _) = scene();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(10.0, 0.0, 0.0);
+        transform.set_rotation_y_axis(1.0);
+        let parent = world.create_entity().with(transform).build();
+        let child = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(1.0, 2.0, 3.0)))
+            .build();
+
+        let mut graph = world.system_data::<SceneGraphMut<'_>>();
+        let before = graph.world_matrix(child).unwrap();
+        graph.reparent(child, Some(parent)).unwrap();
+        assert_relative_eq!(graph.world_matrix(child).unwrap(), before, epsilon = 1.0e-5);
+        assert!(graph.reparent(parent, Some(child)).is_err());
+        graph.reparent(child, None).unwrap();
+        assert_relative_eq!(graph.world_matrix(child).unwrap(), before, epsilon = 1.0e-5);
+    }
+
+    #[test]
+    fn reparent_keeps_mirror() {
+        let (mut world, _) = scene();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 4.0, 0.0);
+        transform.set_rotation_z_axis(1.0);
+        let parent = world.create_entity().with(transform).build();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(1.0, 0.0, 0.0);
+        transform.set_scale(Vector3::new(-1.0, 2.0, 1.0));
+        let child = world.create_entity().with(transform).build();
+
+        let mut graph = world.system_data::<SceneGraphMut<'_>>();
+        let before = graph.world_matrix(child).unwrap();
+        graph.reparent(child, Some(parent)).unwrap();
+        assert_relative_eq!(graph.world_matrix(child).unwrap(), before, epsilon = 1.0e-5);
+        graph.reparent(child, None).unwrap();
+        assert_relative_eq!(graph.world_matrix(child).unwrap(), before, epsilon = 1.0e-5);
+    }
+
+    #[test]
+    fn look_at_world_target() {
+        let (mut world, _) = scene();
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 0.0, 5.0);
+        transform.set_rotation_y_axis(2.0);
+        let parent = world.create_entity().with(transform).build();
+        let child = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Parent::new(parent))
+            .build();
+
+        let mut graph = world.system_data::<SceneGraphMut<'_>>();
+        let target = Point3::new(0.0, 0.0, -5.0);
+        assert!(graph.look_at(child, target, Vector3::y()));
+
+        // Moving forward along the local -Z axis gets closer to the target.
+        let ahead = graph
+            .local_to_world(child, Point3::new(0.0, 0.0, -10.0))
+            .unwrap();
+        assert_relative_eq!(ahead, target, epsilon = 1.0e-4);
+    }
+}
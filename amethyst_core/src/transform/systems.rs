@@ -1,17 +1,24 @@
 //! Scene graph system and types
 
+use fnv::FnvHashMap;
+use rayon::prelude::*;
+
 use crate::{
     ecs::{
         hibitset::BitSet,
         prelude::{
-            ComponentEvent, Entities, Join, ReadExpect, ReadStorage, ReaderId, System, SystemData,
-            World, WriteStorage,
+            ComponentEvent, Entities, Entity, Join, ParJoin, Read, ReadExpect, ReadStorage,
+            ReaderId, System, SystemData, World, WriteStorage,
         },
     },
-    SystemDesc,
+    math::Matrix4,
+    timing::Time,
+    ArcThreadPool, SystemDesc,
 };
 
-use crate::transform::{HierarchyEvent, Parent, ParentHierarchy, Transform};
+use crate::transform::{
+    HierarchyEvent, Parent, ParentHierarchy, Transform, TransformInterpolation,
+};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
@@ -34,11 +41,21 @@ impl<'a, 'b> SystemDesc<'a, 'b, TransformSystem> for TransformSystemDesc {
 }
 
 /// Handles updating `global_matrix` field from `Transform` components.
+///
+/// Entities without a parent are updated in parallel. Entities with a parent are grouped by their
+/// depth in the hierarchy, and each depth layer is updated in parallel once the layer above it is
+/// done. Only entities whose `Transform` or ancestors changed are recomputed.
+///
+/// The `ArcThreadPool` resource is used when it is present, otherwise the global rayon pool is.
 #[derive(Debug)]
 pub struct TransformSystem {
     local_modified: BitSet,
     locals_events_id: ReaderId<ComponentEvent>,
     parent_events_id: ReaderId<HierarchyEvent>,
+    /// Entities with a parent, grouped by their depth in the hierarchy.
+    layers: Vec<Vec<Entity>>,
+    /// Whether the hierarchy changed since `layers` was built.
+    layers_outdated: bool,
 }
 
 impl TransformSystem {
@@ -51,6 +68,94 @@ impl TransformSystem {
             local_modified: BitSet::default(),
             locals_events_id,
             parent_events_id,
+            layers: Vec::new(),
+            layers_outdated: true,
+        }
+    }
+
+    /// Groups the entities of the hierarchy by depth. `ParentHierarchy::all` is sorted so that
+    /// parents always come before their children.
+    fn rebuild_layers(&mut self, hierarchy: &ParentHierarchy) {
+        self.layers.iter_mut().for_each(Vec::clear);
+
+        let mut depths = FnvHashMap::default();
+        for entity in hierarchy.all() {
+            let depth = hierarchy
+                .parent(*entity)
+                .and_then(|parent| depths.get(&parent))
+                .map_or(0, |depth| depth + 1);
+            depths.insert(*entity, depth);
+
+            if self.layers.len() <= depth {
+                self.layers.resize_with(depth + 1, Vec::new);
+            }
+            self.layers[depth].push(*entity);
+        }
+
+        let depth_count = self
+            .layers
+            .iter()
+            .rposition(|layer| !layer.is_empty())
+            .map_or(0, |depth| depth + 1);
+        self.layers.truncate(depth_count);
+        self.layers_outdated = false;
+    }
+
+    fn propagate(
+        &mut self,
+        entities: &Entities<'_>,
+        locals: &mut WriteStorage<'_, Transform>,
+        parents: &ReadStorage<'_, Parent>,
+    ) {
+        // Compute transforms without parents.
+        let root_transforms = (&**entities, &self.local_modified, &*locals, !parents)
+            .par_join()
+            .map(|(entity, _, local, _)| {
+                let global_matrix = local.matrix();
+                debug_assert!(
+                    global_matrix.iter().all(|f| f32::is_finite(*f)),
+                    "Entity {:?} had a non-finite `Transform` {:?}",
+                    entity,
+                    local
+                );
+                (entity, global_matrix)
+            })
+            .collect::<Vec<(Entity, Matrix4<f32>)>>();
+        for (entity, global_matrix) in root_transforms {
+            locals
+                .get_mut(entity)
+                .expect("unreachable: We know this entity has a local because it was just joined.")
+                .global_matrix = global_matrix;
+        }
+
+        // Compute transforms with parents, one depth at a time.
+        for layer in &self.layers {
+            let combined_transforms = {
+                let local_modified = &self.local_modified;
+                let locals = &*locals;
+                layer
+                    .par_iter()
+                    .filter_map(|entity| {
+                        let parent = parents.get(*entity)?;
+                        if !local_modified.contains(entity.id())
+                            && !local_modified.contains(parent.entity.id())
+                        {
+                            return None;
+                        }
+                        let local = locals.get(*entity)?;
+                        let combined_transform = match locals.get(parent.entity) {
+                            Some(parent_global) => parent_global.global_matrix * local.matrix(),
+                            None => local.matrix(),
+                        };
+                        Some((*entity, combined_transform))
+                    })
+                    .collect::<Vec<(Entity, Matrix4<f32>)>>()
+            };
+
+            for (entity, combined_transform) in combined_transforms {
+                self.local_modified.add(entity.id());
+                locals.get_mut(entity).expect("unreachable: We know this entity has a local because is was just modified.").global_matrix = combined_transform;
+            }
         }
     }
 }
@@ -61,8 +166,9 @@ impl<'a> System<'a> for TransformSystem {
         ReadExpect<'a, ParentHierarchy>,
         WriteStorage<'a, Transform>,
         ReadStorage<'a, Parent>,
+        Option<Read<'a, ArcThreadPool>>,
     );
-    fn run(&mut self, (entities, hierarchy, mut locals, parents): Self::SystemData) {
+    fn run(&mut self, (entities, hierarchy, mut locals, parents, pool): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("transform_system");
 
@@ -79,6 +185,7 @@ impl<'a> System<'a> for TransformSystem {
             });
 
         for event in hierarchy.changed().read(&mut self.parent_events_id) {
+            self.layers_outdated = true;
             match *event {
                 HierarchyEvent::Removed(entity) => {
                     // Sometimes the user may have already deleted the entity.
@@ -92,47 +199,13 @@ impl<'a> System<'a> for TransformSystem {
             }
         }
 
-        let mut modified = vec![];
-        // Compute transforms without parents.
-        for (entity, _, local, _) in
-            (&*entities, &self.local_modified, &mut locals, !&parents).join()
-        {
-            modified.push(entity.id());
-            local.global_matrix = local.matrix();
-            debug_assert!(
-                local.is_finite(),
-                format!(
-                    "Entity {:?} had a non-finite `Transform` {:?}",
-                    entity, local
-                )
-            );
+        if self.layers_outdated {
+            self.rebuild_layers(&hierarchy);
         }
-        modified.into_iter().for_each(|id| {
-            self.local_modified.add(id);
-        });
 
-        // Compute transforms with parents.
-        for entity in hierarchy.all() {
-            let self_dirty = self.local_modified.contains(entity.id());
-            if let Some(parent) = parents.get(*entity) {
-                let parent_dirty = self.local_modified.contains(parent.entity.id());
-                if parent_dirty || self_dirty {
-                    let combined_transform = {
-                        let local = locals.get(*entity);
-                        if local.is_none() {
-                            continue;
-                        }
-                        let local = local.unwrap();
-                        if let Some(parent_global) = locals.get(parent.entity) {
-                            (parent_global.global_matrix * local.matrix())
-                        } else {
-                            local.matrix()
-                        }
-                    };
-                    self.local_modified.add(entity.id());
-                    locals.get_mut(*entity).expect("unreachable: We know this entity has a local because is was just modified.").global_matrix = combined_transform;
-                }
-            }
+        match pool {
+            Some(pool) => pool.install(|| self.propagate(&entities, &mut locals, &parents)),
+            None => self.propagate(&entities, &mut locals, &parents),
         }
 
         // Clear the local event reader.
@@ -140,6 +213,75 @@ impl<'a> System<'a> for TransformSystem {
     }
 }
 
+/// Writes interpolated global matrices for entities with a `TransformInterpolation` component.
+///
+/// Must run after the `TransformSystem`. Overwriting the global matrix flags the `Transform` as
+/// modified, which makes the `TransformSystem` recompute the real global matrix on the next
+/// frame before it is interpolated again.
+///
+/// Descendants of an interpolated entity follow its interpolated global matrix. Descendants with
+/// their own `TransformInterpolation` are interpolated from their own history instead, and their
+/// descendants follow them.
+#[derive(Debug, Default)]
+pub struct TransformInterpolationSystem {
+    last_fixed_frame: u64,
+}
+
+impl TransformInterpolationSystem {
+    /// Creates a new transform interpolation system.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<'a> System<'a> for TransformInterpolationSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        ReadExpect<'a, ParentHierarchy>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, TransformInterpolation>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, time, hierarchy, mut locals, mut interpolations): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("transform_interpolation_system");
+
+        let stepped = time.fixed_frame_number() != self.last_fixed_frame;
+        self.last_fixed_frame = time.fixed_frame_number();
+        let alpha = time.interpolation_alpha();
+
+        let mut corrections = Vec::new();
+        for (entity, local, interpolation) in (&entities, &mut locals, &mut interpolations).join() {
+            interpolation.record(local.global_matrix, stepped);
+            if let Some(global_matrix) = interpolation.interpolate(alpha) {
+                if let Some(inverse) = local.global_matrix.try_inverse() {
+                    corrections.push((entity, global_matrix * inverse));
+                }
+                local.global_matrix = global_matrix;
+            }
+        }
+
+        // Moves the descendants by the offset between the real and the interpolated matrix.
+        let mut stack = Vec::new();
+        for (entity, correction) in corrections {
+            stack.extend_from_slice(hierarchy.children(entity));
+            while let Some(child) = stack.pop() {
+                if interpolations.contains(child) {
+                    continue;
+                }
+                if let Some(local) = locals.get_mut(child) {
+                    local.global_matrix = correction * local.global_matrix;
+                }
+                stack.extend_from_slice(hierarchy.children(child));
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -148,8 +290,11 @@ mod tests {
             shred::RunNow,
         },
         math::{Matrix4, Quaternion, Unit, Vector3},
-        transform::{Parent, Transform, TransformSystem, TransformSystemDesc},
-        SystemDesc,
+        transform::{
+            Parent, Transform, TransformInterpolation, TransformInterpolationSystem,
+            TransformSystem, TransformSystemDesc,
+        },
+        SystemDesc, Time,
     };
     use specs_hierarchy::{Hierarchy, HierarchySystem};
 
@@ -486,4 +631,126 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn dirty_subtree_and_reparent() {
+        let (mut world, mut hs, mut system) = transform_world();
+
+        let e1 = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(1.0, 0.0, 0.0)))
+            .build();
+        let e2 = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(0.0, 1.0, 0.0)))
+            .with(Parent { entity: e1 })
+            .build();
+        let e3 = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(0.0, 0.0, 1.0)))
+            .with(Parent { entity: e2 })
+            .build();
+        let e4 = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(5.0, 0.0, 0.0)))
+            .build();
+
+        hs.run_now(&world);
+        system.run_now(&world);
+        world.maintain();
+
+        let translation = |world: &World, entity| {
+            world
+                .read_storage::<Transform>()
+                .get(entity)
+                .unwrap()
+                .global_matrix()
+                .column(3)
+                .xyz()
+        };
+        assert_eq!(translation(&world, e3), Vector3::new(1.0, 1.0, 1.0));
+
+        // Moving the middle of the hierarchy only moves its subtree.
+        world
+            .write_storage::<Transform>()
+            .get_mut(e2)
+            .unwrap()
+            .set_translation_y(2.0);
+        hs.run_now(&world);
+        system.run_now(&world);
+        world.maintain();
+        assert_eq!(translation(&world, e1), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(translation(&world, e3), Vector3::new(1.0, 2.0, 1.0));
+
+        // Moving `e2` under another root changes the depth layers.
+        world
+            .write_storage::<Parent>()
+            .insert(e2, Parent { entity: e4 })
+            .unwrap();
+        hs.run_now(&world);
+        system.run_now(&world);
+        world.maintain();
+        assert_eq!(translation(&world, e2), Vector3::new(5.0, 2.0, 0.0));
+        assert_eq!(translation(&world, e3), Vector3::new(5.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn interpolation() {
+        let (mut world, mut hs, mut system) = transform_world();
+        let mut interpolation_system = TransformInterpolationSystem::new();
+        interpolation_system.setup(&mut world);
+        world.insert(Time::default());
+
+        let e1 = world
+            .create_entity()
+            .with(Transform::default())
+            .with(TransformInterpolation::new())
+            .build();
+        let e2 = world
+            .create_entity()
+            .with(Transform::from(Vector3::new(1.0, 0.0, 0.0)))
+            .with(Parent { entity: e1 })
+            .build();
+
+        let mut frame = |world: &mut World, x: Option<f32>, fixed_steps: u32, remainder: f32| {
+            if let Some(x) = x {
+                world
+                    .write_storage::<Transform>()
+                    .get_mut(e1)
+                    .unwrap()
+                    .set_translation_x(x);
+            }
+            {
+                let mut time = world.write_resource::<Time>();
+                let fixed_seconds = time.fixed_seconds();
+                time.set_delta_seconds(fixed_seconds * (fixed_steps as f32 + remainder) - 1.0e-6);
+                time.start_fixed_update();
+                while time.step_fixed_update() {}
+                time.finish_fixed_update();
+            }
+            hs.run_now(world);
+            system.run_now(world);
+            interpolation_system.run_now(world);
+            world.maintain();
+            let transforms = world.read_storage::<Transform>();
+            let x = |entity| transforms.get(entity).unwrap().global_matrix()[(0, 3)];
+            (x(e1), x(e2))
+        };
+
+        assert_eq!(frame(&mut world, None, 1, 0.0), (0.0, 1.0));
+        let (x, child_x) = frame(&mut world, Some(4.0), 1, 0.5);
+        assert!((x - 2.0).abs() < 0.01, "expected 2.0, got {}", x);
+        assert!(
+            (child_x - 3.0).abs() < 0.01,
+            "expected 3.0, got {}",
+            child_x
+        );
+        let (x, child_x) = frame(&mut world, None, 0, 0.5);
+        assert!((x - 4.0).abs() < 0.01, "expected 4.0, got {}", x);
+        assert!(
+            (child_x - 5.0).abs() < 0.01,
+            "expected 5.0, got {}",
+            child_x
+        );
+    }
 }
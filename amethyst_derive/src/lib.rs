@@ -59,6 +59,10 @@ pub fn widget_id_derive(input: TokenStream) -> TokenStream {
 /// dispatcher.
 ///
 /// This derive may be used for `System`s that do not require special code for `System::setup`.
+///
+/// A `ReaderId<E>` field tagged `#[system_desc(event_bus_reader)]` is registered on the
+/// `EventChannel<E>` of the event bus, inserting the channel and the `EventQueue<E>` if they
+/// don't exist yet. `EventChannel` and `EventQueue` must be in scope.
 #[proc_macro_derive(SystemDesc, attributes(system_desc))]
 pub fn system_desc_derive(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
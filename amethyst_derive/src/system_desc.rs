@@ -152,6 +152,8 @@ fn system_desc_fields(ast: &DeriveInput) -> SystemDescFields<'_> {
                 FieldVariant::Skipped(field)
             } else if field.contains_tag("system_desc", "event_channel_reader") {
                 FieldVariant::Compute(FieldToCompute::ReaderId(field))
+            } else if field.contains_tag("system_desc", "event_bus_reader") {
+                FieldVariant::Compute(FieldToCompute::EventBusReaderId(field))
             } else if field.is_phantom_data() {
                 let field_variant = FieldVariant::PhantomData {
                     system_desc_field_index,
@@ -378,7 +380,8 @@ fn call_system_constructor(context: &Context<'_>) -> TokenStream {
                         .iter()
                         .filter_map(|field_mapping| match &field_mapping.field_variant {
                             FieldVariant::Skipped(..) => None,
-                            FieldVariant::Compute(FieldToCompute::ReaderId(field)) => {
+                            FieldVariant::Compute(field_to_compute) => {
+                                let field = field_to_compute.field();
                                 let field_name =
                                     field.ident.clone().unwrap_or_else(|| snake_case(field));
                                 Some(quote!(#field_name))
@@ -419,7 +422,8 @@ fn call_system_constructor(context: &Context<'_>) -> TokenStream {
                     .iter()
                     .filter_map(|field_mapping| match &field_mapping.field_variant {
                         FieldVariant::Skipped(..) => None,
-                        FieldVariant::Compute(FieldToCompute::ReaderId(field)) => {
+                        FieldVariant::Compute(field_to_compute) => {
+                            let field = field_to_compute.field();
                             let field_name = snake_case(field);
                             Some(quote!(#field_name))
                         }
@@ -462,7 +466,8 @@ fn call_system_constructor(context: &Context<'_>) -> TokenStream {
                         .iter()
                         .filter_map(|field_mapping| match &field_mapping.field_variant {
                             FieldVariant::Skipped(..) => None,
-                            FieldVariant::Compute(FieldToCompute::ReaderId(field)) => {
+                            FieldVariant::Compute(field_to_compute) => {
+                                let field = field_to_compute.field();
                                 let field_name = field
                                     .ident
                                     .as_ref()
@@ -488,7 +493,8 @@ fn call_system_constructor(context: &Context<'_>) -> TokenStream {
                         .iter()
                         .filter_map(|field_mapping| match &field_mapping.field_variant {
                             FieldVariant::Skipped(..) => None,
-                            FieldVariant::Compute(FieldToCompute::ReaderId(field)) => {
+                            FieldVariant::Compute(field_to_compute) => {
+                                let field = field_to_compute.field();
                                 let field_name = field
                                     .ident
                                     .as_ref()
@@ -689,10 +695,11 @@ fn field_computation_expressions(system_desc_fields: &SystemDescFields<'_>) -> T
         TokenStream::new(),
         |mut token_stream, field_mapping| {
             if let FieldMapping {
-                field_variant: FieldVariant::Compute(FieldToCompute::ReaderId(field)),
+                field_variant: FieldVariant::Compute(field_to_compute),
                 ..
             } = field_mapping
             {
+                let field = field_to_compute.field();
                 let field_name = field.ident.clone().unwrap_or_else(|| snake_case(field));
                 let event_type_path = if let Type::Path(TypePath {
                     path: Path { segments, .. },
@@ -726,10 +733,21 @@ fn field_computation_expressions(system_desc_fields: &SystemDescFields<'_>) -> T
                 } else {
                     panic!("Expected `{}` field type to be `Type::Path`.", &field_name)
                 };
-                let tokens = quote! {
-                    let #field_name = world
-                        .fetch_mut::<EventChannel<#event_type_path>>()
-                        .register_reader();
+                let tokens = match field_to_compute {
+                    FieldToCompute::ReaderId(..) => quote! {
+                        let #field_name = world
+                            .fetch_mut::<EventChannel<#event_type_path>>()
+                            .register_reader();
+                    },
+                    FieldToCompute::EventBusReaderId(..) => quote! {
+                        world
+                            .entry::<EventQueue<#event_type_path>>()
+                            .or_insert_with(Default::default);
+                        let #field_name = world
+                            .entry::<EventChannel<#event_type_path>>()
+                            .or_insert_with(Default::default)
+                            .register_reader();
+                    },
                 };
                 token_stream.extend(tokens);
             }
@@ -815,4 +833,15 @@ enum FieldVariant<'f> {
 enum FieldToCompute<'f> {
     /// `ReaderId` from registering as a reader for an `EventChannel` in the `World`.
     ReaderId(&'f Field),
+    /// `ReaderId` of an event bus type, inserting its `EventChannel` and `EventQueue` in the
+    /// `World` if they don't exist yet.
+    EventBusReaderId(&'f Field),
+}
+
+impl<'f> FieldToCompute<'f> {
+    fn field(&self) -> &'f Field {
+        match self {
+            FieldToCompute::ReaderId(field) | FieldToCompute::EventBusReaderId(field) => field,
+        }
+    }
 }
@@ -2,6 +2,7 @@ use std::marker::PhantomData;
 
 use amethyst_core::{
     ecs::{System, SystemData, World, WorldExt},
+    event_bus::EventQueue,
     shrev::{EventChannel, ReaderId},
     SystemDesc,
 };
@@ -130,6 +131,42 @@ fn struct_tuple_with_event_channel_reader() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn struct_named_with_event_bus_reader() -> Result<(), Error> {
+    // Expects `System` to have a `new` constructor.
+    #[derive(Debug, SystemDesc)]
+    #[system_desc(name(SystemNamedEventBusDesc))]
+    struct SystemNamedEventBus {
+        #[system_desc(event_bus_reader)]
+        reader_id: ReaderId<u32>,
+    }
+    impl SystemNamedEventBus {
+        fn new(reader_id: ReaderId<u32>) -> Self {
+            Self { reader_id }
+        }
+    }
+
+    impl<'s> System<'s> for SystemNamedEventBus {
+        type SystemData = ();
+        fn run(&mut self, _: Self::SystemData) {}
+    }
+
+    let mut world = World::new();
+
+    let mut system = SystemNamedEventBusDesc::default().build(&mut world);
+    world.write_resource::<EventQueue<u32>>().send(1);
+    world.write_resource::<EventChannel<u32>>().single_write(2);
+    assert_eq!(
+        world
+            .read_resource::<EventChannel<u32>>()
+            .read(&mut system.reader_id)
+            .collect::<Vec<_>>(),
+        vec![&2]
+    );
+
+    Ok(())
+}
+
 #[test]
 fn struct_tuple_complex() -> Result<(), Error> {
     // Expects `System` to have a `new` constructor.
@@ -0,0 +1,158 @@
+//! Timing of action presses, used for double-tap, long-press and input buffering.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Timing thresholds used by the `InputHandler` to detect double-taps and long-presses, and
+/// to buffer action presses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionTiming {
+    /// Maximum time between two presses of an action for them to count as a double-tap.
+    pub double_tap_window: Duration,
+    /// Time an action must be held down to count as a long-press.
+    pub long_press_duration: Duration,
+    /// Time an action press stays buffered, see `InputHandler::action_buffered`.
+    pub buffer_window: Duration,
+}
+
+impl Default for ActionTiming {
+    fn default() -> Self {
+        ActionTiming {
+            double_tap_window: Duration::from_millis(250),
+            long_press_duration: Duration::from_millis(500),
+            buffer_window: Duration::from_millis(150),
+        }
+    }
+}
+
+/// Tracks the presses of an action across frames.
+///
+/// Presses and releases are recorded as their events are sent, and become visible to the
+/// queries on the next `update`, so a press and release within a single frame is not missed.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ActionState {
+    pub(crate) down: bool,
+    pub(crate) just_pressed: bool,
+    pub(crate) just_released: bool,
+    pub(crate) double_tapped: bool,
+    pub(crate) long_pressed: bool,
+    /// When the action was last pressed.
+    pub(crate) pressed_at: Duration,
+    /// When the action was last pressed, if that press can start a double-tap.
+    tap_at: Option<Duration>,
+    /// When the action was last pressed, until the buffered press is consumed.
+    pub(crate) buffered_at: Option<Duration>,
+    long_press_sent: bool,
+    /// Whether the action was pressed, released or double-tapped since the last update.
+    pressed: bool,
+    released: bool,
+    pressed_twice: bool,
+    /// Whether the bindings hold the action down, `None` if the action isn't bound.
+    pub(crate) polled: Option<bool>,
+}
+
+impl ActionState {
+    /// Records a press of the action at `now`, ignored if the action is already down.
+    pub(crate) fn press(&mut self, now: Duration, timing: &ActionTiming) {
+        if self.down {
+            return;
+        }
+        let double_tapped = match self.tap_at {
+            Some(tap_at) => elapsed(tap_at, now) <= timing.double_tap_window,
+            None => false,
+        };
+        self.down = true;
+        self.pressed = true;
+        self.pressed_twice |= double_tapped;
+        // A double-tap doesn't start another one, so triple taps are not two double-taps.
+        self.tap_at = if double_tapped { None } else { Some(now) };
+        self.pressed_at = now;
+        self.buffered_at = Some(now);
+        self.long_press_sent = false;
+    }
+
+    /// Records a release of the action, ignored if the action is not down.
+    pub(crate) fn release(&mut self) {
+        if self.down {
+            self.down = false;
+            self.released = true;
+        }
+    }
+
+    /// Updates the frame state with the presses and releases since the last update, and
+    /// whether the action is down at `now`.
+    pub(crate) fn update(&mut self, down: bool, now: Duration, timing: &ActionTiming) {
+        if down {
+            self.press(now, timing);
+        } else {
+            self.release();
+        }
+        self.just_pressed = std::mem::replace(&mut self.pressed, false);
+        self.just_released = std::mem::replace(&mut self.released, false);
+        self.double_tapped = std::mem::replace(&mut self.pressed_twice, false);
+        self.long_pressed = false;
+        if self.down
+            && !self.long_press_sent
+            && elapsed(self.pressed_at, now) >= timing.long_press_duration
+        {
+            self.long_pressed = true;
+            self.long_press_sent = true;
+        }
+    }
+}
+
+/// Returns the time elapsed from `since` to `now`, or zero if `since` is later.
+pub(crate) fn elapsed(since: Duration, now: Duration) -> Duration {
+    now.checked_sub(since).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn double_tap_and_long_press() {
+        let timing = ActionTiming::default();
+        let mut state = ActionState::default();
+
+        state.update(true, ms(0), &timing);
+        assert!(state.just_pressed && !state.double_tapped);
+        state.update(false, ms(100), &timing);
+        assert!(state.just_released);
+        state.update(true, ms(200), &timing);
+        assert!(state.double_tapped);
+        state.update(false, ms(300), &timing);
+        state.update(true, ms(400), &timing);
+        assert!(!state.double_tapped);
+
+        state.update(true, ms(800), &timing);
+        assert!(!state.long_pressed);
+        state.update(true, ms(900), &timing);
+        assert!(state.long_pressed);
+        state.update(true, ms(1000), &timing);
+        assert!(!state.long_pressed);
+    }
+
+    #[test]
+    fn press_and_release_within_a_frame() {
+        let timing = ActionTiming::default();
+        let mut state = ActionState::default();
+
+        state.press(ms(0), &timing);
+        state.release();
+        state.update(false, ms(10), &timing);
+        assert!(state.just_pressed && state.just_released && !state.down);
+        state.press(ms(50), &timing);
+        state.release();
+        state.update(false, ms(60), &timing);
+        assert!(state.double_tapped);
+        state.update(false, ms(70), &timing);
+        assert!(!state.just_pressed && !state.just_released && !state.double_tapped);
+    }
+}
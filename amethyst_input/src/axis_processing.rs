@@ -0,0 +1,195 @@
+//! Processing applied to the raw values of axes.
+
+use serde::{Deserialize, Serialize};
+
+/// Maps the magnitude of an axis, from 0 to 1, to the magnitude returned by the `InputHandler`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub enum ResponseCurve {
+    /// Returns the magnitude unchanged.
+    #[default]
+    Linear,
+    /// Raises the magnitude to the power, values over 1 give more precision near the center.
+    Exponential(f32),
+    /// Interpolates linearly between points `(input, output)`, sorted by input.
+    ///
+    /// The curve is extended with `(0, 0)` and `(1, 1)` if it doesn't start at 0 or end at 1.
+    Custom(Vec<(f32, f32)>),
+}
+
+impl ResponseCurve {
+    /// Applies the curve to a magnitude between 0 and 1.
+    pub fn apply(&self, magnitude: f32) -> f32 {
+        match *self {
+            ResponseCurve::Linear => magnitude,
+            ResponseCurve::Exponential(exponent) => magnitude.powf(exponent),
+            ResponseCurve::Custom(ref points) => {
+                let mut previous = (0.0, 0.0);
+                for &point in points.iter().chain(Some(&(1.0, 1.0))) {
+                    if magnitude <= point.0 {
+                        let width = point.0 - previous.0;
+                        if width <= 0.0 {
+                            return point.1;
+                        }
+                        let t = (magnitude - previous.0) / width;
+                        return previous.1 + (point.1 - previous.1) * t;
+                    }
+                    previous = point;
+                }
+                previous.1
+            }
+        }
+    }
+}
+
+/// Processing of the value of an axis, set with `Bindings::set_axis_processing`.
+///
+/// The value is computed in this order: the raw axis value, smoothed by `acceleration` and
+/// `gravity` for emulated axes, the radial dead zone when read as a pair with
+/// `InputHandler::axis_pair_value`, the response curve, and finally the sensitivity.
+///
+/// Example Ron config file entry, in the `axis_processing` of the `Bindings`:
+/// ```ron
+/// "look_x": (curve: Exponential(2.0), sensitivity: 1.5, radial_dead_zone: 0.15),
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisProcessing {
+    /// Curve applied to the magnitude of the value.
+    pub curve: ResponseCurve,
+    /// Multiplies the value after the curve, the result is clamped between -1 and 1.
+    pub sensitivity: f32,
+    /// Magnitude under which a pair of axes reads as centered, see
+    /// `InputHandler::axis_pair_value`.
+    ///
+    /// Unlike the `dead_zone` of `Axis::Controller`, which applies to each axis of a stick
+    /// separately, this doesn't snap diagonal motions to the axes.
+    pub radial_dead_zone: f32,
+    /// Speed in units per second at which an emulated axis moves towards a pressed direction,
+    /// or 0 to move instantly.
+    pub acceleration: f32,
+    /// Speed in units per second at which an emulated axis returns to 0 when released, or 0 to
+    /// return instantly.
+    pub gravity: f32,
+}
+
+impl Default for AxisProcessing {
+    fn default() -> Self {
+        AxisProcessing {
+            curve: ResponseCurve::Linear,
+            sensitivity: 1.0,
+            radial_dead_zone: 0.0,
+            acceleration: 0.0,
+            gravity: 0.0,
+        }
+    }
+}
+
+impl AxisProcessing {
+    /// Applies the response curve and sensitivity to a value between -1 and 1.
+    ///
+    /// An idle axis stays at 0, whatever the output of the curve at 0.
+    pub fn apply(&self, value: f32) -> f32 {
+        if value == 0.0 {
+            return 0.0;
+        }
+        let magnitude = self.curve.apply(value.abs().min(1.0)) * self.sensitivity;
+        magnitude.min(1.0) * value.signum()
+    }
+
+    /// Returns true if emulated axes are smoothed over time.
+    pub(crate) fn smooths(&self) -> bool {
+        self.acceleration > 0.0 || self.gravity > 0.0
+    }
+
+    /// Moves the smoothed `value` of an emulated axis towards `target` for `delta_seconds`.
+    ///
+    /// Moving towards a pressed direction uses the acceleration, returning to 0 or reversing
+    /// uses the gravity until the value crosses 0.
+    pub(crate) fn smooth(&self, value: f32, target: f32, delta_seconds: f32) -> f32 {
+        let towards = |value: f32, target: f32, speed: f32| {
+            if speed <= 0.0 {
+                return target;
+            }
+            let step = speed * delta_seconds;
+            if (target - value).abs() <= step {
+                target
+            } else {
+                value + step * (target - value).signum()
+            }
+        };
+        if value == 0.0 || (target != 0.0 && value.signum() == target.signum()) {
+            if target.abs() >= value.abs() {
+                towards(value, target, self.acceleration)
+            } else {
+                towards(value, target, self.gravity)
+            }
+        } else {
+            towards(value, 0.0, self.gravity)
+        }
+    }
+}
+
+/// Applies a radial dead zone to a pair of axis values, rescaling the remaining range to 0 to 1.
+pub(crate) fn radial_dead_zone(x: f32, y: f32, dead_zone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= dead_zone || magnitude == 0.0 {
+        return (0.0, 0.0);
+    }
+    let scaled = ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    (x / magnitude * scaled, y / magnitude * scaled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_ulps_eq;
+
+    #[test]
+    fn curves() {
+        assert_ulps_eq!(ResponseCurve::Exponential(2.0).apply(0.5), 0.25);
+        let custom = ResponseCurve::Custom(vec![(0.5, 0.2)]);
+        assert_ulps_eq!(custom.apply(0.25), 0.1);
+        assert_ulps_eq!(custom.apply(0.75), 0.6);
+        assert_ulps_eq!(custom.apply(1.0), 1.0);
+
+        let processing = AxisProcessing {
+            curve: ResponseCurve::Exponential(2.0),
+            sensitivity: 3.0,
+            ..Default::default()
+        };
+        assert_ulps_eq!(processing.apply(-0.5), -0.75);
+        assert_ulps_eq!(processing.apply(0.9), 1.0);
+        assert_ulps_eq!(processing.apply(0.0), 0.0);
+
+        let processing = AxisProcessing {
+            curve: ResponseCurve::Exponential(0.0),
+            ..Default::default()
+        };
+        assert_ulps_eq!(processing.apply(0.0), 0.0);
+        assert_ulps_eq!(processing.apply(-0.5), -1.0);
+    }
+
+    #[test]
+    fn smoothing() {
+        let processing = AxisProcessing {
+            acceleration: 4.0,
+            gravity: 2.0,
+            ..Default::default()
+        };
+        assert_ulps_eq!(processing.smooth(0.0, 1.0, 0.1), 0.4);
+        assert_ulps_eq!(processing.smooth(0.8, 1.0, 0.1), 1.0);
+        assert_ulps_eq!(processing.smooth(1.0, 0.0, 0.1), 0.8);
+        assert_ulps_eq!(processing.smooth(0.8, -1.0, 0.1), 0.6);
+        assert_ulps_eq!(processing.smooth(0.1, -1.0, 0.1), 0.0);
+    }
+
+    #[test]
+    fn radial() {
+        let (x, y) = radial_dead_zone(0.1, 0.1, 0.2);
+        assert_ulps_eq!(x, 0.0);
+        assert_ulps_eq!(y, 0.0);
+        let (x, y) = radial_dead_zone(0.0, -0.6, 0.2);
+        assert_ulps_eq!(x, 0.0);
+        assert_ulps_eq!(y, -0.5);
+    }
+}
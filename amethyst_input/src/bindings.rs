@@ -12,7 +12,7 @@ use fnv::FnvHashMap as HashMap;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
-use super::{Axis, Button};
+use super::{Axis, AxisProcessing, Button};
 
 /// Define a set of types used for bindings configuration.
 /// Usually defaulted to `StringBindings`, which uses `String`s.
@@ -103,7 +103,10 @@ impl BindingTypes for StringBindings {
 ///     actions: {
 ///         "fire": [ [Mouse(Left)], [Key(X)] ], // Multiple bindings for one action
 ///         "reload": [ [Key(LControl), Key(R)] ] // Combinations of multiple bindings possible
-///     }
+///     },
+///     axis_processing: { // Optional, see `AxisProcessing`
+///         "leftright": (acceleration: 4.0, gravity: 8.0),
+///     },
 /// )
 /// ```
 #[derive(Derivative, Serialize, Deserialize)]
@@ -119,11 +122,18 @@ pub struct Bindings<T: BindingTypes> {
     /// So for example if you want to quit by either "Esc" or "Ctrl+q" you would have
     /// `[[Esc], [Ctrl, Q]]`.
     pub(super) actions: HashMap<T::Action, SmallVec<[SmallVec<[Button; 2]>; 4]>>,
+    /// Processing applied to the values of axes, kept when an axis is rebound.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(super) axis_processing: HashMap<T::Axis, AxisProcessing>,
 }
 
 /// An enum of possible errors that can occur when binding an action or axis.
-#[derive(Clone, Derivative)]
-#[derivative(Debug(bound = ""))]
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
 pub enum BindingError<T: BindingTypes> {
     /// You attempted to bind a mousewheel axis twice.
     MouseWheelAxisAlreadyBound(T::Axis),
@@ -291,6 +301,35 @@ impl<T: BindingTypes> Bindings<T> {
         self.axes.keys()
     }
 
+    /// Sets the processing applied to the value of an axis, returning the previous processing.
+    ///
+    /// The processing is kept when the axis is replaced or removed.
+    pub fn set_axis_processing<A: Into<T::Axis>>(
+        &mut self,
+        id: A,
+        processing: AxisProcessing,
+    ) -> Option<AxisProcessing> {
+        self.axis_processing.insert(id.into(), processing)
+    }
+
+    /// Removes the processing of an axis, returning it if there was one.
+    pub fn remove_axis_processing<A>(&mut self, id: &A) -> Option<AxisProcessing>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.axis_processing.remove(id)
+    }
+
+    /// Returns the processing applied to the value of an axis.
+    pub fn axis_processing<A>(&self, id: &A) -> Option<&AxisProcessing>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.axis_processing.get(id)
+    }
+
     /// Add a button or button combination to an action.
     ///
     /// This will attempt to insert a new binding between this action and the button(s).
@@ -406,6 +445,44 @@ impl<T: BindingTypes> Bindings<T> {
         Ok(())
     }
 
+    /// Returns true if the button is part of an action binding or of an emulated axis.
+    pub(crate) fn binds_button(&self, button: &Button) -> bool {
+        self.actions
+            .values()
+            .any(|combinations| combinations.iter().any(|c| c.contains(button)))
+            || self.axes.values().any(|axis| match axis {
+                Axis::Emulated { pos, neg } => pos == button || neg == button,
+                _ => false,
+            })
+    }
+
+    /// Returns true if an axis reads the same analog input as `axis`.
+    ///
+    /// Emulated axes never match, their buttons are checked with `binds_button`.
+    pub(crate) fn binds_axis_input(&self, axis: &Axis) -> bool {
+        self.axes.values().any(|bound| match (bound, axis) {
+            (
+                Axis::Controller {
+                    controller_id,
+                    axis,
+                    ..
+                },
+                Axis::Controller {
+                    controller_id: input_controller_id,
+                    axis: input_axis,
+                    ..
+                },
+            ) => controller_id == input_controller_id && axis == input_axis,
+            (
+                Axis::MouseWheel { horizontal },
+                Axis::MouseWheel {
+                    horizontal: input_horizontal,
+                },
+            ) => horizontal == input_horizontal,
+            _ => false,
+        })
+    }
+
     fn check_action_invariants(
         &self,
         id: &T::Action,
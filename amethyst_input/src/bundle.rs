@@ -1,6 +1,9 @@
 //! ECS input bundle
 
-use crate::{BindingError, BindingTypes, Bindings, InputSystemDesc};
+use crate::{
+    ActionTiming, BindingError, BindingOverrides, BindingTypes, Bindings, ComboSet,
+    ComboSystemDesc, InputContexts, InputSystemDesc, PlayerSlots, TouchSettings, UserBindingsFile,
+};
 use amethyst_config::{Config, ConfigError};
 use amethyst_core::{
     ecs::prelude::{DispatcherBuilder, World},
@@ -12,6 +15,8 @@ use std::{error, fmt, path::Path};
 
 #[cfg(feature = "sdl_controller")]
 use crate::sdl_events_system::ControllerMappings;
+#[cfg(not(feature = "sdl_controller"))]
+use crate::{HapticsSystem, NullHaptics};
 
 /// Bundle for adding the `InputHandler`.
 ///
@@ -32,6 +37,12 @@ use crate::sdl_events_system::ControllerMappings;
 #[derivative(Default(bound = ""))]
 pub struct InputBundle<T: BindingTypes> {
     bindings: Option<Bindings<T>>,
+    contexts: Option<InputContexts<T>>,
+    user_bindings: Option<UserBindingsFile<T>>,
+    action_timing: Option<ActionTiming>,
+    combos: Option<ComboSet<T>>,
+    players: Option<PlayerSlots<T>>,
+    touch_settings: Option<TouchSettings>,
     #[cfg(feature = "sdl_controller")]
     controller_mappings: Option<ControllerMappings>,
 }
@@ -61,6 +72,88 @@ impl<T: BindingTypes> InputBundle<T> {
         Ok(self.with_bindings(bindings))
     }
 
+    /// Use the provided input contexts with the `InputHandler`
+    pub fn with_contexts(mut self, contexts: InputContexts<T>) -> Self {
+        self.contexts = Some(contexts);
+        self
+    }
+
+    /// Load input contexts from file
+    pub fn with_contexts_from_file<P: AsRef<Path>>(
+        self,
+        file: P,
+    ) -> Result<Self, BindingsFileError<T>>
+    where
+        InputContexts<T>: Config,
+    {
+        let mut contexts = InputContexts::load_no_fallback(file)?;
+        contexts.check_invariants()?;
+        Ok(self.with_contexts(contexts))
+    }
+
+    /// Load the user's binding overrides from file, and layer them over the bindings
+    ///
+    /// The overrides are saved back to the file every time `InputHandler::start_rebind` changes
+    /// the bindings. A missing file is created on the first rebind.
+    pub fn with_user_bindings_file<P: AsRef<Path>>(
+        mut self,
+        file: P,
+    ) -> Result<Self, BindingsFileError<T>>
+    where
+        BindingOverrides<T>: Config,
+    {
+        self.user_bindings = Some(UserBindingsFile::load(file)?);
+        Ok(self)
+    }
+
+    /// Use the provided thresholds to detect double-taps and long-presses of actions
+    pub fn with_action_timing(mut self, action_timing: ActionTiming) -> Self {
+        self.action_timing = Some(action_timing);
+        self
+    }
+
+    /// Use the provided thresholds to recognize touch gestures, and emulate the mouse with touches
+    pub fn with_touch_settings(mut self, touch_settings: TouchSettings) -> Self {
+        self.touch_settings = Some(touch_settings);
+        self
+    }
+
+    /// Use the provided player slots with the `InputHandler`
+    pub fn with_players(mut self, players: PlayerSlots<T>) -> Self {
+        self.players = Some(players);
+        self
+    }
+
+    /// Load player slots from file
+    pub fn with_players_from_file<P: AsRef<Path>>(
+        self,
+        file: P,
+    ) -> Result<Self, BindingsFileError<T>>
+    where
+        PlayerSlots<T>: Config,
+    {
+        let mut players = PlayerSlots::load_no_fallback(file)?;
+        players.check_invariants()?;
+        Ok(self.with_players(players))
+    }
+
+    /// Recognize the provided combos with a `ComboSystem`
+    pub fn with_combos(mut self, combos: ComboSet<T>) -> Self {
+        self.combos = Some(combos);
+        self
+    }
+
+    /// Load combos from file
+    pub fn with_combos_from_file<P: AsRef<Path>>(
+        self,
+        file: P,
+    ) -> Result<Self, BindingsFileError<T>>
+    where
+        ComboSet<T>: Config,
+    {
+        Ok(self.with_combos(ComboSet::load_no_fallback(file)?))
+    }
+
     /// Load SDL controller mappings from file
     #[cfg(feature = "sdl_controller")]
     pub fn with_sdl_controller_mappings(mut self, mappings: String) -> Self {
@@ -93,11 +186,32 @@ impl<'a, 'b, T: BindingTypes> SystemBundle<'a, 'b> for InputBundle<T> {
                 SdlEventsSystem::<T>::new(world, self.controller_mappings).unwrap(),
             );
         }
-        builder.add(
-            InputSystemDesc::<T>::new(self.bindings).build(world),
-            "input_system",
-            &[],
-        );
+        #[cfg(not(feature = "sdl_controller"))]
+        builder.add(HapticsSystem::new(NullHaptics), "haptics_system", &[]);
+        let mut input_system_desc = InputSystemDesc::<T>::new(self.bindings);
+        if let Some(contexts) = self.contexts {
+            input_system_desc = input_system_desc.with_contexts(contexts);
+        }
+        if let Some(user_bindings) = self.user_bindings {
+            input_system_desc = input_system_desc.with_user_bindings(user_bindings);
+        }
+        if let Some(action_timing) = self.action_timing {
+            input_system_desc = input_system_desc.with_action_timing(action_timing);
+        }
+        if let Some(players) = self.players {
+            input_system_desc = input_system_desc.with_players(players);
+        }
+        if let Some(touch_settings) = self.touch_settings {
+            input_system_desc = input_system_desc.with_touch_settings(touch_settings);
+        }
+        builder.add(input_system_desc.build(world), "input_system", &[]);
+        if let Some(combos) = self.combos {
+            builder.add(
+                ComboSystemDesc::<T>::new(combos).build(world),
+                "combo_system",
+                &["input_system"],
+            );
+        }
         Ok(())
     }
 }
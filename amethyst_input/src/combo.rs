@@ -0,0 +1,426 @@
+//! Recognition of input sequences, like fighting game special moves.
+
+use std::{collections::VecDeque, time::Duration};
+
+use derivative::Derivative;
+use derive_new::new;
+use serde::{Deserialize, Serialize};
+
+use amethyst_core::{
+    ecs::{
+        prelude::{Read, System, World, Write, WriteExpect},
+        SystemData,
+    },
+    shrev::{EventChannel, ReaderId},
+    SystemDesc, Time,
+};
+
+use super::{action_timing::elapsed, BindingTypes, InputEvent, InputHandler};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// A direction of the combo axes, relative to the direction the player is facing.
+///
+/// Forward is the positive direction of the horizontal axis, unless the `ComboRecognizer` is
+/// mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ComboDirection {
+    /// Positive vertical axis.
+    Up,
+    /// Negative vertical axis.
+    Down,
+    /// Forward horizontal axis.
+    Forward,
+    /// Backward horizontal axis.
+    Back,
+    /// Positive vertical and forward horizontal axes.
+    UpForward,
+    /// Positive vertical and backward horizontal axes.
+    UpBack,
+    /// Negative vertical and forward horizontal axes.
+    DownForward,
+    /// Negative vertical and backward horizontal axes.
+    DownBack,
+}
+
+impl ComboDirection {
+    /// Returns the direction for the signs of the horizontal and vertical axes, if any is non zero.
+    fn from_signs(horizontal: i8, vertical: i8) -> Option<Self> {
+        use self::ComboDirection::*;
+
+        match (horizontal, vertical) {
+            (0, 1) => Some(Up),
+            (0, -1) => Some(Down),
+            (1, 0) => Some(Forward),
+            (-1, 0) => Some(Back),
+            (1, 1) => Some(UpForward),
+            (-1, 1) => Some(UpBack),
+            (1, -1) => Some(DownForward),
+            (-1, -1) => Some(DownBack),
+            _ => None,
+        }
+    }
+}
+
+/// An input of a combo step.
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Action: Serialize",
+    deserialize = "T::Action: Deserialize<'de>",
+))]
+pub enum ComboInput<T: BindingTypes> {
+    /// The action is pressed.
+    Action(T::Action),
+    /// The combo axes are moved to the direction.
+    Direction(ComboDirection),
+}
+
+impl<T: BindingTypes> PartialEq for ComboInput<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ComboInput::Action(a), ComboInput::Action(x)) => a == x,
+            (ComboInput::Direction(a), ComboInput::Direction(x)) => a == x,
+            (_, _) => false,
+        }
+    }
+}
+
+/// A step of a combo.
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Action: Serialize",
+    deserialize = "T::Action: Deserialize<'de>",
+))]
+pub struct ComboStep<T: BindingTypes> {
+    /// The input to perform.
+    pub input: ComboInput<T>,
+    /// Maximum time since the previous step, ignored for the first step.
+    #[serde(default = "default_timeout")]
+    pub timeout: Duration,
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_millis(250)
+}
+
+impl<T: BindingTypes> ComboStep<T> {
+    /// Creates a step with the default timeout of 250 milliseconds.
+    pub fn new(input: ComboInput<T>) -> Self {
+        ComboStep {
+            input,
+            timeout: default_timeout(),
+        }
+    }
+}
+
+/// A sequence of inputs triggering an action.
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Action: Serialize",
+    deserialize = "T::Action: Deserialize<'de>",
+))]
+pub struct Combo<T: BindingTypes> {
+    /// The action sent with `InputEvent::ComboTriggered` when the combo is performed.
+    pub action: T::Action,
+    /// The inputs to perform in order, with no other input in between.
+    pub steps: Vec<ComboStep<T>>,
+}
+
+/// The combos to recognize, and the axes read for directions.
+///
+/// Example Ron config file:
+/// ```ron
+/// (
+///     horizontal_axis: Some("horizontal"),
+///     vertical_axis: Some("vertical"),
+///     combos: [
+///         (
+///             action: "fireball",
+///             steps: [
+///                 (input: Direction(Down)),
+///                 (input: Direction(DownForward)),
+///                 (input: Direction(Forward)),
+///                 (input: Action("punch"), timeout: (secs: 0, nanos: 100000000)),
+///             ],
+///         ),
+///     ],
+/// )
+/// ```
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Default(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub struct ComboSet<T: BindingTypes> {
+    /// The axis read for the horizontal component of directions.
+    #[serde(default)]
+    pub horizontal_axis: Option<T::Axis>,
+    /// The axis read for the vertical component of directions.
+    #[serde(default)]
+    pub vertical_axis: Option<T::Axis>,
+    /// Minimal absolute axis value for a direction to be recognized.
+    #[serde(default = "default_axis_threshold")]
+    #[derivative(Default(value = "default_axis_threshold()"))]
+    pub axis_threshold: f32,
+    /// The combos to recognize.
+    #[serde(default)]
+    pub combos: Vec<Combo<T>>,
+}
+
+fn default_axis_threshold() -> f32 {
+    0.5
+}
+
+/// Recognizes combos from action presses and directions.
+///
+/// The `ComboSystem` stores the recognizer as a resource, mirror it to make forward follow the
+/// direction the player is facing.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct ComboRecognizer<T: BindingTypes> {
+    combos: ComboSet<T>,
+    mirrored: bool,
+    direction: Option<ComboDirection>,
+    history: VecDeque<(ComboInput<T>, Duration)>,
+    history_len: usize,
+}
+
+impl<T: BindingTypes> ComboRecognizer<T> {
+    /// Creates a recognizer for a set of combos.
+    pub fn new(combos: ComboSet<T>) -> Self {
+        let history_len = combos
+            .combos
+            .iter()
+            .map(|combo| combo.steps.len())
+            .max()
+            .unwrap_or(0);
+        ComboRecognizer {
+            combos,
+            mirrored: false,
+            direction: None,
+            history: VecDeque::with_capacity(history_len),
+            history_len,
+        }
+    }
+
+    /// Returns the recognized combos.
+    pub fn combos(&self) -> &ComboSet<T> {
+        &self.combos
+    }
+
+    /// Sets whether forward is the negative direction of the horizontal axis.
+    pub fn set_mirrored(&mut self, mirrored: bool) {
+        self.mirrored = mirrored;
+    }
+
+    /// Returns whether forward is the negative direction of the horizontal axis.
+    pub fn mirrored(&self) -> bool {
+        self.mirrored
+    }
+
+    /// Forgets the inputs performed so far.
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    /// Records an input performed at `now`, returning the action of the longest combo it
+    /// completes.
+    pub fn push(&mut self, input: ComboInput<T>, now: Duration) -> Option<T::Action> {
+        if self.history_len == 0 {
+            return None;
+        }
+        if self.history.len() == self.history_len {
+            self.history.pop_front();
+        }
+        self.history.push_back((input, now));
+
+        let history = &self.history;
+        let triggered = self
+            .combos
+            .combos
+            .iter()
+            .filter(|combo| Self::completes(combo, history))
+            .max_by_key(|combo| combo.steps.len())
+            .map(|combo| combo.action.clone());
+        if triggered.is_some() {
+            self.history.clear();
+        }
+        triggered
+    }
+
+    /// Reads the direction of the combo axes, and records it if it changed.
+    pub fn update_direction(
+        &mut self,
+        handler: &InputHandler<T>,
+        now: Duration,
+    ) -> Option<T::Action> {
+        let threshold = self.combos.axis_threshold;
+        let sign = |axis: &Option<T::Axis>| {
+            let value = axis
+                .as_ref()
+                .and_then(|axis| handler.axis_value(axis))
+                .unwrap_or(0.0);
+            if value >= threshold {
+                1
+            } else if value <= -threshold {
+                -1
+            } else {
+                0
+            }
+        };
+        let mut horizontal = sign(&self.combos.horizontal_axis);
+        if self.mirrored {
+            horizontal = -horizontal;
+        }
+        let direction = ComboDirection::from_signs(horizontal, sign(&self.combos.vertical_axis));
+
+        if direction == self.direction {
+            return None;
+        }
+        self.direction = direction;
+        direction.and_then(|direction| self.push(ComboInput::Direction(direction), now))
+    }
+
+    /// Returns true if the last inputs of `history` perform all the steps of the combo.
+    fn completes(combo: &Combo<T>, history: &VecDeque<(ComboInput<T>, Duration)>) -> bool {
+        if combo.steps.is_empty() || combo.steps.len() > history.len() {
+            return false;
+        }
+        let start = history.len() - combo.steps.len();
+        combo.steps.iter().enumerate().all(|(i, step)| {
+            let (ref input, time) = history[start + i];
+            *input == step.input
+                && (i == 0 || elapsed(history[start + i - 1].1, time) <= step.timeout)
+        })
+    }
+}
+
+/// Builds a `ComboSystem`.
+#[derive(Derivative, new)]
+#[derivative(Debug(bound = ""))]
+pub struct ComboSystemDesc<T: BindingTypes> {
+    combos: ComboSet<T>,
+}
+
+impl<'a, 'b, T: BindingTypes> SystemDesc<'a, 'b, ComboSystem<T>> for ComboSystemDesc<T> {
+    fn build(self, world: &mut World) -> ComboSystem<T> {
+        world.insert(ComboRecognizer::new(self.combos));
+        <ComboSystem<T> as System<'_>>::SystemData::setup(world);
+
+        let reader = world
+            .fetch_mut::<EventChannel<InputEvent<T>>>()
+            .register_reader();
+        ComboSystem::new(reader)
+    }
+}
+
+/// Recognizes combos with the `ComboRecognizer` resource.
+///
+/// Reads `InputEvent::ActionPressed` events and the direction of the combo axes, and sends
+/// `InputEvent::ComboTriggered` events.
+#[derive(Derivative, new)]
+#[derivative(Debug(bound = ""))]
+pub struct ComboSystem<T: BindingTypes> {
+    reader: ReaderId<InputEvent<T>>,
+}
+
+impl<'a, T: BindingTypes> System<'a> for ComboSystem<T> {
+    type SystemData = (
+        Read<'a, InputHandler<T>>,
+        WriteExpect<'a, ComboRecognizer<T>>,
+        Write<'a, EventChannel<InputEvent<T>>>,
+        Read<'a, Time>,
+    );
+
+    fn run(&mut self, (handler, mut recognizer, mut events, time): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("combo_system");
+
+        let now = time.absolute_real_time();
+        let mut triggered = Vec::new();
+        triggered.extend(recognizer.update_direction(&handler, now));
+        for event in events.read(&mut self.reader) {
+            if let InputEvent::ActionPressed(action) = event {
+                triggered.extend(recognizer.push(ComboInput::Action(action.clone()), now));
+            }
+        }
+        events.iter_write(triggered.into_iter().map(InputEvent::ComboTriggered));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StringBindings;
+
+    fn fireball() -> ComboSet<StringBindings> {
+        let direction = |direction| ComboStep::new(ComboInput::Direction(direction));
+        ComboSet {
+            combos: vec![
+                Combo {
+                    action: String::from("fireball"),
+                    steps: vec![
+                        direction(ComboDirection::Down),
+                        direction(ComboDirection::DownForward),
+                        direction(ComboDirection::Forward),
+                        ComboStep::new(ComboInput::Action(String::from("punch"))),
+                    ],
+                },
+                Combo {
+                    action: String::from("jab"),
+                    steps: vec![ComboStep::new(ComboInput::Action(String::from("punch")))],
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn perform(
+        recognizer: &mut ComboRecognizer<StringBindings>,
+        inputs: &[(ComboInput<StringBindings>, u64)],
+    ) -> Vec<String> {
+        inputs
+            .iter()
+            .filter_map(|(input, millis)| {
+                recognizer.push(input.clone(), Duration::from_millis(*millis))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recognizes_longest_combo() {
+        let mut recognizer = ComboRecognizer::new(fireball());
+        let punch = ComboInput::Action(String::from("punch"));
+        let triggered = perform(
+            &mut recognizer,
+            &[
+                (ComboInput::Direction(ComboDirection::Down), 0),
+                (ComboInput::Direction(ComboDirection::DownForward), 100),
+                (ComboInput::Direction(ComboDirection::Forward), 200),
+                (punch.clone(), 300),
+                (punch, 400),
+            ],
+        );
+        assert_eq!(triggered, vec!["fireball", "jab"]);
+    }
+
+    #[test]
+    fn step_timeout_breaks_combo() {
+        let mut recognizer = ComboRecognizer::new(fireball());
+        let triggered = perform(
+            &mut recognizer,
+            &[
+                (ComboInput::Direction(ComboDirection::Down), 0),
+                (ComboInput::Direction(ComboDirection::DownForward), 500),
+                (ComboInput::Direction(ComboDirection::Forward), 600),
+                (ComboInput::Action(String::from("punch")), 700),
+            ],
+        );
+        assert_eq!(triggered, vec!["jab"]);
+    }
+}
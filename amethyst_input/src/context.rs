@@ -0,0 +1,324 @@
+//! Named input contexts stacked on top of the global bindings.
+
+use std::cmp::Reverse;
+
+use derivative::Derivative;
+use fnv::FnvHashMap as HashMap;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+use super::{Axis, BindingError, BindingTypes, Bindings, Button};
+
+/// Defines which inputs a context hides from the contexts below it on the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum InputConsumption {
+    /// All inputs are passed to the contexts below.
+    PassThrough,
+    /// Buttons and axes bound in this context are hidden from the contexts below.
+    #[default]
+    Bound,
+    /// No input reaches the contexts below, use this for modal contexts like text fields.
+    All,
+}
+
+impl InputConsumption {
+    /// Returns true if the button is hidden from the contexts below one using `bindings`.
+    pub(crate) fn consumes_button<T: BindingTypes>(
+        self,
+        bindings: &Bindings<T>,
+        button: &Button,
+    ) -> bool {
+        match self {
+            InputConsumption::PassThrough => false,
+            InputConsumption::Bound => bindings.binds_button(button),
+            InputConsumption::All => true,
+        }
+    }
+
+    /// Returns true if the analog input read by `axis` is hidden from the contexts below one
+    /// using `bindings`.
+    pub(crate) fn consumes_axis<T: BindingTypes>(
+        self,
+        bindings: &Bindings<T>,
+        axis: &Axis,
+    ) -> bool {
+        match self {
+            InputConsumption::PassThrough => false,
+            InputConsumption::Bound => bindings.binds_axis_input(axis),
+            InputConsumption::All => true,
+        }
+    }
+}
+
+/// A set of bindings which is only active while it's pushed on the `InputContexts` stack.
+///
+/// Example Ron config file:
+/// ```ron
+/// (
+///     bindings: (
+///         axes: {},
+///         actions: {
+///             "close_menu": [[Key(Escape)]],
+///         },
+///     ),
+///     priority: 10,
+///     consumption: Bound,
+/// )
+/// ```
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Default(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub struct InputContext<T: BindingTypes> {
+    /// Maps inputs to actions and axes while this context is active.
+    pub bindings: Bindings<T>,
+    /// Active contexts with a higher priority are consulted first, regardless of the order they
+    /// were pushed in. Contexts with the same priority are consulted from the most recently pushed.
+    #[serde(default)]
+    pub priority: i32,
+    /// Inputs hidden from the contexts below this one.
+    #[serde(default)]
+    pub consumption: InputConsumption,
+}
+
+impl<T: BindingTypes> InputContext<T> {
+    /// Creates a new context with the given bindings, a priority of 0 which consumes its bound
+    /// inputs.
+    pub fn new(bindings: Bindings<T>) -> Self {
+        InputContext {
+            bindings,
+            priority: 0,
+            consumption: InputConsumption::default(),
+        }
+    }
+
+    /// Sets the priority of this context.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets which inputs this context hides from the contexts below it.
+    pub fn with_consumption(mut self, consumption: InputConsumption) -> Self {
+        self.consumption = consumption;
+        self
+    }
+}
+
+/// Named input contexts, and the stack of the active ones.
+///
+/// The `InputHandler` consults the active contexts from the top of the stack down, and finally
+/// its own `bindings`, which act as the bottom, always active, context. An input consumed by a
+/// context doesn't trigger the actions and axes of the contexts below it. Device level events
+/// and queries like `InputEvent::KeyPressed` or `InputHandler::key_is_down` are not affected by
+/// contexts.
+///
+/// Example Ron config file:
+/// ```ron
+/// (
+///     contexts: {
+///         "gameplay": (
+///             bindings: (
+///                 axes: {},
+///                 actions: {
+///                     "pause": [[Key(Escape)]],
+///                     "jump": [[Key(Space)]],
+///                 },
+///             ),
+///         ),
+///         "menu": (
+///             bindings: (
+///                 axes: {},
+///                 actions: {
+///                     "close_menu": [[Key(Escape)]],
+///                 },
+///             ),
+///             priority: 10,
+///         ),
+///     },
+///     active: ["gameplay"],
+/// )
+/// ```
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Default(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub struct InputContexts<T: BindingTypes> {
+    contexts: HashMap<String, InputContext<T>>,
+    /// Names of the active contexts, in the order they were pushed.
+    #[serde(default)]
+    active: Vec<String>,
+}
+
+impl<T: BindingTypes> InputContexts<T> {
+    /// Creates an empty set of contexts.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers a context under a name, replacing and returning any context with that name.
+    ///
+    /// If the replaced context was active the new one is active in its place.
+    pub fn insert<N: Into<String>>(
+        &mut self,
+        name: N,
+        context: InputContext<T>,
+    ) -> Option<InputContext<T>> {
+        self.contexts.insert(name.into(), context)
+    }
+
+    /// Unregisters a context, removing it from the stack.
+    pub fn remove(&mut self, name: &str) -> Option<InputContext<T>> {
+        self.active.retain(|active| active != name);
+        self.contexts.remove(name)
+    }
+
+    /// Returns a reference to a context.
+    pub fn get(&self, name: &str) -> Option<&InputContext<T>> {
+        self.contexts.get(name)
+    }
+
+    /// Returns a mutable reference to a context.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut InputContext<T>> {
+        self.contexts.get_mut(name)
+    }
+
+    /// Gets the names of all registered contexts.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.contexts.keys().map(String::as_str)
+    }
+
+    /// Pushes a registered context on the stack, moving it to the top if it was already active.
+    ///
+    /// Returns false if no context is registered under that name.
+    pub fn push(&mut self, name: &str) -> bool {
+        if !self.contexts.contains_key(name) {
+            return false;
+        }
+        self.active.retain(|active| active != name);
+        self.active.push(name.to_owned());
+        true
+    }
+
+    /// Pops the most recently pushed context off the stack, returning its name.
+    pub fn pop(&mut self) -> Option<String> {
+        self.active.pop()
+    }
+
+    /// Removes a context from the stack, returning false if it wasn't active.
+    pub fn deactivate(&mut self, name: &str) -> bool {
+        let len = self.active.len();
+        self.active.retain(|active| active != name);
+        self.active.len() != len
+    }
+
+    /// Removes all contexts from the stack.
+    pub fn clear_stack(&mut self) {
+        self.active.clear();
+    }
+
+    /// Returns true if the context is on the stack.
+    pub fn is_active(&self, name: &str) -> bool {
+        self.active.iter().any(|active| active == name)
+    }
+
+    /// Returns the names of the active contexts, in the order they are consulted.
+    pub fn active_names(&self) -> impl Iterator<Item = &str> {
+        self.sorted_active().into_iter().map(|(name, _)| name)
+    }
+
+    /// Returns the active contexts, in the order they are consulted.
+    pub fn active(&self) -> impl Iterator<Item = &InputContext<T>> {
+        self.sorted_active().into_iter().map(|(_, context)| context)
+    }
+
+    /// Checks the invariants of the bindings of every context, see `Bindings::check_invariants`.
+    pub fn check_invariants(&mut self) -> Result<(), BindingError<T>> {
+        for context in self.contexts.values_mut() {
+            context.bindings.check_invariants()?;
+        }
+        let contexts = &self.contexts;
+        self.active.retain(|active| contexts.contains_key(active));
+        Ok(())
+    }
+
+    fn sorted_active(&self) -> SmallVec<[(&str, &InputContext<T>); 8]> {
+        let mut sorted = self
+            .active
+            .iter()
+            .rev()
+            .filter_map(|name| {
+                self.contexts
+                    .get(name)
+                    .map(|context| (name.as_str(), context))
+            })
+            .collect::<SmallVec<[_; 8]>>();
+        // Stable sort, so contexts of equal priority stay most recently pushed first.
+        sorted.sort_by_key(|(_, context)| Reverse(context.priority));
+        sorted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StringBindings;
+    use winit::VirtualKeyCode;
+
+    fn context(priority: i32) -> InputContext<StringBindings> {
+        InputContext::new(Bindings::new()).with_priority(priority)
+    }
+
+    #[test]
+    fn stack_order() {
+        let mut contexts = InputContexts::<StringBindings>::new();
+        contexts.insert("gameplay", context(0));
+        contexts.insert("menu", context(10));
+        contexts.insert("hud", context(0));
+        assert!(!contexts.push("unknown"));
+
+        assert!(contexts.push("menu"));
+        assert!(contexts.push("gameplay"));
+        assert!(contexts.push("hud"));
+        assert_eq!(
+            contexts.active_names().collect::<Vec<_>>(),
+            vec!["menu", "hud", "gameplay"]
+        );
+
+        assert!(contexts.push("gameplay"));
+        assert_eq!(
+            contexts.active_names().collect::<Vec<_>>(),
+            vec!["menu", "gameplay", "hud"]
+        );
+
+        assert_eq!(contexts.pop(), Some(String::from("gameplay")));
+        assert!(contexts.deactivate("menu"));
+        assert!(!contexts.deactivate("menu"));
+        assert_eq!(contexts.active_names().collect::<Vec<_>>(), vec!["hud"]);
+
+        contexts.remove("hud");
+        assert_eq!(contexts.active_names().next(), None);
+    }
+
+    #[test]
+    fn consumption() {
+        let mut bindings = Bindings::<StringBindings>::new();
+        bindings
+            .insert_action_binding(
+                String::from("close_menu"),
+                [Button::Key(VirtualKeyCode::Escape)].iter().cloned(),
+            )
+            .unwrap();
+        let escape = Button::Key(VirtualKeyCode::Escape);
+        let space = Button::Key(VirtualKeyCode::Space);
+
+        assert!(InputConsumption::Bound.consumes_button(&bindings, &escape));
+        assert!(!InputConsumption::Bound.consumes_button(&bindings, &space));
+        assert!(InputConsumption::All.consumes_button(&bindings, &space));
+        assert!(!InputConsumption::PassThrough.consumes_button(&bindings, &escape));
+    }
+}
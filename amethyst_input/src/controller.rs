@@ -56,6 +56,19 @@ pub enum ControllerButton {
     Start,
     /// The centermost button on the controller. Large and green on an Xbox controller.
     Guide,
+    /// An extra button, such as the share button of an Xbox Series X controller or the capture
+    /// button of a Nintendo Switch Pro controller.
+    Misc1,
+    /// The upper right paddle on the back of an Xbox Elite controller.
+    Paddle1,
+    /// The upper left paddle on the back of an Xbox Elite controller.
+    Paddle2,
+    /// The lower right paddle on the back of an Xbox Elite controller.
+    Paddle3,
+    /// The lower left paddle on the back of an Xbox Elite controller.
+    Paddle4,
+    /// The touchpad button of a PS4 or PS5 controller.
+    Touchpad,
 }
 
 /// Controller events generated by the SDL events system.
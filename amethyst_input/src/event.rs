@@ -3,9 +3,11 @@ use serde::{Deserialize, Serialize};
 use winit::{MouseButton, VirtualKeyCode};
 
 use super::{
-    bindings::BindingTypes,
+    bindings::{BindingError, BindingTypes},
     button::Button,
     controller::{ControllerAxis, ControllerButton},
+    player::InputDevice,
+    rebind::RebindTarget,
     scroll_direction::ScrollDirection,
 };
 
@@ -15,6 +17,10 @@ use super::{
 /// InputBundle or InputHandler.
 #[derive(PartialEq, Serialize, Deserialize, Debug, Derivative)]
 #[derivative(Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
 pub enum InputEvent<T>
 where
     T: BindingTypes,
@@ -58,6 +64,57 @@ where
         /// The amount the mouse moved vertically.
         delta_y: f32,
     },
+    /// A finger started touching the screen.
+    TouchStarted {
+        /// Identifier of the finger, unique while it touches.
+        id: u64,
+        /// Horizontal position of the touch in pixels.
+        x: f32,
+        /// Vertical position of the touch in pixels.
+        y: f32,
+    },
+    /// A finger touching the screen moved.
+    TouchMoved {
+        /// Identifier of the finger.
+        id: u64,
+        /// The amount the touch moved horizontally in pixels.
+        delta_x: f32,
+        /// The amount the touch moved vertically in pixels.
+        delta_y: f32,
+    },
+    /// A finger stopped touching the screen, or the touch was cancelled.
+    TouchEnded {
+        /// Identifier of the finger.
+        id: u64,
+    },
+    /// A single finger briefly touched the screen without moving, see `TouchSettings`.
+    TouchTapped {
+        /// Horizontal position of the tap in pixels.
+        x: f32,
+        /// Vertical position of the tap in pixels.
+        y: f32,
+    },
+    /// A single finger quickly moved across the screen, sent when it's lifted.
+    TouchSwiped {
+        /// Horizontal position where the swipe started in pixels.
+        start_x: f32,
+        /// Vertical position where the swipe started in pixels.
+        start_y: f32,
+        /// The distance the swipe moved horizontally in pixels.
+        delta_x: f32,
+        /// The distance the swipe moved vertically in pixels.
+        delta_y: f32,
+    },
+    /// The distance between two fingers touching the screen changed.
+    TouchPinched {
+        /// The new distance divided by the previous one, over 1 when the fingers spread.
+        scale: f32,
+    },
+    /// Two fingers touching the screen rotated around each other.
+    TouchRotated {
+        /// The rotation in radians, positive from the horizontal axis towards the vertical one.
+        angle: f32,
+    },
     /// The mousewheel was moved in either direction
     MouseWheelMoved(ScrollDirection),
     /// An axis value changed.
@@ -104,6 +161,20 @@ where
         /// The id for the controller disconnected.
         which: u32,
     },
+    /// A connected controller was assigned to a player, see `PlayerSlots`.
+    PlayerDeviceAssigned {
+        /// The player the device was assigned to.
+        player: usize,
+        /// The assigned device.
+        device: InputDevice,
+    },
+    /// A disconnected controller was unassigned from a player, see `PlayerSlots`.
+    PlayerDeviceUnassigned {
+        /// The player the device was assigned to.
+        player: usize,
+        /// The unassigned device.
+        device: InputDevice,
+    },
     /// The associated action had any related button or combination pressed.
     ///
     /// If a combination is bound to an action, it will be pressed
@@ -116,4 +187,22 @@ where
     ActionReleased(T::Action),
     /// The associated action has its mouse wheel moved.
     ActionWheelMoved(T::Action),
+    /// The associated action was pressed twice within `ActionTiming::double_tap_window`.
+    ActionDoubleTapped(T::Action),
+    /// The associated action was held down for `ActionTiming::long_press_duration`, sent once
+    /// per press.
+    ActionLongPressed(T::Action),
+    /// The inputs of the combo with the associated action were performed, see `ComboSet`.
+    ComboTriggered(T::Action),
+    /// A rebind started with `InputHandler::start_rebind` captured an input, and the bindings
+    /// were updated.
+    Rebound(RebindTarget<T>),
+    /// A rebind started with `InputHandler::start_rebind` captured an input conflicting with
+    /// other bindings, the bindings were left unchanged.
+    RebindFailed {
+        /// The binding the rebind was capturing.
+        target: RebindTarget<T>,
+        /// The conflict with the existing bindings.
+        error: BindingError<T>,
+    },
 }
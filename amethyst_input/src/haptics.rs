@@ -0,0 +1,295 @@
+//! Output to controllers: rumble and LEDs.
+
+use std::{
+    error, fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use derivative::Derivative;
+use log::warn;
+
+use amethyst_core::ecs::prelude::{System, Write};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// A request sent to a controller.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HapticsCommand {
+    /// Rumbles the controller, replacing any rumble in progress.
+    Rumble {
+        /// The id of the controller, as seen in `Button::Controller`.
+        controller_id: u32,
+        /// Strength of the low frequency motor, from 0 to 1.
+        low_frequency: f32,
+        /// Strength of the high frequency motor, from 0 to 1.
+        high_frequency: f32,
+        /// How long the rumble lasts.
+        duration: Duration,
+    },
+    /// Rumbles the triggers of the controller, replacing any trigger rumble in progress.
+    TriggerRumble {
+        /// The id of the controller, as seen in `Button::Controller`.
+        controller_id: u32,
+        /// Strength of the left trigger motor, from 0 to 1.
+        left: f32,
+        /// Strength of the right trigger motor, from 0 to 1.
+        right: f32,
+        /// How long the rumble lasts.
+        duration: Duration,
+    },
+    /// Sets the color of the LED of the controller.
+    SetLed {
+        /// The id of the controller, as seen in `Button::Controller`.
+        controller_id: u32,
+        /// Red component of the color.
+        red: u8,
+        /// Green component of the color.
+        green: u8,
+        /// Blue component of the color.
+        blue: u8,
+    },
+}
+
+impl HapticsCommand {
+    /// Returns the id of the controller the command is sent to.
+    pub fn controller_id(&self) -> u32 {
+        match *self {
+            HapticsCommand::Rumble { controller_id, .. }
+            | HapticsCommand::TriggerRumble { controller_id, .. }
+            | HapticsCommand::SetLed { controller_id, .. } => controller_id,
+        }
+    }
+}
+
+/// An error occurred while sending a `HapticsCommand`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HapticsError {
+    /// No connected controller has this id.
+    UnknownController(u32),
+    /// The controller or the backend doesn't support the command.
+    Unsupported,
+    /// The backend failed to send the command.
+    Backend(String),
+}
+
+impl fmt::Display for HapticsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            HapticsError::UnknownController(id) => write!(f, "No controller with id {}", id),
+            HapticsError::Unsupported => write!(f, "Haptics command not supported"),
+            HapticsError::Backend(ref msg) => write!(f, "Failed to send haptics command: {}", msg),
+        }
+    }
+}
+
+impl error::Error for HapticsError {}
+
+/// Sends `HapticsCommand`s to the controllers.
+pub trait HapticsBackend {
+    /// Sends a command to its controller.
+    fn execute(&mut self, command: &HapticsCommand) -> Result<(), HapticsError>;
+}
+
+/// A backend ignoring every command, used when the `sdl_controller` feature is disabled.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullHaptics;
+
+impl HapticsBackend for NullHaptics {
+    fn execute(&mut self, _: &HapticsCommand) -> Result<(), HapticsError> {
+        Ok(())
+    }
+}
+
+/// A backend recording the commands it receives, for tests.
+///
+/// Clones share the recorded commands, so a clone can be given to a `HapticsSystem` and the
+/// commands inspected from the other.
+#[derive(Debug, Default, Clone)]
+pub struct MockHaptics {
+    commands: Arc<Mutex<Vec<HapticsCommand>>>,
+}
+
+impl MockHaptics {
+    /// Creates a backend with no recorded command.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the commands received so far, oldest first.
+    pub fn commands(&self) -> Vec<HapticsCommand> {
+        self.commands.lock().expect("Mutex poisoned").clone()
+    }
+
+    /// Forgets the commands received so far.
+    pub fn clear(&self) {
+        self.commands.lock().expect("Mutex poisoned").clear();
+    }
+}
+
+impl HapticsBackend for MockHaptics {
+    fn execute(&mut self, command: &HapticsCommand) -> Result<(), HapticsError> {
+        self.commands
+            .lock()
+            .expect("Mutex poisoned")
+            .push(command.clone());
+        Ok(())
+    }
+}
+
+/// Resource queuing the commands systems send to the controllers.
+///
+/// The commands are sent at the end of the frame by the `SdlEventsSystem` with the
+/// `sdl_controller` feature, and dropped otherwise.
+///
+/// ```rust,edition2018,no_run,noplaypen
+/// # use std::time::Duration;
+/// # use amethyst_input::ControllerHaptics;
+/// # let mut haptics = ControllerHaptics::default();
+/// haptics.rumble(0, 0.2, 0.8, Duration::from_millis(300));
+/// ```
+#[derive(Debug, Default)]
+pub struct ControllerHaptics {
+    commands: Vec<HapticsCommand>,
+}
+
+impl ControllerHaptics {
+    /// Queues a command.
+    pub fn send(&mut self, command: HapticsCommand) {
+        self.commands.push(command);
+    }
+
+    /// Rumbles a controller, strengths range from 0 to 1.
+    pub fn rumble(
+        &mut self,
+        controller_id: u32,
+        low_frequency: f32,
+        high_frequency: f32,
+        duration: Duration,
+    ) {
+        self.send(HapticsCommand::Rumble {
+            controller_id,
+            low_frequency,
+            high_frequency,
+            duration,
+        });
+    }
+
+    /// Stops the rumble of a controller.
+    pub fn stop_rumble(&mut self, controller_id: u32) {
+        self.rumble(controller_id, 0.0, 0.0, Duration::from_secs(0));
+    }
+
+    /// Rumbles the triggers of a controller where supported, strengths range from 0 to 1.
+    pub fn trigger_rumble(
+        &mut self,
+        controller_id: u32,
+        left: f32,
+        right: f32,
+        duration: Duration,
+    ) {
+        self.send(HapticsCommand::TriggerRumble {
+            controller_id,
+            left,
+            right,
+            duration,
+        });
+    }
+
+    /// Sets the color of the LED of a controller where supported.
+    pub fn set_led(&mut self, controller_id: u32, red: u8, green: u8, blue: u8) {
+        self.send(HapticsCommand::SetLed {
+            controller_id,
+            red,
+            green,
+            blue,
+        });
+    }
+
+    /// Returns the commands queued since they were last sent.
+    pub fn pending(&self) -> &[HapticsCommand] {
+        &self.commands
+    }
+
+    /// Sends the queued commands with the backend.
+    ///
+    /// Unsupported commands and commands for disconnected controllers are ignored, other
+    /// failures are logged.
+    pub fn send_all<B: HapticsBackend + ?Sized>(&mut self, backend: &mut B) {
+        for command in self.commands.drain(..) {
+            match backend.execute(&command) {
+                Ok(())
+                | Err(HapticsError::Unsupported)
+                | Err(HapticsError::UnknownController(_)) => {}
+                Err(e) => warn!("{}", e),
+            }
+        }
+    }
+}
+
+/// Sends the commands of the `ControllerHaptics` resource with a backend every frame.
+///
+/// The `InputBundle` adds one with `NullHaptics` when the `sdl_controller` feature is disabled.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct HapticsSystem<B> {
+    #[derivative(Debug = "ignore")]
+    backend: B,
+}
+
+impl<B: HapticsBackend> HapticsSystem<B> {
+    /// Creates a system sending the commands with `backend`.
+    pub fn new(backend: B) -> Self {
+        HapticsSystem { backend }
+    }
+}
+
+impl<'a, B: HapticsBackend + Send> System<'a> for HapticsSystem<B> {
+    type SystemData = Write<'a, ControllerHaptics>;
+
+    fn run(&mut self, mut haptics: Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("haptics_system");
+
+        haptics.send_all(&mut self.backend);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use amethyst_core::ecs::prelude::{RunNow, World, WorldExt};
+
+    #[test]
+    fn mock_records_commands() {
+        let mock = MockHaptics::new();
+        let mut system = HapticsSystem::new(mock.clone());
+        let mut world = World::new();
+        world.insert(ControllerHaptics::default());
+        {
+            let mut haptics = world.write_resource::<ControllerHaptics>();
+            haptics.rumble(1, 0.5, 1.0, Duration::from_millis(200));
+            haptics.set_led(1, 255, 0, 0);
+        }
+        system.run_now(&world);
+
+        assert!(world
+            .read_resource::<ControllerHaptics>()
+            .pending()
+            .is_empty());
+        let commands = mock.commands();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(
+            commands[0],
+            HapticsCommand::Rumble {
+                controller_id: 1,
+                low_frequency: 0.5,
+                high_frequency: 1.0,
+                duration: Duration::from_millis(200),
+            }
+        );
+        assert_eq!(commands[1].controller_id(), 1);
+    }
+}
@@ -1,18 +1,23 @@
 //! World resource that handles all user input.
 
 use super::{
+    action_timing::{elapsed, ActionState, ActionTiming},
+    axis_processing::{radial_dead_zone, AxisProcessing},
     controller::{ControllerButton, ControllerEvent},
     event::InputEvent::{self, *},
+    player::{InputDevice, PlayerSlots},
     scroll_direction::ScrollDirection,
+    touch::{ActiveTouch, TouchSettings, Touches},
     *,
 };
-use amethyst_core::shrev::EventChannel;
+use amethyst_core::{shrev::EventChannel, timing::duration_to_secs};
 use derivative::Derivative;
+use fnv::{FnvHashMap as HashMap, FnvHashSet as HashSet};
 use smallvec::SmallVec;
-use std::{borrow::Borrow, hash::Hash};
+use std::{borrow::Borrow, hash::Hash, iter, time::Duration};
 use winit::{
     dpi::LogicalPosition, DeviceEvent, ElementState, Event, KeyboardInput, MouseButton,
-    MouseScrollDelta, VirtualKeyCode, WindowEvent,
+    MouseScrollDelta, Touch, TouchPhase, VirtualKeyCode, WindowEvent,
 };
 
 /// This struct holds state information about input devices.
@@ -27,6 +32,14 @@ where
 {
     /// Maps inputs to actions and axes.
     pub bindings: Bindings<T>,
+    /// Named contexts with their own bindings, consulted before `bindings` while active.
+    pub contexts: InputContexts<T>,
+    /// Thresholds used to recognize touch gestures, and whether touches emulate the mouse.
+    pub touch_settings: TouchSettings,
+    /// Player slots reading the shared player bindings from their own devices.
+    pub players: PlayerSlots<T>,
+    /// Thresholds used to detect double-taps and long-presses of actions.
+    pub action_timing: ActionTiming,
     /// Encodes the VirtualKeyCode and corresponding scancode.
     pressed_keys: SmallVec<[(VirtualKeyCode, u32); 12]>,
     pressed_mouse_buttons: SmallVec<[MouseButton; 12]>,
@@ -38,10 +51,32 @@ where
     /// while second is the ID used by incoming events.
     connected_controllers: SmallVec<[(u32, u32); 8]>,
     mouse_position: Option<(f32, f32)>,
+    touches: Touches,
     mouse_wheel_vertical: f32,
     mouse_wheel_horizontal: f32,
+    /// The binding captured by the next input.
+    rebind: Option<RebindTarget<T>>,
+    /// Whether a rebind changed the bindings since the last call to `take_rebound`.
+    rebound: bool,
+    /// Buttons held since a rebind captured them, their release doesn't release actions.
+    captured_buttons: SmallVec<[Button; 4]>,
+    /// Timing state of every action bound in `bindings` or an active context.
+    action_states: HashMap<T::Action, ActionState>,
+    /// Actions held down by `press_action`.
+    synthetic_actions: HashSet<T::Action>,
+    /// Axis values set by `set_axis_value`.
+    synthetic_axes: HashMap<T::Axis, f32>,
+    /// Smoothed values of the emulated axes with acceleration or gravity.
+    axis_states: HashMap<T::Axis, f32>,
+    /// Time of the events being processed, set by `set_time` and `send_actions_update`.
+    time: Duration,
+    /// Time of the last call to `send_actions_update`.
+    updated_at: Duration,
 }
 
+/// Minimal absolute value of a controller axis motion captured by `RebindTarget::Axis`.
+const REBIND_AXIS_THRESHOLD: f32 = 0.5;
+
 impl<T> InputHandler<T>
 where
     T: BindingTypes,
@@ -87,19 +122,10 @@ where
                             .iter()
                             .cloned(),
                         );
-                        self.send_axis_moved_events_key(event_handler, key_code, scancode);
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations.iter().filter(|c| {
-                                c.contains(&Button::Key(key_code))
-                                    || c.contains(&Button::ScanCode(scancode))
-                            }) {
-                                if combination
-                                    .iter()
-                                    .all(|button| self.button_is_down(*button))
-                                {
-                                    event_handler.single_write(ActionPressed(action.clone()));
-                                }
-                            }
+                        if !self.capture_button(Button::Key(key_code), event_handler) {
+                            let buttons = [Button::Key(key_code), Button::ScanCode(scancode)];
+                            self.send_axis_moved_events(event_handler, &buttons);
+                            self.send_action_pressed_events(event_handler, &buttons);
                         }
                     }
                 }
@@ -125,26 +151,10 @@ where
                             .iter()
                             .cloned(),
                         );
-                        self.send_axis_moved_events_key(event_handler, key_code, scancode);
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations {
-                                if combination.contains(&Button::Key(key_code))
-                                    && combination
-                                        .iter()
-                                        .filter(|b| b != &&Button::Key(key_code))
-                                        .all(|b| self.button_is_down(*b))
-                                {
-                                    event_handler.single_write(ActionReleased(action.clone()));
-                                }
-                                if combination.contains(&Button::ScanCode(scancode))
-                                    && combination
-                                        .iter()
-                                        .filter(|b| b != &&Button::ScanCode(scancode))
-                                        .all(|b| self.button_is_down(*b))
-                                {
-                                    event_handler.single_write(ActionReleased(action.clone()));
-                                }
-                            }
+                        if !self.release_captured(Button::Key(key_code)) {
+                            let buttons = [Button::Key(key_code), Button::ScanCode(scancode)];
+                            self.send_axis_moved_events(event_handler, &buttons);
+                            self.send_action_released_events(event_handler, &buttons);
                         }
                     }
                 }
@@ -153,88 +163,70 @@ where
                     button,
                     ..
                 } => {
-                    let mouse_button = button;
-                    if self
-                        .pressed_mouse_buttons
-                        .iter()
-                        .all(|&b| b != mouse_button)
-                    {
-                        self.pressed_mouse_buttons.push(mouse_button);
-                        event_handler.iter_write(
-                            [
-                                MouseButtonPressed(mouse_button),
-                                ButtonPressed(Button::Mouse(mouse_button)),
-                            ]
-                            .iter()
-                            .cloned(),
-                        );
-                        self.send_axis_moved_events_mouse(event_handler, mouse_button);
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations
-                                .iter()
-                                .filter(|c| c.contains(&Button::Mouse(mouse_button)))
-                            {
-                                if combination
-                                    .iter()
-                                    .all(|button| self.button_is_down(*button))
-                                {
-                                    event_handler.single_write(ActionPressed(action.clone()));
-                                }
-                            }
-                        }
-                    }
+                    self.press_mouse_button(button, event_handler);
                 }
                 WindowEvent::MouseInput {
                     state: ElementState::Released,
                     button,
                     ..
                 } => {
-                    let mouse_button = button;
-                    let index = self
-                        .pressed_mouse_buttons
-                        .iter()
-                        .position(|&b| b == mouse_button);
-                    if let Some(i) = index {
-                        self.pressed_mouse_buttons.swap_remove(i);
-                        event_handler.iter_write(
-                            [
-                                MouseButtonReleased(mouse_button),
-                                ButtonReleased(Button::Mouse(mouse_button)),
-                            ]
-                            .iter()
-                            .cloned(),
-                        );
-                        self.send_axis_moved_events_mouse(event_handler, mouse_button);
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations {
-                                if combination.contains(&Button::Mouse(mouse_button))
-                                    && combination
-                                        .iter()
-                                        .filter(|b| b != &&Button::Mouse(mouse_button))
-                                        .all(|b| self.button_is_down(*b))
-                                {
-                                    event_handler.single_write(ActionReleased(action.clone()));
-                                }
-                            }
-                        }
-                    }
+                    self.release_mouse_button(button, event_handler);
                 }
                 WindowEvent::CursorMoved {
                     position: LogicalPosition { x, y },
                     ..
                 } => {
-                    if let Some((old_x, old_y)) = self.mouse_position {
-                        event_handler.single_write(CursorMoved {
-                            delta_x: (x as f32) * hidpi - old_x,
-                            delta_y: (y as f32) * hidpi - old_y,
-                        });
+                    self.move_cursor(((x as f32) * hidpi, (y as f32) * hidpi), event_handler);
+                }
+                WindowEvent::Touch(Touch {
+                    phase,
+                    location: LogicalPosition { x, y },
+                    id,
+                    ..
+                }) => {
+                    let position = ((x as f32) * hidpi, (y as f32) * hidpi);
+                    let emulate_mouse = self.touch_settings.emulate_mouse;
+                    match phase {
+                        TouchPhase::Started => {
+                            if self.touches.start(id, position, self.time, event_handler)
+                                && emulate_mouse
+                            {
+                                self.move_cursor(position, event_handler);
+                                self.press_mouse_button(MouseButton::Left, event_handler);
+                            }
+                        }
+                        TouchPhase::Moved => {
+                            if self.touches.moved(id, position, event_handler) && emulate_mouse {
+                                self.move_cursor(position, event_handler);
+                            }
+                        }
+                        TouchPhase::Ended => {
+                            if self.touches.lifted(
+                                id,
+                                self.time,
+                                &self.touch_settings,
+                                event_handler,
+                            ) && emulate_mouse
+                            {
+                                self.release_mouse_button(MouseButton::Left, event_handler);
+                            }
+                        }
+                        TouchPhase::Cancelled => {
+                            if self.touches.cancelled(id, event_handler) && emulate_mouse {
+                                self.release_mouse_button(MouseButton::Left, event_handler);
+                            }
+                        }
                     }
-                    self.mouse_position = Some(((x as f32) * hidpi, (y as f32) * hidpi));
                 }
                 WindowEvent::Focused(false) => {
                     self.pressed_keys.clear();
                     self.pressed_mouse_buttons.clear();
+                    self.captured_buttons.retain(|button| match button {
+                        Button::Controller(..) => true,
+                        _ => false,
+                    });
                     self.mouse_position = None;
+                    self.touches.clear();
                 }
                 _ => {}
             },
@@ -257,6 +249,7 @@ where
                         self.mouse_wheel_vertical = delta_y.signum();
                     }
                     self.invoke_wheel_moved(delta_x, delta_y, event_handler);
+                    self.capture_mouse_wheel(delta_x, delta_y, event_handler);
                 }
                 DeviceEvent::MouseWheel {
                     delta: MouseScrollDelta::PixelDelta(LogicalPosition { x, y }),
@@ -268,6 +261,7 @@ where
                         self.mouse_wheel_vertical = y.signum() as f32;
                     }
                     self.invoke_wheel_moved(x as f32, y as f32, event_handler);
+                    self.capture_mouse_wheel(x as f32, y as f32, event_handler);
                 }
                 _ => {}
             },
@@ -298,6 +292,14 @@ where
                             self.controller_axes.push((controller_id, axis, value));
                         });
                     event_handler.single_write(event.into());
+                    if value.abs() >= REBIND_AXIS_THRESHOLD {
+                        self.capture_controller_axis(
+                            controller_id,
+                            axis,
+                            value < 0.0,
+                            event_handler,
+                        );
+                    }
                 }
             }
             ControllerButtonPressed { which, button } => {
@@ -317,18 +319,9 @@ where
                             .iter()
                             .cloned(),
                         );
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations
-                                .iter()
-                                .filter(|c| c.contains(&Button::Controller(controller_id, button)))
-                            {
-                                if combination
-                                    .iter()
-                                    .all(|button| self.button_is_down(*button))
-                                {
-                                    event_handler.single_write(ActionPressed(action.clone()));
-                                }
-                            }
+                        let button = Button::Controller(controller_id, button);
+                        if !self.capture_button(button, event_handler) {
+                            self.send_action_pressed_events(event_handler, &[button]);
                         }
                     }
                 }
@@ -349,21 +342,9 @@ where
                             .iter()
                             .cloned(),
                         );
-                        for (action, combinations) in self.bindings.actions.iter() {
-                            for combination in combinations {
-                                if combination.contains(&Button::Controller(controller_id, button))
-                                {
-                                    let down = combination
-                                        .iter()
-                                        .filter(|b| {
-                                            b != &&Button::Controller(controller_id, button)
-                                        })
-                                        .all(|b| self.button_is_down(*b));
-                                    if down {
-                                        event_handler.single_write(ActionReleased(action.clone()));
-                                    }
-                                }
-                            }
+                        let button = Button::Controller(controller_id, button);
+                        if !self.release_captured(button) {
+                            self.send_action_released_events(event_handler, &[button]);
                         }
                     }
                 }
@@ -377,6 +358,12 @@ where
                         .all(|&ids| ids.0 != controller_id)
                     {
                         self.connected_controllers.push((controller_id, which));
+                        if let Some(player) = self.players.auto_assign(controller_id) {
+                            event_handler.single_write(PlayerDeviceAssigned {
+                                player,
+                                device: InputDevice::Controller(controller_id),
+                            });
+                        }
                     }
                 }
             }
@@ -391,12 +378,114 @@ where
                         self.controller_axes.retain(|a| a.0 != controller_id);
                         self.pressed_controller_buttons
                             .retain(|b| b.0 != controller_id);
+                        let device = InputDevice::Controller(controller_id);
+                        if let Some(player) = self.players.unassign(device) {
+                            event_handler.single_write(PlayerDeviceUnassigned { player, device });
+                        }
                     }
                 }
             }
         }
     }
 
+    /// Updates the timing state of the actions, sending `InputEvent::ActionDoubleTapped` and
+    /// `InputEvent::ActionLongPressed` events, and the smoothed values of emulated axes, sending
+    /// `InputEvent::AxisMoved` events while they move.
+    ///
+    /// `now` is the time elapsed since an arbitrary fixed point, the `InputSystem` calls this
+    /// automatically with `Time::absolute_real_time` at the start of every frame after processing
+    /// the events of the frame.
+    pub fn send_actions_update(
+        &mut self,
+        now: Duration,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        // Taken out of the handler to update it while reading the bindings.
+        let mut states = std::mem::replace(&mut self.action_states, HashMap::default());
+        for state in states.values_mut() {
+            state.polled = None;
+        }
+        {
+            let mut poll = |action: &T::Action, down: bool| match states.get_mut(action) {
+                Some(state) => state.polled = Some(state.polled.unwrap_or(false) || down),
+                None => {
+                    let mut state = ActionState::default();
+                    state.polled = Some(down);
+                    states.insert(action.clone(), state);
+                }
+            };
+            let layers = self.layers();
+            for (layer, (bindings, _)) in layers.iter().enumerate() {
+                for (action, combinations) in bindings.actions.iter() {
+                    let down = combinations.iter().any(|combination| {
+                        combination
+                            .iter()
+                            .all(|button| self.button_is_down_in_layer(&layers, layer, button))
+                    });
+                    poll(action, down);
+                }
+            }
+            for action in self.synthetic_actions.iter() {
+                poll(action, true);
+            }
+        }
+        // Actions which are not bound anymore are released, then forgotten.
+        states.retain(|_, state| state.down || state.polled.is_some());
+
+        self.update_axis_states(
+            duration_to_secs(elapsed(self.updated_at, now)),
+            event_handler,
+        );
+        self.updated_at = now;
+        self.time = now;
+        for (action, state) in states.iter_mut() {
+            state.update(state.polled.unwrap_or(false), now, &self.action_timing);
+            if state.double_tapped {
+                event_handler.single_write(ActionDoubleTapped(action.clone()));
+            }
+            if state.long_pressed {
+                event_handler.single_write(ActionLongPressed(action.clone()));
+            }
+        }
+        self.action_states = states;
+    }
+
+    /// Starts listening for the next input to bind to `target`.
+    ///
+    /// The next pressed button is captured instead of triggering actions, keys are bound by their
+    /// `VirtualKeyCode`. `RebindTarget::Axis` captures the next controller axis moved past half its
+    /// range or mouse wheel motion instead. The updated bindings are checked with
+    /// `Bindings::check_invariants`, and either `InputEvent::Rebound` or
+    /// `InputEvent::RebindFailed` is sent.
+    ///
+    /// Returns false without listening if `target` is a `RebindTarget::EmulatedAxis` whose axis
+    /// isn't bound to an emulated axis.
+    pub fn start_rebind(&mut self, target: RebindTarget<T>) -> bool {
+        if let RebindTarget::EmulatedAxis { ref axis, .. } = target {
+            match self.bindings.axes.get(axis) {
+                Some(Axis::Emulated { .. }) => {}
+                _ => return false,
+            }
+        }
+        self.rebind = Some(target);
+        true
+    }
+
+    /// Stops listening for an input to rebind, returning the binding that was being captured.
+    pub fn cancel_rebind(&mut self) -> Option<RebindTarget<T>> {
+        self.rebind.take()
+    }
+
+    /// Returns the binding being captured, if a rebind is in progress.
+    pub fn rebind_target(&self) -> Option<&RebindTarget<T>> {
+        self.rebind.as_ref()
+    }
+
+    /// Returns whether a rebind changed the bindings since the last call, and resets it.
+    pub(crate) fn take_rebound(&mut self) -> bool {
+        std::mem::replace(&mut self.rebound, false)
+    }
+
     /// This function is to be called whenever a frame begins. It resets some input values.
     ///
     /// The `InputSystem` will call this automatically. If you're using that system, you
@@ -406,6 +495,15 @@ where
         self.mouse_wheel_horizontal = 0.0;
     }
 
+    /// Sets the time of the events sent next, used to time action presses and touches.
+    ///
+    /// `now` is the time elapsed since the same fixed point as in `send_actions_update`. The
+    /// `InputSystem` calls this automatically with `Time::absolute_real_time` before processing
+    /// the events of the frame.
+    pub fn set_time(&mut self, now: Duration) {
+        self.time = now;
+    }
+
     /// Returns an iterator over all keys that are down.
     pub fn keys_that_are_down(&self) -> impl Iterator<Item = VirtualKeyCode> + '_ {
         self.pressed_keys.iter().map(|k| k.0)
@@ -490,6 +588,23 @@ where
         self.mouse_position
     }
 
+    /// Returns an iterator over the fingers touching the screen.
+    pub fn touches(&self) -> impl Iterator<Item = &ActiveTouch> {
+        self.touches.active.iter()
+    }
+
+    /// Returns a finger touching the screen by its id.
+    pub fn touch(&self, id: u64) -> Option<&ActiveTouch> {
+        self.touches.active.iter().find(|touch| touch.id == id)
+    }
+
+    /// Returns the first finger which touched the screen, while it touches.
+    ///
+    /// This is the touch emulating the mouse with `TouchSettings::emulate_mouse`.
+    pub fn primary_touch(&self) -> Option<&ActiveTouch> {
+        self.touches.primary.and_then(|id| self.touch(id))
+    }
+
     /// Returns an iterator over all buttons that are down.
     pub fn buttons_that_are_down(&self) -> impl Iterator<Item = Button> + '_ {
         let mouse_buttons = self
@@ -517,60 +632,682 @@ where
     }
 
     /// Returns the value of an axis by the id, if the id doesn't exist this returns None.
+    ///
+    /// The axis is read from the topmost active context binding it, inputs consumed by a context
+    /// above that one are treated as released. The `AxisProcessing` of the axis in that context is
+    /// applied to the value.
     pub fn axis_value<A>(&self, id: &A) -> Option<f32>
     where
         T::Axis: Borrow<A>,
         A: Hash + Eq + ?Sized,
     {
-        self.bindings.axes.get(id).map(|a| match *a {
-            Axis::Emulated { pos, neg, .. } => {
-                match (self.button_is_down(pos), self.button_is_down(neg)) {
-                    (true, false) => 1.0,
-                    (false, true) => -1.0,
-                    _ => 0.0,
+        self.raw_axis_value(id)
+            .map(|(value, processing)| match processing {
+                Some(processing) => processing.apply(value),
+                None => value,
+            })
+    }
+
+    /// Returns the values of two axes read as the horizontal and vertical axes of a stick, or
+    /// None if either axis doesn't exist.
+    ///
+    /// The largest `AxisProcessing::radial_dead_zone` of the two axes is applied to the pair, then
+    /// the response curve and sensitivity of each axis are applied to the magnitude of the pair.
+    /// The returned vector is at most 1 long, so diagonals are not faster than straight motions.
+    pub fn axis_pair_value<A>(&self, x: &A, y: &A) -> Option<(f32, f32)>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        let default = AxisProcessing::default();
+        let (x, x_processing) = self.raw_axis_value(x)?;
+        let (y, y_processing) = self.raw_axis_value(y)?;
+        let x_processing = x_processing.unwrap_or(&default);
+        let y_processing = y_processing.unwrap_or(&default);
+
+        let dead_zone = x_processing
+            .radial_dead_zone
+            .max(y_processing.radial_dead_zone);
+        let (x, y) = radial_dead_zone(x, y, dead_zone);
+        let magnitude = (x * x + y * y).sqrt();
+        if magnitude == 0.0 {
+            return Some((0.0, 0.0));
+        }
+        let x = x / magnitude * x_processing.apply(magnitude);
+        let y = y / magnitude * y_processing.apply(magnitude);
+        let length = (x * x + y * y).sqrt().max(1.0);
+        Some((x / length, y / length))
+    }
+
+    /// Returns true if any of the actions bindings is down, or the action is held down by
+    /// `press_action`.
+    ///
+    /// If a binding represents a combination of buttons, all of them need to be down.
+    /// Bindings of every active context are considered, but a button consumed by a context only
+    /// counts as down for that context and the ones above it.
+    pub fn action_is_down<A>(&self, action: &A) -> Option<bool>
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        if self.synthetic_actions.contains(action) {
+            return Some(true);
+        }
+        let layers = self.layers();
+        layers
+            .iter()
+            .enumerate()
+            .filter_map(|(layer, bindings)| {
+                bindings.0.actions.get(action).map(|combinations| {
+                    combinations.iter().any(|combination| {
+                        combination
+                            .iter()
+                            .all(|button| self.button_is_down_in_layer(&layers, layer, button))
+                    })
+                })
+            })
+            .fold(None, |down, layer_down| {
+                Some(down.unwrap_or(false) || layer_down)
+            })
+    }
+
+    /// Returns the value of an axis for a player, read from the devices assigned to the player.
+    ///
+    /// The response curve and sensitivity of the axis are applied, but emulated axes are not
+    /// smoothed.
+    ///
+    /// Returns `None` if the player doesn't exist or its bindings don't have the axis.
+    pub fn axis_value_for<A>(&self, player: usize, id: &A) -> Option<f32>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        let bindings = self.players.player_bindings(player)?;
+        let value = self.player_axis_value(self.players.devices(player), bindings.axes.get(id)?);
+        Some(match bindings.axis_processing(id) {
+            Some(processing) => processing.apply(value),
+            None => value,
+        })
+    }
+
+    /// Sets the number of players of `players`, then assigns the connected controllers without a
+    /// player to the players without a controller.
+    pub fn set_player_count(
+        &mut self,
+        count: usize,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        self.players.set_player_count(count);
+        self.assign_connected_controllers(event_handler);
+    }
+
+    /// Assigns the connected controllers without a player to the players without a controller,
+    /// if `PlayerSlots::auto_assign_controllers` is set.
+    ///
+    /// Controllers are assigned when they connect, call this after replacing `players`.
+    pub fn assign_connected_controllers(
+        &mut self,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        let mut controller_ids = self
+            .connected_controllers
+            .iter()
+            .map(|ids| ids.0)
+            .collect::<SmallVec<[u32; 8]>>();
+        controller_ids.sort();
+        for controller_id in controller_ids {
+            if let Some(player) = self.players.auto_assign(controller_id) {
+                event_handler.single_write(PlayerDeviceAssigned {
+                    player,
+                    device: InputDevice::Controller(controller_id),
+                });
+            }
+        }
+    }
+
+    /// Returns true if any of the action bindings of a player is down on the devices assigned to
+    /// the player.
+    ///
+    /// Returns `None` if the player doesn't exist or its bindings don't have the action.
+    pub fn action_is_down_for<A>(&self, player: usize, action: &A) -> Option<bool>
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        let combinations = self.players.player_bindings(player)?.actions.get(action)?;
+        let devices = self.players.devices(player);
+        Some(combinations.iter().any(|combination| {
+            combination
+                .iter()
+                .all(|button| self.button_is_down_for(devices, *button))
+        }))
+    }
+
+    /// Holds an action down without any input device, until `release_action` is called.
+    ///
+    /// Sends `InputEvent::ActionPressed` if the action wasn't held down already. Use this, or an
+    /// `InputScript`, to drive the handler from tests or bots.
+    pub fn press_action(
+        &mut self,
+        action: T::Action,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        if !self.synthetic_actions.contains(&action) {
+            self.synthetic_actions.insert(action.clone());
+            self.action_states
+                .entry(action.clone())
+                .or_default()
+                .press(self.time, &self.action_timing);
+            event_handler.single_write(ActionPressed(action));
+        }
+    }
+
+    /// Releases an action held down by `press_action`, sending `InputEvent::ActionReleased`.
+    pub fn release_action<A>(&mut self, action: &A, event_handler: &mut EventChannel<InputEvent<T>>)
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        if let Some(action) = self.synthetic_actions.take(action) {
+            self.release_action_state(&action);
+            event_handler.single_write(ActionReleased(action));
+        }
+    }
+
+    /// Overrides the value of an axis, until `reset_axis_value` is called.
+    ///
+    /// The value is returned as is by `axis_value`, without the processing of the axis, and an
+    /// `InputEvent::AxisMoved` is sent.
+    pub fn set_axis_value(
+        &mut self,
+        axis: T::Axis,
+        value: f32,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        self.synthetic_axes.insert(axis.clone(), value);
+        event_handler.single_write(AxisMoved { axis, value });
+    }
+
+    /// Stops overriding the value of an axis set by `set_axis_value`.
+    ///
+    /// An `InputEvent::AxisMoved` is sent with the value of the axis read from its binding, or
+    /// 0.0 if it isn't bound.
+    pub fn reset_axis_value<A>(&mut self, axis: &A, event_handler: &mut EventChannel<InputEvent<T>>)
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        if let Some((axis, _)) = self.synthetic_axes.remove_entry(axis) {
+            let value = self.axis_value::<T::Axis>(&axis).unwrap_or(0.0);
+            event_handler.single_write(AxisMoved { axis, value });
+        }
+    }
+
+    /// Returns true if the action was pressed during the last frame.
+    ///
+    /// This and the other action timing queries are updated by `send_actions_update`, and return
+    /// false for unknown actions.
+    pub fn action_just_pressed<A>(&self, action: &A) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.action_state(action, |state| state.just_pressed)
+    }
+
+    /// Returns true if the action was released during the last frame.
+    pub fn action_just_released<A>(&self, action: &A) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.action_state(action, |state| state.just_released)
+    }
+
+    /// Returns for how long the action has been held down, or `None` if it's not down.
+    pub fn action_held_for<A>(&self, action: &A) -> Option<Duration>
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.action_states
+            .get(action)
+            .filter(|state| state.down)
+            .map(|state| elapsed(state.pressed_at, self.time))
+    }
+
+    /// Returns true if the action was pressed during the last frame, within
+    /// `ActionTiming::double_tap_window` of its previous press.
+    pub fn action_double_tapped<A>(&self, action: &A) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.action_state(action, |state| state.double_tapped)
+    }
+
+    /// Returns true if the action has been held down for `ActionTiming::long_press_duration`
+    /// during the last frame.
+    pub fn action_long_pressed<A>(&self, action: &A) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.action_state(action, |state| state.long_pressed)
+    }
+
+    /// Returns true if the action was pressed within the last `ActionTiming::buffer_window`, and
+    /// the press wasn't consumed yet.
+    ///
+    /// Use this to queue actions pressed slightly before they can be performed, for example
+    /// an attack pressed during the end of the previous one.
+    pub fn action_buffered<A>(&self, action: &A) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        self.action_state(action, |state| match state.buffered_at {
+            Some(buffered_at) => {
+                elapsed(buffered_at, self.time) <= self.action_timing.buffer_window
+            }
+            None => false,
+        })
+    }
+
+    /// Consumes a buffered press of the action, returning true if there was one.
+    pub fn consume_buffered_action<A>(&mut self, action: &A) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        let buffered = self.action_buffered(action);
+        if let Some(state) = self.action_states.get_mut(action) {
+            state.buffered_at = None;
+        }
+        buffered
+    }
+
+    fn action_state<A, F>(&self, action: &A, f: F) -> bool
+    where
+        T::Action: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+        F: FnOnce(&ActionState) -> bool,
+    {
+        self.action_states.get(action).map(f).unwrap_or(false)
+    }
+
+    /// Returns the bindings of the active contexts in the order they are consulted, followed by
+    /// `bindings`, along with the inputs they consume.
+    fn layers(&self) -> SmallVec<[Layer<'_, T>; 8]> {
+        self.contexts
+            .active()
+            .map(|context| (&context.bindings, context.consumption))
+            .chain(iter::once((&self.bindings, InputConsumption::PassThrough)))
+            .collect()
+    }
+
+    /// Returns true if no layer above `layer` consumes the button.
+    fn button_reaches_layer(layers: &[Layer<'_, T>], layer: usize, button: &Button) -> bool {
+        layers[..layer]
+            .iter()
+            .all(|(bindings, consumption)| !consumption.consumes_button(*bindings, button))
+    }
+
+    fn button_is_down_in_layer(
+        &self,
+        layers: &[Layer<'_, T>],
+        layer: usize,
+        button: &Button,
+    ) -> bool {
+        Self::button_reaches_layer(layers, layer, button) && self.button_is_down(*button)
+    }
+
+    fn layer_axis_value(&self, layers: &[Layer<'_, T>], layer: usize, axis: &Axis) -> f32 {
+        if let Axis::Emulated { pos, neg } = *axis {
+            return match (
+                self.button_is_down_in_layer(layers, layer, &pos),
+                self.button_is_down_in_layer(layers, layer, &neg),
+            ) {
+                (true, false) => 1.0,
+                (false, true) => -1.0,
+                _ => 0.0,
+            };
+        }
+        if layers[..layer]
+            .iter()
+            .any(|(bindings, consumption)| consumption.consumes_axis(*bindings, axis))
+        {
+            return 0.0;
+        }
+        match *axis {
+            Axis::Controller {
+                controller_id,
+                axis,
+                invert,
+                dead_zone,
+                ..
+            } => self.controller_axis_value(controller_id, axis, invert, dead_zone),
+            Axis::MouseWheel { horizontal } => self.mouse_wheel_value(horizontal),
+            Axis::Emulated { .. } => unreachable!(),
+        }
+    }
+
+    fn controller_axis_value(
+        &self,
+        controller_id: u32,
+        axis: ControllerAxis,
+        invert: bool,
+        dead_zone: f64,
+    ) -> f32 {
+        self.controller_axes
+            .iter()
+            .find(|&&(id, a, _)| id == controller_id && a == axis)
+            .map(|&(_, _, val)| if invert { -val } else { val })
+            .map(|val| {
+                let dead_zone = dead_zone as f32;
+                if val < -dead_zone {
+                    (val + dead_zone) / (1.0 - dead_zone)
+                } else if val > dead_zone {
+                    (val - dead_zone) / (1.0 - dead_zone)
+                } else {
+                    0.0
+                }
+            })
+            .unwrap_or(0.0)
+    }
+
+    /// Checks if a button is down on the devices of a player, for any assigned controller if
+    /// it's a controller button.
+    fn button_is_down_for(&self, devices: &[InputDevice], button: Button) -> bool {
+        match button {
+            Button::Key(_) | Button::ScanCode(_) => {
+                devices.contains(&InputDevice::Keyboard) && self.button_is_down(button)
+            }
+            Button::Mouse(_) | Button::MouseWheel(_) => {
+                devices.contains(&InputDevice::Mouse) && self.button_is_down(button)
+            }
+            Button::Controller(_, button) => devices.iter().any(|device| match *device {
+                InputDevice::Controller(id) => self.controller_button_is_down(id, button),
+                _ => false,
+            }),
+        }
+    }
+
+    /// Returns the value of an axis on the devices of a player, the one furthest from zero if
+    /// several controllers are assigned.
+    fn player_axis_value(&self, devices: &[InputDevice], axis: &Axis) -> f32 {
+        match *axis {
+            Axis::Emulated { pos, neg } => match (
+                self.button_is_down_for(devices, pos),
+                self.button_is_down_for(devices, neg),
+            ) {
+                (true, false) => 1.0,
+                (false, true) => -1.0,
+                _ => 0.0,
+            },
+            Axis::Controller {
+                axis,
+                invert,
+                dead_zone,
+                ..
+            } => devices
+                .iter()
+                .filter_map(|device| match *device {
+                    InputDevice::Controller(id) => {
+                        Some(self.controller_axis_value(id, axis, invert, dead_zone))
+                    }
+                    _ => None,
+                })
+                .fold(0.0, |value: f32, other: f32| {
+                    if other.abs() > value.abs() {
+                        other
+                    } else {
+                        value
+                    }
+                }),
+            Axis::MouseWheel { horizontal } => {
+                if devices.contains(&InputDevice::Mouse) {
+                    self.mouse_wheel_value(horizontal)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Returns the value of an axis before its response curve and sensitivity, along with the
+    /// processing of the axis.
+    fn raw_axis_value<A>(&self, id: &A) -> Option<(f32, Option<&AxisProcessing>)>
+    where
+        T::Axis: Borrow<A>,
+        A: Hash + Eq + ?Sized,
+    {
+        if let Some(&value) = self.synthetic_axes.get(id) {
+            return Some((value, None));
+        }
+        let layers = self.layers();
+        let (layer, axis) = layers
+            .iter()
+            .enumerate()
+            .find_map(|(layer, bindings)| bindings.0.axes.get(id).map(|axis| (layer, axis)))?;
+        let bindings: &Bindings<T> = layers[layer].0;
+        let processing = bindings.axis_processing(id);
+        let value = match (axis, processing) {
+            (Axis::Emulated { .. }, Some(processing)) if processing.smooths() => {
+                self.axis_states.get(id).cloned().unwrap_or(0.0)
+            }
+            _ => self.layer_axis_value(&layers, layer, axis),
+        };
+        Some((value, processing))
+    }
+
+    /// Moves the smoothed emulated axes towards their current value for `delta_seconds`.
+    fn update_axis_states(
+        &mut self,
+        delta_seconds: f32,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        let mut axis_states = HashMap::default();
+        {
+            let layers = self.layers();
+            for (layer, (bindings, _)) in layers.iter().enumerate() {
+                for (id, processing) in bindings.axis_processing.iter() {
+                    // Axes bound in a context above shadow this one.
+                    if !processing.smooths()
+                        || layers[..layer]
+                            .iter()
+                            .any(|(bindings, _)| bindings.axes.contains_key(id))
+                    {
+                        continue;
+                    }
+                    if let Some(axis @ Axis::Emulated { .. }) = bindings.axes.get(id) {
+                        let target = self.layer_axis_value(&layers, layer, axis);
+                        let value = self.axis_states.get(id).cloned().unwrap_or(0.0);
+                        let smoothed = processing.smooth(value, target, delta_seconds);
+                        if smoothed != value {
+                            event_handler.single_write(AxisMoved {
+                                axis: id.clone(),
+                                value: processing.apply(smoothed),
+                            });
+                        }
+                        axis_states.insert(id.clone(), smoothed);
+                    }
+                }
+            }
+        }
+        self.axis_states = axis_states;
+    }
+
+    /// Binds `button` to the rebind target, returns false if no rebind captures buttons.
+    fn capture_button(
+        &mut self,
+        button: Button,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) -> bool {
+        let bindings = match self.rebind {
+            Some(RebindTarget::Action(ref action)) => {
+                let mut bindings = self.bindings.clone();
+                let mut combinations = SmallVec::new();
+                combinations.push(SmallVec::from_slice(&[button]));
+                bindings.actions.insert(action.clone(), combinations);
+                bindings
+            }
+            Some(RebindTarget::EmulatedAxis { ref axis, positive }) => {
+                let mut bindings = self.bindings.clone();
+                if let Some(Axis::Emulated { pos, neg }) = bindings.axes.get_mut(axis) {
+                    if positive {
+                        *pos = button;
+                    } else {
+                        *neg = button;
+                    }
                 }
+                bindings
+            }
+            Some(RebindTarget::Axis(_)) | None => return false,
+        };
+        self.captured_buttons.push(button);
+        self.apply_rebind(bindings, event_handler);
+        true
+    }
+
+    fn capture_controller_axis(
+        &mut self,
+        controller_id: u32,
+        axis: ControllerAxis,
+        invert: bool,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        if let Some(RebindTarget::Axis(ref id)) = self.rebind {
+            let mut bindings = self.bindings.clone();
+            let dead_zone = match bindings.axes.get(id) {
+                Some(Axis::Controller { dead_zone, .. }) => *dead_zone,
+                _ => 0.0,
+            };
+            bindings.axes.insert(
+                id.clone(),
+                Axis::Controller {
+                    controller_id,
+                    axis,
+                    invert,
+                    dead_zone,
+                },
+            );
+            self.apply_rebind(bindings, event_handler);
+        }
+    }
+
+    fn capture_mouse_wheel(
+        &mut self,
+        delta_x: f32,
+        delta_y: f32,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        if let Some(RebindTarget::Axis(ref id)) = self.rebind {
+            let mut bindings = self.bindings.clone();
+            bindings.axes.insert(
+                id.clone(),
+                Axis::MouseWheel {
+                    horizontal: delta_x.abs() > delta_y.abs(),
+                },
+            );
+            self.apply_rebind(bindings, event_handler);
+        }
+    }
+
+    /// Forgets a button captured by a rebind, returns whether `button` was captured.
+    fn release_captured(&mut self, button: Button) -> bool {
+        match self.captured_buttons.iter().position(|&b| b == button) {
+            Some(index) => {
+                self.captured_buttons.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ends the rebind, replacing the bindings if they uphold their invariants.
+    fn apply_rebind(
+        &mut self,
+        mut bindings: Bindings<T>,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        let target = self
+            .rebind
+            .take()
+            .expect("Unreachable: Only called while a rebind is in progress.");
+        match bindings.check_invariants() {
+            Ok(()) => {
+                self.bindings = bindings;
+                self.rebound = true;
+                event_handler.single_write(Rebound(target));
+            }
+            Err(error) => event_handler.single_write(RebindFailed { target, error }),
+        }
+    }
+
+    fn press_mouse_button(
+        &mut self,
+        mouse_button: MouseButton,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        if self
+            .pressed_mouse_buttons
+            .iter()
+            .all(|&b| b != mouse_button)
+        {
+            self.pressed_mouse_buttons.push(mouse_button);
+            event_handler.iter_write(
+                [
+                    MouseButtonPressed(mouse_button),
+                    ButtonPressed(Button::Mouse(mouse_button)),
+                ]
+                .iter()
+                .cloned(),
+            );
+            if !self.capture_button(Button::Mouse(mouse_button), event_handler) {
+                let buttons = [Button::Mouse(mouse_button)];
+                self.send_axis_moved_events(event_handler, &buttons);
+                self.send_action_pressed_events(event_handler, &buttons);
             }
-            Axis::Controller {
-                controller_id,
-                axis,
-                invert,
-                dead_zone,
-                ..
-            } => self
-                .controller_axes
+        }
+    }
+
+    fn release_mouse_button(
+        &mut self,
+        mouse_button: MouseButton,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        let index = self
+            .pressed_mouse_buttons
+            .iter()
+            .position(|&b| b == mouse_button);
+        if let Some(i) = index {
+            self.pressed_mouse_buttons.swap_remove(i);
+            event_handler.iter_write(
+                [
+                    MouseButtonReleased(mouse_button),
+                    ButtonReleased(Button::Mouse(mouse_button)),
+                ]
                 .iter()
-                .find(|&&(id, a, _)| id == controller_id && a == axis)
-                .map(|&(_, _, val)| if invert { -val } else { val })
-                .map(|val| {
-                    let dead_zone = dead_zone as f32;
-                    if val < -dead_zone {
-                        (val + dead_zone) / (1.0 - dead_zone)
-                    } else if val > dead_zone {
-                        (val - dead_zone) / (1.0 - dead_zone)
-                    } else {
-                        0.0
-                    }
-                })
-                .unwrap_or(0.0),
-            Axis::MouseWheel { horizontal } => self.mouse_wheel_value(horizontal),
-        })
+                .cloned(),
+            );
+            if !self.release_captured(Button::Mouse(mouse_button)) {
+                let buttons = [Button::Mouse(mouse_button)];
+                self.send_axis_moved_events(event_handler, &buttons);
+                self.send_action_released_events(event_handler, &buttons);
+            }
+        }
     }
 
-    /// Returns true if any of the actions bindings is down.
-    ///
-    /// If a binding represents a combination of buttons, all of them need to be down.
-    pub fn action_is_down<A>(&self, action: &A) -> Option<bool>
-    where
-        T::Action: Borrow<A>,
-        A: Hash + Eq + ?Sized,
-    {
-        self.bindings.actions.get(action).map(|combinations| {
-            combinations.iter().any(|combination| {
-                combination
-                    .iter()
-                    .all(|button| self.button_is_down(*button))
-            })
-        })
+    /// Moves the cursor to a position in pixels.
+    fn move_cursor(&mut self, (x, y): (f32, f32), event_handler: &mut EventChannel<InputEvent<T>>) {
+        if let Some((old_x, old_y)) = self.mouse_position {
+            event_handler.single_write(CursorMoved {
+                delta_x: x - old_x,
+                delta_y: y - old_y,
+            });
+        }
+        self.mouse_position = Some((x, y));
     }
 
     /// Retrieve next free controller number to allocate new controller to
@@ -589,6 +1326,15 @@ where
         }
     }
 
+    /// Map controller_id into the controller's index used by external events
+    #[cfg(feature = "sdl_controller")]
+    pub(crate) fn controller_id_to_idx(&self, controller_id: u32) -> Option<u32> {
+        self.connected_controllers
+            .iter()
+            .find(|ids| ids.0 == controller_id)
+            .map(|ids| ids.1)
+    }
+
     /// Map controller's index from external event into controller_id
     fn controller_idx_to_id(&self, index: u32) -> Option<u32> {
         self.connected_controllers
@@ -633,26 +1379,21 @@ where
         };
 
         // check for actions being bound to any invoked mouse wheel
-        for (action, combinations) in self.bindings.actions.iter() {
-            for combination in combinations {
-                if let Some(dir) = dir_x {
-                    if combination.contains(&Button::MouseWheel(dir))
-                        && combination
-                            .iter()
-                            .filter(|b| **b != Button::MouseWheel(dir))
-                            .all(|b| self.button_is_down(*b))
-                    {
-                        events.push(ActionWheelMoved(action.clone()));
-                    }
-                }
-                if let Some(dir) = dir_y {
-                    if combination.contains(&Button::MouseWheel(dir))
-                        && combination
-                            .iter()
-                            .filter(|b| **b != Button::MouseWheel(dir))
-                            .all(|b| self.button_is_down(*b))
-                    {
-                        events.push(ActionWheelMoved(action.clone()));
+        let layers = self.layers();
+        for (layer, (bindings, _)) in layers.iter().enumerate() {
+            for (action, combinations) in bindings.actions.iter() {
+                for combination in combinations {
+                    for dir in dir_x.iter().chain(dir_y.iter()) {
+                        let wheel = Button::MouseWheel(*dir);
+                        if combination.contains(&wheel)
+                            && Self::button_reaches_layer(&layers, layer, &wheel)
+                            && combination
+                                .iter()
+                                .filter(|b| **b != wheel)
+                                .all(|b| self.button_is_down_in_layer(&layers, layer, b))
+                        {
+                            events.push(ActionWheelMoved(action.clone()));
+                        }
                     }
                 }
             }
@@ -662,86 +1403,128 @@ where
         event_handler.iter_write(events);
     }
 
-    fn send_axis_moved_events_key(
-        &self,
+    /// Sends `ActionPressed` for every combination containing one of the pressed `buttons`
+    /// which is now entirely down.
+    fn send_action_pressed_events(
+        &mut self,
         event_handler: &mut EventChannel<InputEvent<T>>,
-        key_code: VirtualKeyCode,
-        scancode: u32,
+        buttons: &[Button],
     ) {
-        for (axis, input_axis) in self.bindings.axes.iter() {
-            if let Axis::Emulated { pos, neg } = input_axis {
-                let value = self
-                    .axis_value(axis)
-                    .expect("Unreachable: `axis` is from bindings axes.");
-                match *pos {
-                    Button::Key(key_code_pos) if key_code_pos == key_code => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
-                    }
-                    Button::ScanCode(scancode_pos) if scancode_pos == scancode => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
+        let mut pressed = SmallVec::<[T::Action; 4]>::new();
+        {
+            let layers = self.layers();
+            for (layer, (bindings, _)) in layers.iter().enumerate() {
+                for (action, combinations) in bindings.actions.iter() {
+                    for combination in combinations
+                        .iter()
+                        .filter(|c| buttons.iter().any(|button| c.contains(button)))
+                    {
+                        if combination
+                            .iter()
+                            .all(|button| self.button_is_down_in_layer(&layers, layer, button))
+                        {
+                            pressed.push(action.clone());
+                        }
                     }
-                    _ => {}
                 }
+            }
+        }
+        for action in pressed {
+            self.action_states
+                .entry(action.clone())
+                .or_default()
+                .press(self.time, &self.action_timing);
+            event_handler.single_write(ActionPressed(action));
+        }
+    }
 
-                match *neg {
-                    Button::Key(key_code_neg) if key_code_neg == key_code => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
-                    }
-                    Button::ScanCode(scancode_neg) if scancode_neg == scancode => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
+    /// Sends `ActionReleased` for every combination containing one of the released `buttons`
+    /// whose other buttons are still down.
+    ///
+    /// The action is released once none of its combinations hold it down anymore.
+    fn send_action_released_events(
+        &mut self,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+        buttons: &[Button],
+    ) {
+        let mut released = SmallVec::<[T::Action; 4]>::new();
+        {
+            let layers = self.layers();
+            for (layer, (bindings, _)) in layers.iter().enumerate() {
+                for (action, combinations) in bindings.actions.iter() {
+                    for combination in combinations {
+                        for button in buttons {
+                            if combination.contains(button)
+                                && Self::button_reaches_layer(&layers, layer, button)
+                                && combination
+                                    .iter()
+                                    .filter(|b| b != &button)
+                                    .all(|b| self.button_is_down_in_layer(&layers, layer, b))
+                            {
+                                released.push(action.clone());
+                            }
+                        }
                     }
-                    _ => {}
                 }
             }
         }
+        for action in released {
+            self.release_action_state(&action);
+            event_handler.single_write(ActionReleased(action));
+        }
+    }
+
+    /// Releases the timing state of an action, unless another binding still holds it down.
+    fn release_action_state(&mut self, action: &T::Action) {
+        if !self.action_is_down(action).unwrap_or(false) {
+            if let Some(state) = self.action_states.get_mut(action) {
+                state.release();
+            }
+        }
     }
 
-    fn send_axis_moved_events_mouse(
+    /// Sends `AxisMoved` for every emulated axis made of one of the `buttons`.
+    fn send_axis_moved_events(
         &self,
         event_handler: &mut EventChannel<InputEvent<T>>,
-        mouse_button: MouseButton,
+        buttons: &[Button],
     ) {
-        for (axis, input_axis) in self.bindings.axes.iter() {
-            if let Axis::Emulated { pos, neg } = input_axis {
-                let value = self
-                    .axis_value(axis)
-                    .expect("Unreachable: `axis` is from bindings axes.");
-                match *pos {
-                    Button::Mouse(mouse_button_pos) if mouse_button_pos == mouse_button => {
-                        event_handler.single_write(AxisMoved {
-                            axis: axis.clone(),
-                            value,
-                        });
-                    }
-                    _ => {}
-                }
-
-                match *neg {
-                    Button::Mouse(mouse_button_neg) if mouse_button_neg == mouse_button => {
+        let layers = self.layers();
+        for (layer, (bindings, _)) in layers.iter().enumerate() {
+            for (axis, input_axis) in bindings.axes.iter() {
+                if let Axis::Emulated { pos, neg } = input_axis {
+                    let moved = buttons.iter().any(|button| {
+                        (button == pos || button == neg)
+                            && Self::button_reaches_layer(&layers, layer, button)
+                    });
+                    // Axes bound in a context above shadow this one.
+                    let shadowed = layers[..layer]
+                        .iter()
+                        .any(|(bindings, _)| bindings.axes.contains_key(axis));
+                    let processing = bindings.axis_processing(axis);
+                    // Smoothed axes are sent by `update_axis_states`.
+                    let smoothed = processing
+                        .map(|processing| processing.smooths())
+                        .unwrap_or(false);
+                    if moved && !shadowed && !smoothed {
+                        let value = self.layer_axis_value(&layers, layer, input_axis);
                         event_handler.single_write(AxisMoved {
                             axis: axis.clone(),
-                            value,
+                            value: match processing {
+                                Some(processing) => processing.apply(value),
+                                None => value,
+                            },
                         });
                     }
-                    _ => {}
                 }
             }
         }
     }
 }
 
+/// Bindings of a context, along with the inputs it consumes.
+type Layer<'a, T> = (&'a Bindings<T>, InputConsumption);
+
 #[cfg(test)]
 mod tests {
     use std::fmt::Debug;
@@ -1201,6 +1984,424 @@ mod tests {
         assert_ulps_eq!(handler.mouse_wheel_value(true), -1.0);
     }
 
+    #[test]
+    fn context_consumes_bound_input() {
+        // Bind Escape in both the global bindings and a "menu" context
+        // While the context is active, Escape only triggers the context action.
+        // Other buttons and the global action go through once the context is popped.
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        handler
+            .bindings
+            .insert_action_binding(
+                String::from("pause"),
+                [Button::Key(VirtualKeyCode::Escape)].iter().cloned(),
+            )
+            .unwrap();
+        handler
+            .bindings
+            .insert_action_binding(
+                String::from("jump"),
+                [Button::Key(VirtualKeyCode::Space)].iter().cloned(),
+            )
+            .unwrap();
+        let mut menu_bindings = Bindings::new();
+        menu_bindings
+            .insert_action_binding(
+                String::from("close_menu"),
+                [Button::Key(VirtualKeyCode::Escape)].iter().cloned(),
+            )
+            .unwrap();
+        handler
+            .contexts
+            .insert("menu", InputContext::new(menu_bindings));
+        assert!(handler.contexts.push("menu"));
+
+        handler.send_event(&key_press(1, VirtualKeyCode::Escape), &mut events, HIDPI);
+        handler.send_event(&key_press(57, VirtualKeyCode::Space), &mut events, HIDPI);
+        let actions = events
+            .read(&mut reader)
+            .filter_map(|event| match event {
+                InputEvent::ActionPressed(action) => Some(action.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![String::from("close_menu"), String::from("jump")]
+        );
+        assert_eq!(handler.action_is_down("close_menu"), Some(true));
+        assert_eq!(handler.action_is_down("pause"), Some(false));
+        assert_eq!(handler.action_is_down("jump"), Some(true));
+
+        assert_eq!(handler.contexts.pop(), Some(String::from("menu")));
+        assert_eq!(handler.action_is_down("close_menu"), None);
+        assert_eq!(handler.action_is_down("pause"), Some(true));
+    }
+
+    #[test]
+    fn context_consumes_all_input() {
+        use approx::assert_ulps_eq;
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        handler
+            .bindings
+            .insert_axis(
+                String::from("horizontal"),
+                Axis::Emulated {
+                    pos: Button::Key(VirtualKeyCode::D),
+                    neg: Button::Key(VirtualKeyCode::A),
+                },
+            )
+            .unwrap();
+        handler.contexts.insert(
+            "text_field",
+            InputContext::new(Bindings::new()).with_consumption(InputConsumption::All),
+        );
+
+        handler.send_event(&key_press(32, VirtualKeyCode::D), &mut events, HIDPI);
+        assert_ulps_eq!(handler.axis_value("horizontal").unwrap(), 1.0);
+        handler.contexts.push("text_field");
+        assert_ulps_eq!(handler.axis_value("horizontal").unwrap(), 0.0);
+        assert!(handler.key_is_down(VirtualKeyCode::D));
+    }
+
+    #[test]
+    fn rebind_action() {
+        // Start a rebind, the next key press is bound to the action instead of triggering actions.
+        // A key already bound to another action conflicts and leaves the bindings unchanged.
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        handler
+            .bindings
+            .insert_action_binding(
+                String::from("jump"),
+                [Button::Key(VirtualKeyCode::Space)].iter().cloned(),
+            )
+            .unwrap();
+        handler
+            .bindings
+            .insert_action_binding(
+                String::from("fire"),
+                [Button::Key(VirtualKeyCode::X)].iter().cloned(),
+            )
+            .unwrap();
+
+        let target = RebindTarget::Action(String::from("jump"));
+        assert!(handler.start_rebind(target.clone()));
+        handler.send_event(&key_press(31, VirtualKeyCode::X), &mut events, HIDPI);
+        handler.send_event(&key_release(31, VirtualKeyCode::X), &mut events, HIDPI);
+        assert!(events.read(&mut reader).any(|event| match event {
+            InputEvent::RebindFailed { target: t, .. } => *t == target,
+            _ => false,
+        }));
+        assert_eq!(handler.rebind_target(), None);
+        assert!(!handler.take_rebound());
+
+        assert!(handler.start_rebind(target.clone()));
+        handler.send_event(&key_press(57, VirtualKeyCode::W), &mut events, HIDPI);
+        let event_vec = events.read(&mut reader).cloned().collect::<Vec<_>>();
+        assert!(event_vec.contains(&InputEvent::Rebound(target)));
+        assert!(!event_vec.contains(&InputEvent::ActionPressed(String::from("jump"))));
+        handler.send_event(&key_release(57, VirtualKeyCode::W), &mut events, HIDPI);
+        assert!(!events
+            .read(&mut reader)
+            .any(|event| *event == InputEvent::ActionReleased(String::from("jump"))));
+        assert!(handler.take_rebound());
+        assert_eq!(
+            handler.bindings.action_bindings("jump").collect::<Vec<_>>(),
+            vec![[Button::Key(VirtualKeyCode::W)]]
+        );
+        assert!(!handler.start_rebind(RebindTarget::EmulatedAxis {
+            axis: String::from("horizontal"),
+            positive: true,
+        }));
+    }
+
+    #[test]
+    fn action_timing() {
+        // Tap an action twice, then hold it down.
+        // Check the just pressed, buffered and held queries, and the double-tap and long-press events.
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        handler
+            .bindings
+            .insert_action_binding(
+                String::from("dash"),
+                [Button::Key(VirtualKeyCode::D)].iter().cloned(),
+            )
+            .unwrap();
+        let ms = Duration::from_millis;
+
+        handler.send_event(&key_press(32, VirtualKeyCode::D), &mut events, HIDPI);
+        handler.send_actions_update(ms(0), &mut events);
+        assert!(handler.action_just_pressed("dash"));
+        assert!(handler.action_buffered("dash"));
+        handler.send_event(&key_release(32, VirtualKeyCode::D), &mut events, HIDPI);
+        handler.send_actions_update(ms(100), &mut events);
+        assert!(handler.action_just_released("dash"));
+        assert!(handler.consume_buffered_action("dash"));
+        assert!(!handler.action_buffered("dash"));
+
+        handler.set_time(ms(200));
+        handler.send_event(&key_press(32, VirtualKeyCode::D), &mut events, HIDPI);
+        handler.send_actions_update(ms(200), &mut events);
+        assert!(handler.action_double_tapped("dash"));
+        handler.send_actions_update(ms(800), &mut events);
+        assert_eq!(handler.action_held_for("dash"), Some(ms(600)));
+        assert!(handler.action_long_pressed("dash"));
+        assert!(!handler.action_just_pressed("dash"));
+        assert!(!handler.action_buffered("dash"));
+
+        let timing_events = events
+            .read(&mut reader)
+            .filter_map(|event| match event {
+                InputEvent::ActionDoubleTapped(_) | InputEvent::ActionLongPressed(_) => {
+                    Some(event.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            timing_events,
+            vec![
+                InputEvent::ActionDoubleTapped(String::from("dash")),
+                InputEvent::ActionLongPressed(String::from("dash")),
+            ]
+        );
+
+        // Two taps within single frames still count as a double-tap.
+        handler.send_event(&key_release(32, VirtualKeyCode::D), &mut events, HIDPI);
+        handler.send_actions_update(ms(900), &mut events);
+        for &time in &[1000, 1100] {
+            handler.set_time(ms(time));
+            handler.send_event(&key_press(32, VirtualKeyCode::D), &mut events, HIDPI);
+            handler.send_event(&key_release(32, VirtualKeyCode::D), &mut events, HIDPI);
+            handler.send_actions_update(ms(time + 10), &mut events);
+            assert!(handler.action_just_pressed("dash"));
+            assert!(handler.action_just_released("dash"));
+        }
+        assert!(handler.action_double_tapped("dash"));
+    }
+
+    #[test]
+    fn player_actions() {
+        // Two players share controller agnostic bindings, the first also uses the keyboard.
+        // Connected controllers are assigned to the players in order, and unassigned when
+        // disconnected.
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        let mut bindings = Bindings::new();
+        bindings
+            .insert_action_binding(
+                String::from("jump"),
+                [Button::Controller(0, ControllerButton::A)].iter().cloned(),
+            )
+            .unwrap();
+        bindings
+            .insert_action_binding(
+                String::from("jump"),
+                [Button::Key(VirtualKeyCode::Space)].iter().cloned(),
+            )
+            .unwrap();
+        handler.players = PlayerSlots::new(bindings);
+        handler.players.set_player_count(2);
+        handler.players.assign(0, InputDevice::Keyboard);
+
+        for which in &[7, 9] {
+            handler.send_controller_event(
+                &ControllerEvent::ControllerConnected { which: *which },
+                &mut events,
+            );
+        }
+        handler.send_controller_event(
+            &ControllerEvent::ControllerButtonPressed {
+                which: 9,
+                button: ControllerButton::A,
+            },
+            &mut events,
+        );
+        assert_eq!(handler.action_is_down_for(0, "jump"), Some(false));
+        assert_eq!(handler.action_is_down_for(1, "jump"), Some(true));
+        assert_eq!(handler.action_is_down_for(2, "jump"), None);
+
+        handler.send_event(&key_press(57, VirtualKeyCode::Space), &mut events, HIDPI);
+        assert_eq!(handler.action_is_down_for(0, "jump"), Some(true));
+
+        handler.send_controller_event(
+            &ControllerEvent::ControllerDisconnected { which: 9 },
+            &mut events,
+        );
+        assert_eq!(handler.players.devices(1), &[]);
+
+        let player_events = events
+            .read(&mut reader)
+            .filter_map(|event| match event {
+                InputEvent::PlayerDeviceAssigned { .. }
+                | InputEvent::PlayerDeviceUnassigned { .. } => Some(event.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            player_events,
+            vec![
+                InputEvent::PlayerDeviceAssigned {
+                    player: 0,
+                    device: InputDevice::Controller(0),
+                },
+                InputEvent::PlayerDeviceAssigned {
+                    player: 1,
+                    device: InputDevice::Controller(1),
+                },
+                InputEvent::PlayerDeviceUnassigned {
+                    player: 1,
+                    device: InputDevice::Controller(1),
+                },
+            ]
+        );
+
+        // Controllers connected before a player is added are assigned to it.
+        handler.players.set_player_count(1);
+        handler.send_controller_event(
+            &ControllerEvent::ControllerConnected { which: 11 },
+            &mut events,
+        );
+        assert_eq!(handler.players.player_of(InputDevice::Controller(1)), None);
+        handler.set_player_count(3, &mut events);
+        assert_eq!(handler.players.devices(1), &[InputDevice::Controller(1)]);
+        assert_eq!(handler.players.devices(2), &[]);
+    }
+
+    #[test]
+    fn axis_processing() {
+        // Read two emulated axes as a pair, the diagonal is normalized.
+        // Then smooth one of them and check it accelerates over time.
+        use approx::assert_ulps_eq;
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        handler
+            .bindings
+            .insert_axis(
+                String::from("x"),
+                Axis::Emulated {
+                    pos: Button::Key(VirtualKeyCode::D),
+                    neg: Button::Key(VirtualKeyCode::A),
+                },
+            )
+            .unwrap();
+        handler
+            .bindings
+            .insert_axis(
+                String::from("y"),
+                Axis::Emulated {
+                    pos: Button::Key(VirtualKeyCode::W),
+                    neg: Button::Key(VirtualKeyCode::S),
+                },
+            )
+            .unwrap();
+
+        handler.send_event(&key_press(32, VirtualKeyCode::D), &mut events, HIDPI);
+        handler.send_event(&key_press(17, VirtualKeyCode::W), &mut events, HIDPI);
+        let (x, y) = handler.axis_pair_value("x", "y").unwrap();
+        assert_ulps_eq!(x, std::f32::consts::FRAC_1_SQRT_2);
+        assert_ulps_eq!(y, std::f32::consts::FRAC_1_SQRT_2);
+        assert_eq!(handler.axis_pair_value("x", "z"), None);
+
+        handler.bindings.set_axis_processing(
+            "x",
+            AxisProcessing {
+                acceleration: 2.0,
+                sensitivity: 0.5,
+                ..Default::default()
+            },
+        );
+        assert_ulps_eq!(handler.axis_value("x").unwrap(), 0.0);
+        handler.send_actions_update(Duration::from_millis(250), &mut events);
+        assert_ulps_eq!(handler.axis_value("x").unwrap(), 0.25);
+        handler.send_actions_update(Duration::from_millis(1000), &mut events);
+        assert_ulps_eq!(handler.axis_value("x").unwrap(), 0.5);
+    }
+
+    #[test]
+    fn touch_emulates_mouse() {
+        // The primary touch moves the cursor and holds the left mouse button, a second finger
+        // doesn't.
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        handler.touch_settings.emulate_mouse = true;
+
+        handler.send_event(
+            &touch(0, TouchPhase::Started, 10.0, 20.0),
+            &mut events,
+            HIDPI,
+        );
+        handler.send_event(
+            &touch(1, TouchPhase::Started, 50.0, 50.0),
+            &mut events,
+            HIDPI,
+        );
+        assert_eq!(handler.touches().count(), 2);
+        assert_eq!(handler.primary_touch().map(|touch| touch.id), Some(0));
+        assert_eq!(handler.mouse_position(), Some((10.0, 20.0)));
+        assert!(handler.mouse_button_is_down(MouseButton::Left));
+
+        handler.send_event(&touch(1, TouchPhase::Moved, 60.0, 50.0), &mut events, HIDPI);
+        handler.send_event(&touch(0, TouchPhase::Moved, 15.0, 20.0), &mut events, HIDPI);
+        assert_eq!(handler.touch(1).unwrap().position, (60.0, 50.0));
+        assert_eq!(handler.mouse_position(), Some((15.0, 20.0)));
+
+        handler.send_event(&touch(1, TouchPhase::Ended, 60.0, 50.0), &mut events, HIDPI);
+        assert!(handler.mouse_button_is_down(MouseButton::Left));
+        handler.send_event(
+            &touch(0, TouchPhase::Cancelled, 15.0, 20.0),
+            &mut events,
+            HIDPI,
+        );
+        assert!(!handler.mouse_button_is_down(MouseButton::Left));
+        assert_eq!(handler.touches().count(), 0);
+    }
+
+    #[test]
+    fn touch_timing() {
+        // A touch is timed from the time set before its events, a long touch isn't a tap.
+
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        let ms = Duration::from_millis;
+
+        for &(start, end) in &[(0, 1000), (1100, 1150)] {
+            handler.set_time(ms(start));
+            handler.send_event(
+                &touch(0, TouchPhase::Started, 10.0, 20.0),
+                &mut events,
+                HIDPI,
+            );
+            assert_eq!(handler.touch(0).unwrap().started_at, ms(start));
+            handler.set_time(ms(end));
+            handler.send_event(&touch(0, TouchPhase::Ended, 10.0, 20.0), &mut events, HIDPI);
+        }
+        let taps = events
+            .read(&mut reader)
+            .filter(|event| match event {
+                InputEvent::TouchTapped { .. } => true,
+                _ => false,
+            })
+            .count();
+        assert_eq!(taps, 1);
+    }
+
     /// Compares two sets for equality, but not the order
     fn sets_are_equal<T>(a: &[T], b: &[T])
     where
@@ -1286,6 +2487,18 @@ right: `{:?}`",
         }
     }
 
+    fn touch(id: u64, phase: TouchPhase, x: f64, y: f64) -> Event {
+        Event::WindowEvent {
+            window_id: unsafe { WindowId::dummy() },
+            event: WindowEvent::Touch(Touch {
+                device_id: unsafe { DeviceId::dummy() },
+                phase,
+                location: LogicalPosition::new(x, y),
+                id,
+            }),
+        }
+    }
+
     fn mouse_wheel(x: f32, y: f32) -> Event {
         Event::DeviceEvent {
             device_id: unsafe { DeviceId::dummy() },
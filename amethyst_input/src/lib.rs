@@ -12,15 +12,30 @@
 #[cfg(feature = "sdl_controller")]
 pub use self::sdl_events_system::SdlEventsSystem;
 pub use self::{
+    action_timing::ActionTiming,
     axis::Axis,
+    axis_processing::{AxisProcessing, ResponseCurve},
     bindings::{BindingError, BindingTypes, Bindings, StringBindings},
     bundle::{BindingsFileError, InputBundle},
     button::Button,
+    combo::{
+        Combo, ComboDirection, ComboInput, ComboRecognizer, ComboSet, ComboStep, ComboSystem,
+        ComboSystemDesc,
+    },
+    context::{InputConsumption, InputContext, InputContexts},
     controller::{ControllerAxis, ControllerButton, ControllerEvent},
     event::InputEvent,
+    haptics::{
+        ControllerHaptics, HapticsBackend, HapticsCommand, HapticsError, HapticsSystem,
+        MockHaptics, NullHaptics,
+    },
     input_handler::InputHandler,
+    player::{InputDevice, PlayerSlot, PlayerSlots},
+    rebind::{BindingOverrides, RebindTarget, UserBindingsFile},
     scroll_direction::ScrollDirection,
+    synthetic::{InputCommand, InputScript},
     system::{InputSystem, InputSystemDesc},
+    touch::{ActiveTouch, TouchSettings},
     util::{
         get_input_axis_simple, get_key, get_mouse_button, is_close_requested, is_key_down,
         is_key_up, is_mouse_button_down,
@@ -32,15 +47,24 @@ use std::iter::Iterator;
 
 use winit;
 
+mod action_timing;
 mod axis;
+mod axis_processing;
 mod bindings;
 mod bundle;
 mod button;
+mod combo;
+mod context;
 mod controller;
 mod event;
+mod haptics;
 mod input_handler;
+mod player;
+mod rebind;
 mod scroll_direction;
+mod synthetic;
 mod system;
+mod touch;
 mod util;
 
 #[cfg(feature = "sdl_controller")]
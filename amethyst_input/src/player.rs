@@ -0,0 +1,233 @@
+//! Player slots for local multiplayer, sharing bindings between devices.
+
+use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+use super::{BindingError, BindingTypes, Bindings};
+
+/// A device which can be assigned to a player slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputDevice {
+    /// The keyboard, see `PlayerSlot::bindings` to split it between players.
+    Keyboard,
+    /// The mouse and its wheel.
+    Mouse,
+    /// The controller with this id, as seen in `Button::Controller`.
+    Controller(u32),
+}
+
+impl InputDevice {
+    /// Returns the id of the controller, if this device is a controller.
+    pub fn controller_id(self) -> Option<u32> {
+        match self {
+            InputDevice::Controller(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+/// The devices and bindings of a player.
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Default(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub struct PlayerSlot<T: BindingTypes> {
+    /// The devices read for this player.
+    #[serde(default)]
+    pub devices: SmallVec<[InputDevice; 2]>,
+    /// Bindings used instead of `PlayerSlots::bindings`, for example to give each player half of
+    /// the keyboard.
+    #[serde(default)]
+    pub bindings: Option<Bindings<T>>,
+}
+
+/// Player slots, each reading the actions and axes of the shared bindings from its own devices.
+///
+/// Controller ids in the shared bindings are ignored: `Button::Controller(_, button)` and
+/// `Axis::Controller` read the controllers assigned to the player, so the bindings are written
+/// once for every player. Keys and mouse buttons are only read for players assigned the keyboard
+/// or the mouse.
+///
+/// Controllers connected while `auto_assign_controllers` is set are assigned to the first player
+/// without a controller, and disconnected controllers are unassigned, see
+/// `InputEvent::PlayerDeviceAssigned`.
+///
+/// Example Ron config file:
+/// ```ron
+/// (
+///     bindings: (
+///         axes: {
+///             "move": Controller(controller_id: 0, axis: LeftX, invert: false, dead_zone: 0.1),
+///         },
+///         actions: {
+///             "jump": [[Controller(0, A)]],
+///         },
+///     ),
+///     slots: [
+///         (devices: [Keyboard, Mouse]),
+///         (),
+///     ],
+/// )
+/// ```
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Default(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub struct PlayerSlots<T: BindingTypes> {
+    /// Bindings shared by the players without their own bindings.
+    pub bindings: Bindings<T>,
+    #[serde(default)]
+    slots: Vec<PlayerSlot<T>>,
+    /// Whether connected controllers are assigned to players automatically.
+    #[serde(default = "default_auto_assign_controllers")]
+    #[derivative(Default(value = "true"))]
+    pub auto_assign_controllers: bool,
+}
+
+fn default_auto_assign_controllers() -> bool {
+    true
+}
+
+impl<T: BindingTypes> PlayerSlots<T> {
+    /// Creates player slots sharing `bindings`, without any player.
+    pub fn new(bindings: Bindings<T>) -> Self {
+        PlayerSlots {
+            bindings,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the number of players, removing the last ones or adding players without devices.
+    ///
+    /// Use `InputHandler::set_player_count` to assign the connected controllers to the new
+    /// players.
+    pub fn set_player_count(&mut self, count: usize) {
+        self.slots.resize_with(count, Default::default);
+    }
+
+    /// Returns the number of players.
+    pub fn player_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the slot of a player.
+    pub fn get(&self, player: usize) -> Option<&PlayerSlot<T>> {
+        self.slots.get(player)
+    }
+
+    /// Returns the bindings used by a player, or `None` if the player doesn't exist.
+    pub fn player_bindings(&self, player: usize) -> Option<&Bindings<T>> {
+        self.slots
+            .get(player)
+            .map(|slot| slot.bindings.as_ref().unwrap_or(&self.bindings))
+    }
+
+    /// Sets the bindings used by a player instead of the shared bindings.
+    ///
+    /// Returns false if the player doesn't exist.
+    pub fn set_player_bindings(&mut self, player: usize, bindings: Option<Bindings<T>>) -> bool {
+        match self.slots.get_mut(player) {
+            Some(slot) => {
+                slot.bindings = bindings;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the devices assigned to a player.
+    pub fn devices(&self, player: usize) -> &[InputDevice] {
+        self.slots
+            .get(player)
+            .map(|slot| &slot.devices[..])
+            .unwrap_or(&[])
+    }
+
+    /// Returns the player a device is assigned to.
+    pub fn player_of(&self, device: InputDevice) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.devices.contains(&device))
+    }
+
+    /// Assigns a device to a player, unassigning it from any other player.
+    ///
+    /// Returns false if the player doesn't exist.
+    pub fn assign(&mut self, player: usize, device: InputDevice) -> bool {
+        if player >= self.slots.len() {
+            return false;
+        }
+        self.unassign(device);
+        self.slots[player].devices.push(device);
+        true
+    }
+
+    /// Unassigns a device, returning the player it was assigned to.
+    pub fn unassign(&mut self, device: InputDevice) -> Option<usize> {
+        let player = self.player_of(device)?;
+        self.slots[player].devices.retain(|d| *d != device);
+        Some(player)
+    }
+
+    /// Checks the invariants of the shared bindings and the bindings of every player, see
+    /// `Bindings::check_invariants`.
+    pub fn check_invariants(&mut self) -> Result<(), BindingError<T>> {
+        self.bindings.check_invariants()?;
+        for slot in self.slots.iter_mut() {
+            if let Some(ref mut bindings) = slot.bindings {
+                bindings.check_invariants()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Assigns a connected controller to the first player without a controller.
+    pub(crate) fn auto_assign(&mut self, controller_id: u32) -> Option<usize> {
+        if !self.auto_assign_controllers
+            || self
+                .player_of(InputDevice::Controller(controller_id))
+                .is_some()
+        {
+            return None;
+        }
+        let player = self.slots.iter().position(|slot| {
+            slot.devices
+                .iter()
+                .all(|device| device.controller_id().is_none())
+        })?;
+        self.slots[player]
+            .devices
+            .push(InputDevice::Controller(controller_id));
+        Some(player)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StringBindings;
+
+    #[test]
+    fn assign_devices() {
+        let mut players = PlayerSlots::<StringBindings>::new(Bindings::new());
+        players.set_player_count(2);
+        assert!(players.assign(0, InputDevice::Keyboard));
+        assert!(!players.assign(2, InputDevice::Mouse));
+
+        assert_eq!(players.auto_assign(3), Some(0));
+        assert_eq!(players.auto_assign(3), None);
+        assert_eq!(players.auto_assign(5), Some(1));
+        assert_eq!(players.auto_assign(6), None);
+
+        assert!(players.assign(1, InputDevice::Controller(3)));
+        assert_eq!(players.devices(0), &[InputDevice::Keyboard]);
+        assert_eq!(players.player_of(InputDevice::Controller(3)), Some(1));
+        assert_eq!(players.unassign(InputDevice::Controller(5)), Some(1));
+        assert_eq!(players.devices(1), &[InputDevice::Controller(3)]);
+    }
+}
@@ -0,0 +1,274 @@
+//! Runtime rebinding of actions and axes, and persistence of the user's bindings.
+
+use std::path::{Path, PathBuf};
+
+use derivative::Derivative;
+use fnv::FnvHashMap as HashMap;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+use amethyst_config::{Config, ConfigError};
+
+use super::{Axis, BindingError, BindingTypes, Bindings, Button};
+
+/// The binding captured by a rebind, see `InputHandler::start_rebind`.
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub enum RebindTarget<T: BindingTypes> {
+    /// Replaces all bindings of the action with the next pressed button.
+    Action(T::Action),
+    /// Binds the next controller axis motion or mouse wheel motion to the axis.
+    ///
+    /// A controller axis moved in its negative direction is bound inverted.
+    Axis(T::Axis),
+    /// Replaces one button of an emulated axis with the next pressed button.
+    EmulatedAxis {
+        /// The emulated axis to rebind.
+        axis: T::Axis,
+        /// Whether to replace the `pos` or the `neg` button.
+        positive: bool,
+    },
+}
+
+impl<T: BindingTypes> PartialEq for RebindTarget<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RebindTarget::Action(a), RebindTarget::Action(x)) => a == x,
+            (RebindTarget::Axis(a), RebindTarget::Axis(x)) => a == x,
+            (
+                RebindTarget::EmulatedAxis {
+                    axis: a,
+                    positive: b,
+                },
+                RebindTarget::EmulatedAxis {
+                    axis: x,
+                    positive: y,
+                },
+            ) => a == x && b == y,
+            (_, _) => false,
+        }
+    }
+}
+
+/// The bindings a user changed from the default bindings.
+///
+/// Overrides are saved separately from the default bindings, so updates to the defaults still
+/// apply to the bindings the user didn't change.
+///
+/// Example Ron config file:
+/// ```ron
+/// (
+///     axes: {
+///         "leftright": Some(Emulated(pos: Key(D), neg: Key(A))),
+///     },
+///     actions: {
+///         "fire": [[Key(F)]],
+///         "reload": [], // Unbound by the user
+///     },
+/// )
+/// ```
+#[derive(Derivative, Serialize, Deserialize)]
+#[derivative(Debug(bound = ""), Default(bound = ""), Clone(bound = ""))]
+#[serde(bound(
+    serialize = "T::Axis: Serialize, T::Action: Serialize",
+    deserialize = "T::Axis: Deserialize<'de>, T::Action: Deserialize<'de>",
+))]
+pub struct BindingOverrides<T: BindingTypes> {
+    /// Axes replaced by the user, `None` if the user removed the axis.
+    axes: HashMap<T::Axis, Option<Axis>>,
+    /// Bindings replacing all bindings of an action, empty if the user unbound the action.
+    actions: HashMap<T::Action, SmallVec<[SmallVec<[Button; 2]>; 4]>>,
+}
+
+impl<T: BindingTypes> BindingOverrides<T> {
+    /// Creates empty overrides.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Computes the overrides turning `defaults` into `bindings`.
+    pub fn diff(defaults: &Bindings<T>, bindings: &Bindings<T>) -> Self {
+        let mut overrides = BindingOverrides::new();
+        for (id, axis) in bindings.axes.iter() {
+            if defaults.axes.get(id) != Some(axis) {
+                overrides.axes.insert(id.clone(), Some(axis.clone()));
+            }
+        }
+        for id in defaults.axes.keys() {
+            if !bindings.axes.contains_key(id) {
+                overrides.axes.insert(id.clone(), None);
+            }
+        }
+        for (id, combinations) in bindings.actions.iter() {
+            if defaults.actions.get(id) != Some(combinations) {
+                overrides.actions.insert(id.clone(), combinations.clone());
+            }
+        }
+        for id in defaults.actions.keys() {
+            if !bindings.actions.contains_key(id) {
+                overrides.actions.insert(id.clone(), SmallVec::new());
+            }
+        }
+        overrides
+    }
+
+    /// Returns the `defaults` with these overrides applied.
+    pub fn apply(&self, defaults: &Bindings<T>) -> Result<Bindings<T>, BindingError<T>> {
+        let mut bindings = defaults.clone();
+        for (id, axis) in self.axes.iter() {
+            match axis {
+                Some(axis) => bindings.axes.insert(id.clone(), axis.clone()),
+                None => bindings.axes.remove(id),
+            };
+        }
+        for (id, combinations) in self.actions.iter() {
+            if combinations.is_empty() {
+                bindings.actions.remove(id);
+            } else {
+                bindings.actions.insert(id.clone(), combinations.clone());
+            }
+        }
+        bindings.check_invariants()?;
+        Ok(bindings)
+    }
+
+    /// Returns true if the user didn't change any binding.
+    pub fn is_empty(&self) -> bool {
+        self.axes.is_empty() && self.actions.is_empty()
+    }
+}
+
+/// A file storing the `BindingOverrides` of the user.
+///
+/// The `InputSystem` applies the overrides over its default bindings when it's built, and saves
+/// the overrides every time a rebind is applied.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct UserBindingsFile<T: BindingTypes> {
+    path: PathBuf,
+    overrides: BindingOverrides<T>,
+    #[derivative(Debug = "ignore")]
+    write: fn(&BindingOverrides<T>, &Path) -> Result<(), ConfigError>,
+}
+
+impl<T: BindingTypes> UserBindingsFile<T> {
+    /// Loads the overrides from a file, starting with no overrides if the file doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError>
+    where
+        BindingOverrides<T>: Config,
+    {
+        let path = path.as_ref().to_path_buf();
+        let overrides = if path.exists() {
+            BindingOverrides::load_no_fallback(&path)?
+        } else {
+            BindingOverrides::new()
+        };
+        Ok(UserBindingsFile {
+            path,
+            overrides,
+            write: |overrides, path| overrides.write(path),
+        })
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the overrides, as last loaded or saved.
+    pub fn overrides(&self) -> &BindingOverrides<T> {
+        &self.overrides
+    }
+
+    /// Saves the overrides turning `defaults` into `bindings` to the file.
+    pub fn save(
+        &mut self,
+        defaults: &Bindings<T>,
+        bindings: &Bindings<T>,
+    ) -> Result<(), ConfigError> {
+        self.overrides = BindingOverrides::diff(defaults, bindings);
+        (self.write)(&self.overrides, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StringBindings;
+    use winit::VirtualKeyCode;
+
+    fn defaults() -> Bindings<StringBindings> {
+        let mut bindings = Bindings::new();
+        bindings
+            .insert_action_binding(
+                String::from("fire"),
+                [Button::Key(VirtualKeyCode::X)].iter().cloned(),
+            )
+            .unwrap();
+        bindings
+            .insert_action_binding(
+                String::from("reload"),
+                [Button::Key(VirtualKeyCode::R)].iter().cloned(),
+            )
+            .unwrap();
+        bindings
+            .insert_axis(
+                String::from("leftright"),
+                Axis::Emulated {
+                    pos: Button::Key(VirtualKeyCode::Right),
+                    neg: Button::Key(VirtualKeyCode::Left),
+                },
+            )
+            .unwrap();
+        bindings
+    }
+
+    #[test]
+    fn overrides_round_trip() {
+        let defaults = defaults();
+        let mut bindings = defaults.clone();
+        bindings.remove_axis("leftright");
+        bindings
+            .remove_action_binding("fire", &[Button::Key(VirtualKeyCode::X)])
+            .unwrap();
+        bindings
+            .insert_action_binding(
+                String::from("fire"),
+                [Button::Key(VirtualKeyCode::F)].iter().cloned(),
+            )
+            .unwrap();
+
+        let overrides = BindingOverrides::diff(&defaults, &bindings);
+        assert_eq!(overrides.actions.len(), 1);
+        assert_eq!(overrides.axes.get("leftright"), Some(&None));
+
+        let applied = overrides.apply(&defaults).unwrap();
+        assert_eq!(
+            applied.action_bindings("fire").collect::<Vec<_>>(),
+            vec![[Button::Key(VirtualKeyCode::F)]]
+        );
+        assert_eq!(
+            applied.action_bindings("reload").collect::<Vec<_>>(),
+            vec![[Button::Key(VirtualKeyCode::R)]]
+        );
+        assert_eq!(applied.axis("leftright"), None);
+        assert!(BindingOverrides::diff(&bindings, &applied).is_empty());
+    }
+
+    #[test]
+    fn conflicting_overrides() {
+        let mut overrides = BindingOverrides::<StringBindings>::new();
+        overrides.actions.insert(
+            String::from("fire"),
+            [[Button::Key(VirtualKeyCode::R)].iter().cloned().collect()]
+                .iter()
+                .cloned()
+                .collect(),
+        );
+        assert!(overrides.apply(&defaults()).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt, marker::PhantomData, path::PathBuf};
+use std::{fmt, marker::PhantomData, path::PathBuf, time::Duration};
 
 use derivative::Derivative;
 use derive_new::new;
@@ -17,7 +17,8 @@ use amethyst_core::{
 
 use super::{
     controller::{ControllerAxis, ControllerButton, ControllerEvent},
-    BindingTypes, InputEvent, InputHandler,
+    BindingTypes, ControllerHaptics, HapticsBackend, HapticsCommand, HapticsError, InputEvent,
+    InputHandler,
 };
 
 /// A collection of errors that can occur in the SDL system.
@@ -77,7 +78,8 @@ where
     }
 }
 
-/// A system that pumps SDL events into the `amethyst_input` APIs.
+/// A system that pumps SDL events into the `amethyst_input` APIs, and sends the commands of the
+/// `ControllerHaptics` resource to the controllers.
 #[allow(missing_debug_implementations)]
 pub struct SdlEventsSystem<T: BindingTypes> {
     #[allow(dead_code)]
@@ -92,12 +94,13 @@ pub struct SdlEventsSystem<T: BindingTypes> {
 type SdlEventsData<'a, T> = (
     Write<'a, InputHandler<T>>,
     Write<'a, EventChannel<InputEvent<T>>>,
+    Write<'a, ControllerHaptics>,
 );
 
 impl<'a, T: BindingTypes> System<'a> for SdlEventsSystem<T> {
     type SystemData = SdlEventsData<'a, T>;
 
-    fn run(&mut self, (mut handler, mut output): Self::SystemData) {
+    fn run(&mut self, (mut handler, mut output, mut haptics): Self::SystemData) {
         let mut event_pump = self
             .event_pump
             .take()
@@ -107,6 +110,73 @@ impl<'a, T: BindingTypes> System<'a> for SdlEventsSystem<T> {
             self.handle_sdl_event(&event, &mut handler, &mut output);
         }
         self.event_pump = Some(event_pump);
+
+        haptics.send_all(&mut SdlHaptics {
+            controllers: &mut self.opened_controllers,
+            handler: &handler,
+        });
+    }
+}
+
+/// Sends haptics commands to the opened SDL controllers.
+struct SdlHaptics<'a, T: BindingTypes> {
+    controllers: &'a mut [(u32, GameController)],
+    handler: &'a InputHandler<T>,
+}
+
+impl<'a, T: BindingTypes> HapticsBackend for SdlHaptics<'a, T> {
+    fn execute(&mut self, command: &HapticsCommand) -> Result<(), HapticsError> {
+        let controller_id = command.controller_id();
+        let controller = match self.handler.controller_id_to_idx(controller_id) {
+            Some(idx) => self
+                .controllers
+                .iter_mut()
+                .map(|(_, c)| c)
+                .find(|c| c.instance_id() == idx),
+            None => None,
+        }
+        .ok_or(HapticsError::UnknownController(controller_id))?;
+
+        let strength = |value: f32| (value.max(0.0).min(1.0) * 65535.0) as u16;
+        let millis =
+            |duration: Duration| duration.as_secs() as u32 * 1000 + duration.subsec_millis();
+        let result = match *command {
+            HapticsCommand::Rumble {
+                low_frequency,
+                high_frequency,
+                duration,
+                ..
+            } => {
+                if !controller.has_rumble() {
+                    return Err(HapticsError::Unsupported);
+                }
+                controller.set_rumble(
+                    strength(low_frequency),
+                    strength(high_frequency),
+                    millis(duration),
+                )
+            }
+            HapticsCommand::TriggerRumble {
+                left,
+                right,
+                duration,
+                ..
+            } => {
+                if !controller.has_rumble_triggers() {
+                    return Err(HapticsError::Unsupported);
+                }
+                controller.set_rumble_triggers(strength(left), strength(right), millis(duration))
+            }
+            HapticsCommand::SetLed {
+                red, green, blue, ..
+            } => {
+                if !controller.has_led() {
+                    return Err(HapticsError::Unsupported);
+                }
+                controller.set_led(red, green, blue)
+            }
+        };
+        result.map_err(|e| HapticsError::Backend(e.to_string()))
     }
 }
 
@@ -147,7 +217,7 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
             opened_controllers: vec![],
             marker: PhantomData,
         };
-        let (mut handler, mut output) = SdlEventsData::fetch(world);
+        let (mut handler, mut output, _) = SdlEventsData::fetch(world);
         sys.initialize_controllers(&mut handler, &mut output);
         Ok(sys)
     }
@@ -166,7 +236,7 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
             } => {
                 handler.send_controller_event(
                     &ControllerAxisMoved {
-                        which: which as u32,
+                        which,
                         axis: axis.into(),
                         value: if value > 0 {
                             f32::from(value) / 32767.0
@@ -180,7 +250,7 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
             Event::ControllerButtonDown { which, button, .. } => {
                 handler.send_controller_event(
                     &ControllerButtonPressed {
-                        which: which as u32,
+                        which,
                         button: button.into(),
                     },
                     output,
@@ -189,20 +259,15 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
             Event::ControllerButtonUp { which, button, .. } => {
                 handler.send_controller_event(
                     &ControllerButtonReleased {
-                        which: which as u32,
+                        which,
                         button: button.into(),
                     },
                     output,
                 );
             }
             Event::ControllerDeviceRemoved { which, .. } => {
-                self.close_controller(which as u32);
-                handler.send_controller_event(
-                    &ControllerDisconnected {
-                        which: which as u32,
-                    },
-                    output,
-                );
+                self.close_controller(which);
+                handler.send_controller_event(&ControllerDisconnected { which }, output);
             }
             Event::ControllerDeviceAdded { which, .. } => {
                 if let Some(idx) = self.open_controller(which) {
@@ -216,7 +281,7 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
     fn open_controller(&mut self, which: u32) -> Option<u32> {
         if self.controller_subsystem.is_game_controller(which) {
             self.controller_subsystem.open(which).ok().map(|c| {
-                let id = c.instance_id() as u32;
+                let id = c.instance_id();
                 self.opened_controllers.push((which, c));
                 id
             })
@@ -229,7 +294,7 @@ impl<T: BindingTypes> SdlEventsSystem<T> {
         let index = self
             .opened_controllers
             .iter()
-            .position(|(_, c)| c.instance_id() as u32 == which);
+            .position(|(_, c)| c.instance_id() == which);
         if let Some(i) = index {
             self.opened_controllers.swap_remove(i);
         }
@@ -270,6 +335,12 @@ impl From<Button> for ControllerButton {
             Button::Back => ControllerButton::Back,
             Button::Start => ControllerButton::Start,
             Button::Guide => ControllerButton::Guide,
+            Button::Misc1 => ControllerButton::Misc1,
+            Button::Paddle1 => ControllerButton::Paddle1,
+            Button::Paddle2 => ControllerButton::Paddle2,
+            Button::Paddle3 => ControllerButton::Paddle3,
+            Button::Paddle4 => ControllerButton::Paddle4,
+            Button::Touchpad => ControllerButton::Touchpad,
         }
     }
 }
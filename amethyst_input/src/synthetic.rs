@@ -0,0 +1,192 @@
+//! Scripted synthetic input, for tests and bots.
+
+use std::collections::VecDeque;
+
+use amethyst_core::shrev::EventChannel;
+use derivative::Derivative;
+
+use super::{BindingTypes, InputEvent, InputHandler};
+
+/// A step of an `InputScript`.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Clone(bound = ""))]
+pub enum InputCommand<T: BindingTypes> {
+    /// Holds the action down, see `InputHandler::press_action`.
+    PressAction(T::Action),
+    /// Releases the action, see `InputHandler::release_action`.
+    ReleaseAction(T::Action),
+    /// Overrides the value of the axis, see `InputHandler::set_axis_value`.
+    SetAxis(T::Axis, f32),
+    /// Stops overriding the value of the axis, see `InputHandler::reset_axis_value`.
+    ResetAxis(T::Axis),
+    /// Runs the next commands this many frames later.
+    Wait(u64),
+}
+
+/// A sequence of synthetic inputs, played one frame at a time by the `InputSystem`.
+///
+/// Insert a script as a resource to drive the `InputHandler` without any input device, for
+/// example in tests or for bots. The script runs after the input events of the frame, so its
+/// actions and axes are visible to every system which runs after the `InputSystem`, and are
+/// deterministic in frames.
+///
+/// ```rust,edition2018,no_run,noplaypen
+/// # use amethyst_input::{InputScript, StringBindings};
+/// let script = InputScript::<StringBindings>::new()
+///     .hold_action(String::from("jump"), 3)
+///     .set_axis(String::from("horizontal"), 0.5)
+///     .wait(10)
+///     .reset_axis(String::from("horizontal"));
+/// ```
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""), Clone(bound = ""))]
+pub struct InputScript<T: BindingTypes> {
+    commands: VecDeque<InputCommand<T>>,
+    /// Frames to skip before running the next command.
+    wait: u64,
+}
+
+impl<T: BindingTypes> InputScript<T> {
+    /// Creates an empty script.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends a command to the script.
+    pub fn push(&mut self, command: InputCommand<T>) {
+        self.commands.push_back(command);
+    }
+
+    /// Appends a command to the script.
+    pub fn with(mut self, command: InputCommand<T>) -> Self {
+        self.push(command);
+        self
+    }
+
+    /// Holds the action down.
+    pub fn press_action(self, action: T::Action) -> Self {
+        self.with(InputCommand::PressAction(action))
+    }
+
+    /// Releases the action.
+    pub fn release_action(self, action: T::Action) -> Self {
+        self.with(InputCommand::ReleaseAction(action))
+    }
+
+    /// Holds the action down for a number of frames, then releases it.
+    pub fn hold_action(self, action: T::Action, frames: u64) -> Self {
+        self.press_action(action.clone())
+            .wait(frames)
+            .release_action(action)
+    }
+
+    /// Overrides the value of the axis.
+    pub fn set_axis(self, axis: T::Axis, value: f32) -> Self {
+        self.with(InputCommand::SetAxis(axis, value))
+    }
+
+    /// Stops overriding the value of the axis.
+    pub fn reset_axis(self, axis: T::Axis) -> Self {
+        self.with(InputCommand::ResetAxis(axis))
+    }
+
+    /// Runs the next commands a number of frames later.
+    pub fn wait(self, frames: u64) -> Self {
+        self.with(InputCommand::Wait(frames))
+    }
+
+    /// Returns true if every command ran.
+    pub fn is_finished(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Runs the commands of the current frame.
+    pub(crate) fn step(
+        &mut self,
+        handler: &mut InputHandler<T>,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) {
+        if self.wait > 0 {
+            self.wait -= 1;
+            return;
+        }
+        while let Some(command) = self.commands.pop_front() {
+            match command {
+                InputCommand::PressAction(action) => handler.press_action(action, event_handler),
+                InputCommand::ReleaseAction(action) => {
+                    handler.release_action(&action, event_handler)
+                }
+                InputCommand::SetAxis(axis, value) => {
+                    handler.set_axis_value(axis, value, event_handler)
+                }
+                InputCommand::ResetAxis(axis) => handler.reset_axis_value(&axis, event_handler),
+                InputCommand::Wait(0) => {}
+                InputCommand::Wait(frames) => {
+                    // The current frame is the first one waited.
+                    self.wait = frames - 1;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StringBindings;
+
+    #[test]
+    fn hold_action() {
+        let mut handler = InputHandler::<StringBindings>::new();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        let mut script = InputScript::new()
+            .hold_action(String::from("jump"), 3)
+            .set_axis(String::from("horizontal"), 0.5)
+            .wait(2)
+            .reset_axis(String::from("horizontal"));
+
+        let mut frames = Vec::new();
+        for _ in 0..6 {
+            script.step(&mut handler, &mut events);
+            frames.push((
+                handler.action_is_down("jump"),
+                handler.axis_value("horizontal"),
+            ));
+        }
+        assert!(script.is_finished());
+        assert_eq!(
+            frames,
+            vec![
+                (Some(true), None),
+                (Some(true), None),
+                (Some(true), None),
+                (None, Some(0.5)),
+                (None, Some(0.5)),
+                (None, None),
+            ]
+        );
+        let axis_events = events
+            .read(&mut reader)
+            .filter(|event| match event {
+                InputEvent::AxisMoved { .. } => true,
+                _ => false,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            axis_events,
+            vec![
+                InputEvent::AxisMoved {
+                    axis: String::from("horizontal"),
+                    value: 0.5,
+                },
+                InputEvent::AxisMoved {
+                    axis: String::from("horizontal"),
+                    value: 0.0,
+                },
+            ]
+        );
+    }
+}
@@ -1,15 +1,19 @@
 //! Input system
 use derive_new::new;
+use log::error;
 use winit::Event;
 
-use crate::{BindingTypes, Bindings, InputEvent, InputHandler};
+use crate::{
+    ActionTiming, BindingTypes, Bindings, InputContexts, InputEvent, InputHandler, InputScript,
+    PlayerSlots, TouchSettings, UserBindingsFile,
+};
 use amethyst_core::{
     ecs::{
         prelude::{Read, ReadExpect, System, World, Write},
         SystemData,
     },
     shrev::{EventChannel, ReaderId},
-    SystemDesc,
+    SystemDesc, Time,
 };
 use amethyst_window::ScreenDimensions;
 
@@ -23,6 +27,51 @@ where
     T: BindingTypes,
 {
     bindings: Option<Bindings<T>>,
+    #[new(default)]
+    contexts: Option<InputContexts<T>>,
+    #[new(default)]
+    user_bindings: Option<UserBindingsFile<T>>,
+    #[new(default)]
+    action_timing: Option<ActionTiming>,
+    #[new(default)]
+    players: Option<PlayerSlots<T>>,
+    #[new(default)]
+    touch_settings: Option<TouchSettings>,
+}
+
+impl<T> InputSystemDesc<T>
+where
+    T: BindingTypes,
+{
+    /// Use the provided input contexts with the `InputHandler`.
+    pub fn with_contexts(mut self, contexts: InputContexts<T>) -> Self {
+        self.contexts = Some(contexts);
+        self
+    }
+
+    /// Layer the user's bindings over the default bindings, and save them after every rebind.
+    pub fn with_user_bindings(mut self, user_bindings: UserBindingsFile<T>) -> Self {
+        self.user_bindings = Some(user_bindings);
+        self
+    }
+
+    /// Use the provided action timing thresholds with the `InputHandler`.
+    pub fn with_action_timing(mut self, action_timing: ActionTiming) -> Self {
+        self.action_timing = Some(action_timing);
+        self
+    }
+
+    /// Use the provided player slots with the `InputHandler`.
+    pub fn with_players(mut self, players: PlayerSlots<T>) -> Self {
+        self.players = Some(players);
+        self
+    }
+
+    /// Use the provided touch gesture thresholds with the `InputHandler`.
+    pub fn with_touch_settings(mut self, touch_settings: TouchSettings) -> Self {
+        self.touch_settings = Some(touch_settings);
+        self
+    }
 }
 
 impl<'a, 'b, T> SystemDesc<'a, 'b, InputSystem<T>> for InputSystemDesc<T>
@@ -36,28 +85,63 @@ where
         if let Some(bindings) = self.bindings.as_ref() {
             world.fetch_mut::<InputHandler<T>>().bindings = bindings.clone();
         }
+        if let Some(contexts) = self.contexts {
+            world.fetch_mut::<InputHandler<T>>().contexts = contexts;
+        }
+        if let Some(action_timing) = self.action_timing {
+            world.fetch_mut::<InputHandler<T>>().action_timing = action_timing;
+        }
+        if let Some(players) = self.players {
+            world.fetch_mut::<InputHandler<T>>().players = players;
+        }
+        if let Some(touch_settings) = self.touch_settings {
+            world.fetch_mut::<InputHandler<T>>().touch_settings = touch_settings;
+        }
 
-        InputSystem::new(reader, self.bindings)
+        let mut bindings = self.bindings;
+        if let Some(user_bindings) = self.user_bindings.as_ref() {
+            let mut handler = world.fetch_mut::<InputHandler<T>>();
+            let defaults = bindings.get_or_insert_with(|| handler.bindings.clone());
+            match user_bindings.overrides().apply(defaults) {
+                Ok(user_bindings) => handler.bindings = user_bindings,
+                Err(e) => error!(
+                    "Failed to apply user bindings '{}': {:?}",
+                    user_bindings.path().display(),
+                    e
+                ),
+            }
+        }
+
+        let mut system = InputSystem::new(reader, bindings);
+        system.user_bindings = self.user_bindings;
+        system
     }
 }
 
 /// Input system
 ///
 /// Will read `winit::Event` from `EventHandler<winit::Event>`, process them with `InputHandler`,
-/// and push the results in `EventHandler<InputEvent>`.
+/// and push the results in `EventHandler<InputEvent>`. Then runs the commands of the current frame
+/// of the `InputScript` resource, if any.
 #[derive(Debug)]
 pub struct InputSystem<T>
 where
     T: BindingTypes,
 {
     reader: ReaderId<Event>,
+    /// The default bindings.
     bindings: Option<Bindings<T>>,
+    user_bindings: Option<UserBindingsFile<T>>,
 }
 
 impl<T: BindingTypes> InputSystem<T> {
     /// Create a new input system. Needs a reader id for `EventHandler<winit::Event>`.
     pub fn new(reader: ReaderId<Event>, bindings: Option<Bindings<T>>) -> Self {
-        InputSystem { reader, bindings }
+        InputSystem {
+            reader,
+            bindings,
+            user_bindings: None,
+        }
     }
 
     fn process_event(
@@ -76,13 +160,19 @@ impl<'a, T: BindingTypes> System<'a> for InputSystem<T> {
         Write<'a, InputHandler<T>>,
         Write<'a, EventChannel<InputEvent<T>>>,
         ReadExpect<'a, ScreenDimensions>,
+        Read<'a, Time>,
+        Option<Write<'a, InputScript<T>>>,
     );
 
-    fn run(&mut self, (input, mut handler, mut output, screen_dimensions): Self::SystemData) {
+    fn run(
+        &mut self,
+        (input, mut handler, mut output, screen_dimensions, time, script): Self::SystemData,
+    ) {
         #[cfg(feature = "profiler")]
         profile_scope!("input_system");
 
         handler.send_frame_begin();
+        handler.set_time(time.absolute_real_time());
         for event in input.read(&mut self.reader) {
             Self::process_event(
                 event,
@@ -91,5 +181,23 @@ impl<'a, T: BindingTypes> System<'a> for InputSystem<T> {
                 screen_dimensions.hidpi_factor() as f32,
             );
         }
+        if let Some(mut script) = script {
+            script.step(&mut *handler, &mut *output);
+        }
+        handler.send_actions_update(time.absolute_real_time(), &mut *output);
+
+        if handler.take_rebound() {
+            if let (Some(defaults), Some(user_bindings)) =
+                (self.bindings.as_ref(), self.user_bindings.as_mut())
+            {
+                if let Err(e) = user_bindings.save(defaults, &handler.bindings) {
+                    error!(
+                        "Failed to save user bindings '{}': {}",
+                        user_bindings.path().display(),
+                        e
+                    );
+                }
+            }
+        }
     }
 }
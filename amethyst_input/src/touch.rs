@@ -0,0 +1,283 @@
+//! Touch tracking and gesture recognition.
+
+use std::time::Duration;
+
+use amethyst_core::shrev::EventChannel;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+use super::{action_timing::elapsed, BindingTypes, InputEvent};
+
+/// Thresholds used by the `InputHandler` to recognize touch gestures.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TouchSettings {
+    /// Maximum duration of a touch for it to count as a tap.
+    pub tap_max_duration: Duration,
+    /// Maximum distance in pixels a touch can move and still count as a tap.
+    pub tap_max_distance: f32,
+    /// Minimal distance in pixels a touch must move to count as a swipe.
+    pub swipe_min_distance: f32,
+    /// Maximum duration of a touch for it to count as a swipe.
+    pub swipe_max_duration: Duration,
+    /// Moves the mouse cursor with the primary touch, and holds the left mouse button down while
+    /// it touches, so mouse driven code like the UI works with touch screens.
+    pub emulate_mouse: bool,
+}
+
+impl Default for TouchSettings {
+    fn default() -> Self {
+        TouchSettings {
+            tap_max_duration: Duration::from_millis(300),
+            tap_max_distance: 10.0,
+            swipe_min_distance: 50.0,
+            swipe_max_duration: Duration::from_millis(500),
+            emulate_mouse: false,
+        }
+    }
+}
+
+/// A finger touching the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveTouch {
+    /// Identifier of the finger, unique while it touches.
+    pub id: u64,
+    /// Position where the touch started, in pixels.
+    pub start_position: (f32, f32),
+    /// Current position of the touch, in pixels.
+    pub position: (f32, f32),
+    /// Time the touch started, see `InputHandler::set_time`.
+    pub started_at: Duration,
+}
+
+/// The fingers touching the screen, and the state of the gestures they perform.
+#[derive(Debug, Default)]
+pub(crate) struct Touches {
+    pub(crate) active: SmallVec<[ActiveTouch; 4]>,
+    /// The first finger touching the screen, until it's lifted.
+    pub(crate) primary: Option<u64>,
+    /// Whether another finger touched while the primary one did, which prevents taps and swipes.
+    multi_touch: bool,
+}
+
+impl Touches {
+    /// Starts tracking a finger, returning true if it's the primary touch.
+    pub(crate) fn start<T: BindingTypes>(
+        &mut self,
+        id: u64,
+        position: (f32, f32),
+        now: Duration,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) -> bool {
+        self.end(id);
+        self.active.push(ActiveTouch {
+            id,
+            start_position: position,
+            position,
+            started_at: now,
+        });
+        event_handler.single_write(InputEvent::TouchStarted {
+            id,
+            x: position.0,
+            y: position.1,
+        });
+        if self.primary.is_none() {
+            self.primary = Some(id);
+            self.multi_touch = false;
+            true
+        } else {
+            self.multi_touch = true;
+            false
+        }
+    }
+
+    /// Moves a finger, sending pinch and rotate gestures while two fingers touch.
+    ///
+    /// Returns true if it's the primary touch.
+    pub(crate) fn moved<T: BindingTypes>(
+        &mut self,
+        id: u64,
+        position: (f32, f32),
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) -> bool {
+        let before = self.pair();
+        let touch = match self.active.iter_mut().find(|touch| touch.id == id) {
+            Some(touch) => touch,
+            None => return false,
+        };
+        let delta = (position.0 - touch.position.0, position.1 - touch.position.1);
+        touch.position = position;
+        event_handler.single_write(InputEvent::TouchMoved {
+            id,
+            delta_x: delta.0,
+            delta_y: delta.1,
+        });
+
+        if let (Some(before), Some(after)) = (before, self.pair()) {
+            let distance_before = length(before);
+            let distance_after = length(after);
+            if distance_before > 0.0 && distance_after != distance_before {
+                event_handler.single_write(InputEvent::TouchPinched {
+                    scale: distance_after / distance_before,
+                });
+            }
+            let mut angle = after.1.atan2(after.0) - before.1.atan2(before.0);
+            if angle > std::f32::consts::PI {
+                angle -= 2.0 * std::f32::consts::PI;
+            } else if angle < -std::f32::consts::PI {
+                angle += 2.0 * std::f32::consts::PI;
+            }
+            if angle != 0.0 {
+                event_handler.single_write(InputEvent::TouchRotated { angle });
+            }
+        }
+        self.primary == Some(id)
+    }
+
+    /// Lifts a finger, sending a tap or swipe gesture if it performed one.
+    ///
+    /// Returns true if it was the primary touch.
+    pub(crate) fn lifted<T: BindingTypes>(
+        &mut self,
+        id: u64,
+        now: Duration,
+        settings: &TouchSettings,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) -> bool {
+        let touch = match self.end(id) {
+            Some(touch) => touch,
+            None => return false,
+        };
+        event_handler.single_write(InputEvent::TouchEnded { id });
+
+        let primary = self.primary == Some(id);
+        if primary && !self.multi_touch {
+            let duration = elapsed(touch.started_at, now);
+            let delta = (
+                touch.position.0 - touch.start_position.0,
+                touch.position.1 - touch.start_position.1,
+            );
+            let distance = length(delta);
+            if duration <= settings.tap_max_duration && distance <= settings.tap_max_distance {
+                event_handler.single_write(InputEvent::TouchTapped {
+                    x: touch.position.0,
+                    y: touch.position.1,
+                });
+            } else if duration <= settings.swipe_max_duration
+                && distance >= settings.swipe_min_distance
+            {
+                event_handler.single_write(InputEvent::TouchSwiped {
+                    start_x: touch.start_position.0,
+                    start_y: touch.start_position.1,
+                    delta_x: delta.0,
+                    delta_y: delta.1,
+                });
+            }
+        }
+        if self.active.is_empty() {
+            self.primary = None;
+        }
+        primary
+    }
+
+    /// Stops tracking a finger without recognizing gestures, like when the touch is cancelled.
+    ///
+    /// Returns true if it was the primary touch.
+    pub(crate) fn cancelled<T: BindingTypes>(
+        &mut self,
+        id: u64,
+        event_handler: &mut EventChannel<InputEvent<T>>,
+    ) -> bool {
+        if self.end(id).is_none() {
+            return false;
+        }
+        event_handler.single_write(InputEvent::TouchEnded { id });
+        let primary = self.primary == Some(id);
+        if self.active.is_empty() {
+            self.primary = None;
+        }
+        primary
+    }
+
+    /// Stops tracking a finger, returning it if it was touching.
+    fn end(&mut self, id: u64) -> Option<ActiveTouch> {
+        let index = self.active.iter().position(|touch| touch.id == id)?;
+        Some(self.active.remove(index))
+    }
+
+    /// Clears all touches, like when the window loses focus.
+    pub(crate) fn clear(&mut self) {
+        self.active.clear();
+        self.primary = None;
+    }
+
+    /// Returns the vector from the first to the second finger while exactly two fingers touch.
+    fn pair(&self) -> Option<(f32, f32)> {
+        match self.active[..] {
+            [ref first, ref second] => Some((
+                second.position.0 - first.position.0,
+                second.position.1 - first.position.1,
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn length((x, y): (f32, f32)) -> f32 {
+    (x * x + y * y).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StringBindings;
+
+    #[test]
+    fn gestures() {
+        let settings = TouchSettings::default();
+        let mut touches = Touches::default();
+        let mut events = EventChannel::<InputEvent<StringBindings>>::new();
+        let mut reader = events.register_reader();
+        let ms = Duration::from_millis;
+
+        assert!(touches.start(0, (10.0, 10.0), ms(0), &mut events));
+        assert!(touches.lifted(0, ms(100), &settings, &mut events));
+        assert!(touches.start(1, (10.0, 10.0), ms(200), &mut events));
+        touches.moved(1, (110.0, 10.0), &mut events);
+        touches.lifted(1, ms(400), &settings, &mut events);
+
+        touches.start(2, (0.0, 0.0), ms(500), &mut events);
+        assert!(!touches.start(3, (10.0, 0.0), ms(500), &mut events));
+        assert!(!touches.moved(3, (0.0, 20.0), &mut events));
+        touches.lifted(3, ms(600), &settings, &mut events);
+        touches.lifted(2, ms(600), &settings, &mut events);
+        assert!(touches.primary.is_none());
+
+        let gestures = events
+            .read(&mut reader)
+            .filter_map(|event| match event {
+                InputEvent::TouchTapped { .. }
+                | InputEvent::TouchSwiped { .. }
+                | InputEvent::TouchPinched { .. }
+                | InputEvent::TouchRotated { .. } => Some(event.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            gestures,
+            vec![
+                InputEvent::TouchTapped { x: 10.0, y: 10.0 },
+                InputEvent::TouchSwiped {
+                    start_x: 10.0,
+                    start_y: 10.0,
+                    delta_x: 100.0,
+                    delta_y: 0.0,
+                },
+                InputEvent::TouchPinched { scale: 2.0 },
+                InputEvent::TouchRotated {
+                    angle: std::f32::consts::FRAC_PI_2,
+                },
+            ]
+        );
+    }
+}
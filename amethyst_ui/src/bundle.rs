@@ -4,8 +4,8 @@ use crate::{
     BlinkSystem, CacheSelectionOrderSystem, FontAsset, NoCustomUi, ResizeSystemDesc,
     SelectionKeyboardSystemDesc, SelectionMouseSystemDesc, TextEditingInputSystemDesc,
     TextEditingMouseSystemDesc, ToNativeWidget, UiButtonActionRetriggerSystemDesc,
-    UiButtonSystemDesc, UiLoaderSystemDesc, UiMouseSystem, UiSoundRetriggerSystemDesc,
-    UiSoundSystemDesc, UiTransformSystemDesc, WidgetId,
+    UiButtonSystemDesc, UiCursorSystemDesc, UiLoaderSystemDesc, UiMouseSystem,
+    UiSoundRetriggerSystemDesc, UiSoundSystemDesc, UiTransformSystemDesc, WidgetId,
 };
 use amethyst_assets::Processor;
 use amethyst_core::{
@@ -94,6 +94,11 @@ where
             "ui_mouse_system",
             &["ui_transform"],
         );
+        builder.add(
+            UiCursorSystemDesc::<T>::default().build(world),
+            "ui_cursor_system",
+            &["ui_mouse_system"],
+        );
         builder.add(
             UiButtonSystemDesc::default().build(world),
             "ui_button_system",
@@ -0,0 +1,365 @@
+//! Cursors shown while hovering ui elements, including custom and animated cursor images.
+
+use std::marker::PhantomData;
+
+use amethyst_assets::Handle;
+use amethyst_core::{
+    ecs::prelude::{
+        Component, DenseVecStorage, Entities, Entity, Read, ReadExpect, ReadStorage, System,
+        SystemData, World, Write, WriteStorage,
+    },
+    shrev::{EventChannel, ReaderId},
+    Hidden, SystemDesc, Time,
+};
+use amethyst_input::{BindingTypes, InputHandler};
+use amethyst_rendy::Texture;
+use amethyst_window::{Cursor, CursorMode, MouseCursor, ScreenDimensions};
+
+use crate::{Anchor, UiEvent, UiEventType, UiImage, UiTransform};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// An image drawn in place of the system cursor, animated if it has several frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorImage {
+    /// The frames of the cursor, shown in order and looping.
+    pub frames: Vec<Handle<Texture>>,
+    /// How long each frame is shown, in seconds.
+    pub frame_time: f32,
+    /// The width of the cursor, in pixels.
+    pub width: f32,
+    /// The height of the cursor, in pixels.
+    pub height: f32,
+    /// The point of the image, from its top left corner in pixels, placed at the mouse position.
+    pub hotspot: (f32, f32),
+}
+
+impl CursorImage {
+    /// Creates a still cursor image, with its hotspot at the top left corner.
+    pub fn new(texture: Handle<Texture>, width: f32, height: f32) -> Self {
+        CursorImage::animated(vec![texture], 0.0, width, height)
+    }
+
+    /// Creates an animated cursor image, with its hotspot at the top left corner.
+    pub fn animated(
+        frames: Vec<Handle<Texture>>,
+        frame_time: f32,
+        width: f32,
+        height: f32,
+    ) -> Self {
+        CursorImage {
+            frames,
+            frame_time,
+            width,
+            height,
+            hotspot: (0.0, 0.0),
+        }
+    }
+
+    /// Sets the point of the image placed at the mouse position.
+    pub fn with_hotspot(mut self, x: f32, y: f32) -> Self {
+        self.hotspot = (x, y);
+        self
+    }
+
+    /// Returns the frame shown `time` seconds after the cursor appeared.
+    pub fn frame(&self, time: f32) -> Option<&Handle<Texture>> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let index = if self.frame_time > 0.0 {
+            (time / self.frame_time) as usize % self.frames.len()
+        } else {
+            0
+        };
+        Some(&self.frames[index])
+    }
+}
+
+/// Attach this to an `Interactable` ui element to change the cursor while the mouse hovers it.
+///
+/// The `DefaultUiCursor` resource is used while no such element is hovered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UiCursor {
+    /// The system cursor icon, used when there is no image.
+    pub icon: MouseCursor,
+    /// An image drawn in place of the system cursor.
+    pub image: Option<CursorImage>,
+}
+
+impl UiCursor {
+    /// Creates a cursor showing a system cursor icon.
+    pub fn from_icon(icon: MouseCursor) -> Self {
+        UiCursor { icon, image: None }
+    }
+
+    /// Creates a cursor drawing an image.
+    pub fn from_image(image: CursorImage) -> Self {
+        UiCursor {
+            icon: MouseCursor::Default,
+            image: Some(image),
+        }
+    }
+}
+
+impl Component for UiCursor {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Resource holding the cursor used while no element with a `UiCursor` is hovered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DefaultUiCursor(pub UiCursor);
+
+/// Builds a `UiCursorSystem`.
+#[derive(Debug)]
+pub struct UiCursorSystemDesc<T: BindingTypes> {
+    _marker: PhantomData<T>,
+}
+
+impl<T: BindingTypes> Default for UiCursorSystemDesc<T> {
+    fn default() -> Self {
+        UiCursorSystemDesc {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'b, T: BindingTypes> SystemDesc<'a, 'b, UiCursorSystem<T>> for UiCursorSystemDesc<T> {
+    fn build(self, world: &mut World) -> UiCursorSystem<T> {
+        <UiCursorSystem<T> as System<'_>>::SystemData::setup(world);
+
+        let event_reader = world.fetch_mut::<EventChannel<UiEvent>>().register_reader();
+
+        UiCursorSystem::new(event_reader)
+    }
+}
+
+/// Sets the `Cursor` resource from the `UiCursor` of the hovered element, and draws cursor images
+/// at the mouse position.
+///
+/// Cursor images are drawn by an entity with a transparent `UiTransform` created by this system,
+/// and hidden while the cursor is `CursorMode::Hidden` or `CursorMode::Locked`.
+#[derive(Debug)]
+pub struct UiCursorSystem<T: BindingTypes> {
+    event_reader: ReaderId<UiEvent>,
+    hovered: Option<Entity>,
+    /// The element whose cursor is shown, `None` for the default cursor.
+    shown: Option<Entity>,
+    /// Seconds since the shown cursor appeared, to animate its image.
+    timer: f32,
+    /// The `DefaultUiCursor` last applied to the `Cursor`.
+    default: Option<UiCursor>,
+    image_entity: Option<Entity>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: BindingTypes> UiCursorSystem<T> {
+    /// Creates a new `UiCursorSystem` reading the hovered elements from `event_reader`.
+    pub fn new(event_reader: ReaderId<UiEvent>) -> Self {
+        UiCursorSystem {
+            event_reader,
+            hovered: None,
+            shown: None,
+            timer: 0.0,
+            default: None,
+            image_entity: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: BindingTypes> System<'a> for UiCursorSystem<T> {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, EventChannel<UiEvent>>,
+        ReadStorage<'a, UiCursor>,
+        Read<'a, DefaultUiCursor>,
+        Read<'a, InputHandler<T>>,
+        ReadExpect<'a, ScreenDimensions>,
+        Read<'a, Time>,
+        Write<'a, Cursor>,
+        WriteStorage<'a, UiTransform>,
+        WriteStorage<'a, UiImage>,
+        WriteStorage<'a, Hidden>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            events,
+            ui_cursors,
+            default_cursor,
+            input,
+            screen_dimensions,
+            time,
+            mut cursor,
+            mut transforms,
+            mut images,
+            mut hiddens,
+        ): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("ui_cursor_system");
+
+        for event in events.read(&mut self.event_reader) {
+            match event.event_type {
+                UiEventType::HoverStart => self.hovered = Some(event.target),
+                UiEventType::HoverStop if self.hovered == Some(event.target) => self.hovered = None,
+                _ => {}
+            }
+        }
+
+        let shown = self.hovered.filter(|e| ui_cursors.contains(*e));
+        let mut changed = shown != self.shown;
+        if changed {
+            self.shown = shown;
+            self.timer = 0.0;
+        } else {
+            self.timer += time.delta_real_seconds();
+        }
+        if self.default.as_ref() != Some(&default_cursor.0) {
+            self.default = Some(default_cursor.0.clone());
+            changed |= shown.is_none();
+        }
+        let ui_cursor = shown
+            .and_then(|e| ui_cursors.get(e))
+            .unwrap_or(&default_cursor.0);
+
+        // The default cursor is only applied when it changes, so other systems can set the
+        // `Cursor` while no `UiCursor` is hovered.
+        if changed || shown.is_some() {
+            cursor.icon = ui_cursor.icon;
+            cursor.software_cursor = ui_cursor.image.is_some();
+        }
+
+        let visible = cursor.mode == CursorMode::Normal || cursor.mode == CursorMode::Confined;
+        let drawn = ui_cursor
+            .image
+            .as_ref()
+            .filter(|_| visible)
+            .and_then(|image| Some((image, image.frame(self.timer)?, input.mouse_position()?)));
+
+        match (drawn, self.image_entity) {
+            (Some((image, frame, (x, y))), _) => {
+                let entity = match self.image_entity.filter(|e| entities.is_alive(*e)) {
+                    Some(entity) => entity,
+                    None => {
+                        let entity = entities.create();
+                        self.image_entity = Some(entity);
+                        entity
+                    }
+                };
+                let x = x as f32 - image.hotspot.0;
+                let y = screen_dimensions.height() - y as f32 + image.hotspot.1;
+                if let Some(transform) = transforms.get_mut(entity) {
+                    transform.local_x = x;
+                    transform.local_y = y;
+                    transform.width = image.width;
+                    transform.height = image.height;
+                } else {
+                    let transform = UiTransform::new(
+                        "cursor".to_string(),
+                        Anchor::BottomLeft,
+                        Anchor::TopLeft,
+                        x,
+                        y,
+                        std::f32::MAX,
+                        image.width,
+                        image.height,
+                    )
+                    .into_transparent();
+                    transforms
+                        .insert(entity, transform)
+                        .expect("Unreachable: the cursor entity is alive");
+                }
+                images
+                    .insert(entity, UiImage::Texture(frame.clone()))
+                    .expect("Unreachable: the cursor entity is alive");
+                hiddens.remove(entity);
+            }
+            (None, Some(entity)) => {
+                if entities.is_alive(entity) && !hiddens.contains(entity) {
+                    hiddens
+                        .insert(entity, Hidden)
+                        .expect("Unreachable: the cursor entity is alive");
+                }
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use amethyst_assets::{AssetStorage, Loader};
+    use amethyst_core::ecs::{rayon::ThreadPoolBuilder, Builder, RunNow, WorldExt};
+    use amethyst_input::StringBindings;
+    use amethyst_rendy::{loaders::load_from_srgb, palette::Srgb};
+    use std::sync::Arc;
+
+    fn textures(count: usize) -> Vec<Handle<Texture>> {
+        let loader = Loader::new(".", Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let storage = AssetStorage::<Texture>::default();
+        (0..count)
+            .map(|_| {
+                loader.load_from_data(load_from_srgb(Srgb::new(1., 1., 1.)).into(), (), &storage)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cursor_image_frames() {
+        let frames = textures(3);
+        let image = CursorImage::animated(frames.clone(), 0.1, 16.0, 16.0);
+        assert_eq!(image.frame(0.0), Some(&frames[0]));
+        assert_eq!(image.frame(0.15), Some(&frames[1]));
+        assert_eq!(image.frame(0.25), Some(&frames[2]));
+        assert_eq!(image.frame(0.35), Some(&frames[0]));
+
+        let still = CursorImage::new(frames[1].clone(), 16.0, 16.0);
+        assert_eq!(still.frame(10.0), Some(&frames[1]));
+
+        let empty = CursorImage::animated(Vec::new(), 0.1, 16.0, 16.0);
+        assert_eq!(empty.frame(0.0), None);
+    }
+
+    #[test]
+    fn default_cursor_applied_on_change() {
+        let mut world = World::new();
+        world.insert(ScreenDimensions::new(100, 100, 1.0));
+        <UiCursorSystem<StringBindings> as System<'_>>::SystemData::setup(&mut world);
+        let reader = world.fetch_mut::<EventChannel<UiEvent>>().register_reader();
+        let mut system = UiCursorSystem::<StringBindings>::new(reader);
+        let element = world
+            .create_entity()
+            .with(UiCursor::from_icon(MouseCursor::Hand))
+            .build();
+
+        system.run_now(&world);
+        assert_eq!(world.read_resource::<Cursor>().icon, MouseCursor::Default);
+
+        world.write_resource::<Cursor>().icon = MouseCursor::Wait;
+        system.run_now(&world);
+        assert_eq!(world.read_resource::<Cursor>().icon, MouseCursor::Wait);
+
+        world
+            .write_resource::<EventChannel<UiEvent>>()
+            .single_write(UiEvent::new(UiEventType::HoverStart, element));
+        system.run_now(&world);
+        assert_eq!(world.read_resource::<Cursor>().icon, MouseCursor::Hand);
+
+        world
+            .write_resource::<EventChannel<UiEvent>>()
+            .single_write(UiEvent::new(UiEventType::HoverStop, element));
+        system.run_now(&world);
+        assert_eq!(world.read_resource::<Cursor>().icon, MouseCursor::Default);
+
+        world.insert(DefaultUiCursor(UiCursor::from_icon(MouseCursor::Crosshair)));
+        system.run_now(&world);
+        assert_eq!(world.read_resource::<Cursor>().icon, MouseCursor::Crosshair);
+    }
+}
@@ -17,6 +17,7 @@ pub use self::{
         UiButtonActionRetriggerSystemDesc, UiButtonActionType, UiButtonBuilder,
         UiButtonBuilderResources, UiButtonSystem, UiButtonSystemDesc,
     },
+    cursor::{CursorImage, DefaultUiCursor, UiCursor, UiCursorSystem, UiCursorSystemDesc},
     event::{targeted, Interactable, UiEvent, UiEventType, UiMouseSystem},
     event_retrigger::{EventReceiver, EventRetriggerSystem, EventRetriggerSystemDesc},
     font::{
@@ -55,6 +56,7 @@ pub(crate) use paste;
 mod blink;
 mod bundle;
 mod button;
+mod cursor;
 mod event;
 mod event_retrigger;
 mod font;
@@ -1,5 +1,5 @@
 use amethyst_assets::AssetStorage;
-use amethyst_audio::{output::Output, Source, SourceHandle};
+use amethyst_audio::{output::Output, Mixer, Source, SourceHandle};
 use amethyst_core::{
     ecs::{
         prelude::{Component, DenseVecStorage},
@@ -87,7 +87,7 @@ impl<'a, 'b> SystemDesc<'a, 'b, UiSoundSystem> for UiSoundSystemDesc {
 }
 
 /// Handles any dispatches `UiPlaySoundAction`s and plays the received
-/// sounds through the set `Output`.
+/// sounds through the set `Output`, on the `ui` bus of the `Mixer` if there is one.
 #[derive(Debug)]
 pub struct UiSoundSystem {
     event_reader: ReaderId<UiPlaySoundAction>,
@@ -107,9 +107,10 @@ impl<'s> System<'s> for UiSoundSystem {
         Write<'s, EventChannel<UiPlaySoundAction>>,
         Read<'s, AssetStorage<Source>>,
         Option<Read<'s, Output>>,
+        Option<Write<'s, Mixer>>,
     );
 
-    fn run(&mut self, (sound_events, audio_storage, audio_output): Self::SystemData) {
+    fn run(&mut self, (sound_events, audio_storage, audio_output, mut mixer): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("ui_sound_system");
 
@@ -118,7 +119,10 @@ impl<'s> System<'s> for UiSoundSystem {
         for event in sound_events.read(event_reader) {
             if let Some(output) = audio_output.as_ref() {
                 if let Some(sound) = audio_storage.get(&event.0) {
-                    output.play_once(sound, 1.0);
+                    match mixer {
+                        Some(ref mut mixer) => mixer.play_once(output, sound, 1.0, Mixer::UI),
+                        None => output.play_once(sound, 1.0),
+                    }
                 }
             }
         }
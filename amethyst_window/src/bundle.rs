@@ -1,6 +1,6 @@
-use crate::{DisplayConfig, EventsLoopSystem, WindowSystem};
+use crate::{CursorSystemDesc, DisplayConfig, EventsLoopSystem, WindowSystem};
 use amethyst_config::Config;
-use amethyst_core::{bundle::SystemBundle, ecs::World, shred::DispatcherBuilder};
+use amethyst_core::{bundle::SystemBundle, ecs::World, shred::DispatcherBuilder, SystemDesc};
 use amethyst_error::Error;
 use winit::EventsLoop;
 
@@ -13,6 +13,8 @@ pub const SCREEN_HEIGHT: u32 = 600;
 
 /// Bundle providing easy initializing of the appopriate `Window`, `WindowSystem` `EventLoop` and
 /// `EventLoopSystem` constructs used for creating the rendering window of amethyst with `winit`
+///
+/// Also adds the `CursorSystem`, controlling the mouse cursor through the `Cursor` resource.
 #[derive(Debug)]
 pub struct WindowBundle {
     config: DisplayConfig,
@@ -59,6 +61,11 @@ impl<'a, 'b> SystemBundle<'a, 'b> for WindowBundle {
             "window",
             &[],
         );
+        builder.add(
+            CursorSystemDesc::default().build(world),
+            "cursor",
+            &["window"],
+        );
         builder.add_thread_local(EventsLoopSystem::new(event_loop));
         Ok(())
     }
@@ -0,0 +1,207 @@
+use amethyst_core::{
+    ecs::{Read, ReadExpect, System, SystemData, World},
+    shrev::{EventChannel, ReaderId},
+    SystemDesc,
+};
+use serde::{Deserialize, Serialize};
+use winit::{Event, MouseCursor, Window, WindowEvent};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// How the mouse cursor behaves over the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CursorMode {
+    /// The cursor is visible and moves freely.
+    #[default]
+    Normal,
+    /// The cursor is invisible over the window, but moves freely.
+    Hidden,
+    /// The cursor is visible but can't leave the window.
+    Confined,
+    /// The cursor is invisible and kept at the center of the window.
+    ///
+    /// Read the relative motion of the mouse from the `InputEvent::MouseMoved` events, the
+    /// position of the cursor no longer changes.
+    Locked,
+}
+
+/// World resource controlling the mouse cursor, applied to the window by the `CursorSystem`.
+///
+/// The cursor is released while the window isn't focused, and captured again when it regains
+/// focus.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Cursor {
+    /// How the cursor behaves over the window.
+    pub mode: CursorMode,
+    /// The system cursor icon shown over the window.
+    pub icon: MouseCursor,
+    /// Hides the system cursor without changing the mode, while the game draws its own cursor
+    /// image at the position of the mouse, like the `UiCursorSystem` of `amethyst_ui` does.
+    pub software_cursor: bool,
+}
+
+impl Cursor {
+    /// Returns true if the system cursor is currently invisible.
+    pub fn is_hidden(&self) -> bool {
+        self.software_cursor || self.mode == CursorMode::Hidden || self.mode == CursorMode::Locked
+    }
+
+    /// Returns true if the cursor can't leave the window.
+    pub fn is_grabbed(&self) -> bool {
+        self.mode == CursorMode::Confined || self.mode == CursorMode::Locked
+    }
+}
+
+/// Builds a `CursorSystem`.
+#[derive(Default, Debug)]
+pub struct CursorSystemDesc;
+
+impl<'a, 'b> SystemDesc<'a, 'b, CursorSystem> for CursorSystemDesc {
+    fn build(self, world: &mut World) -> CursorSystem {
+        <CursorSystem as System<'_>>::SystemData::setup(world);
+
+        let event_reader = world.fetch_mut::<EventChannel<Event>>().register_reader();
+
+        CursorSystem::new(event_reader)
+    }
+}
+
+/// System applying the `Cursor` resource to the window.
+#[derive(Debug)]
+pub struct CursorSystem {
+    event_reader: ReaderId<Event>,
+    focused: bool,
+    /// The state last applied to the window, as `(hidden, grabbed, icon)`.
+    applied: Option<(bool, bool, MouseCursor)>,
+}
+
+impl CursorSystem {
+    /// Creates a new `CursorSystem` reading the focus of the window from `event_reader`.
+    pub fn new(event_reader: ReaderId<Event>) -> Self {
+        CursorSystem {
+            event_reader,
+            focused: true,
+            applied: None,
+        }
+    }
+
+    /// Reads the focus changes of the window, and returns the `(hidden, grabbed, icon)` state to
+    /// apply to the window if it changed.
+    fn update(
+        &mut self,
+        cursor: &Cursor,
+        events: &EventChannel<Event>,
+    ) -> Option<(bool, bool, MouseCursor)> {
+        for event in events.read(&mut self.event_reader) {
+            if let Event::WindowEvent {
+                event: WindowEvent::Focused(focused),
+                ..
+            } = *event
+            {
+                self.focused = focused;
+            }
+        }
+
+        let state = (
+            cursor.is_hidden() && self.focused,
+            cursor.is_grabbed() && self.focused,
+            cursor.icon,
+        );
+        if self.applied == Some(state) {
+            None
+        } else {
+            self.applied = Some(state);
+            Some(state)
+        }
+    }
+}
+
+impl<'a> System<'a> for CursorSystem {
+    type SystemData = (
+        ReadExpect<'a, Window>,
+        Read<'a, Cursor>,
+        Read<'a, EventChannel<Event>>,
+    );
+
+    fn run(&mut self, (window, cursor, events): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("cursor_system");
+
+        if let Some((hidden, grabbed, icon)) = self.update(&cursor, &events) {
+            if let Err(err) = window.grab_cursor(grabbed) {
+                log::error!("Unable to grab or release the cursor. Error: {:?}", err);
+            }
+            window.hide_cursor(hidden);
+            window.set_cursor(icon);
+        }
+
+        if cursor.mode == CursorMode::Locked && self.focused {
+            if let Some(size) = window.get_inner_size() {
+                let center = (size.width / 2.0, size.height / 2.0);
+                if let Err(err) = window.set_cursor_position(center.into()) {
+                    log::error!("Unable to move the cursor. Error: {:?}", err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use winit::WindowId;
+
+    fn focus_event(focused: bool) -> Event {
+        Event::WindowEvent {
+            // Safe because the id is only compared, never used to reach a window.
+            window_id: unsafe { WindowId::dummy() },
+            event: WindowEvent::Focused(focused),
+        }
+    }
+
+    #[test]
+    fn cursor_modes() {
+        let mut cursor = Cursor::default();
+        assert!(!cursor.is_hidden() && !cursor.is_grabbed());
+        cursor.mode = CursorMode::Hidden;
+        assert!(cursor.is_hidden() && !cursor.is_grabbed());
+        cursor.mode = CursorMode::Confined;
+        assert!(!cursor.is_hidden() && cursor.is_grabbed());
+        cursor.mode = CursorMode::Locked;
+        assert!(cursor.is_hidden() && cursor.is_grabbed());
+        cursor.mode = CursorMode::Normal;
+        cursor.software_cursor = true;
+        assert!(cursor.is_hidden() && !cursor.is_grabbed());
+    }
+
+    #[test]
+    fn cursor_released_without_focus() {
+        let mut events = EventChannel::<Event>::new();
+        let mut system = CursorSystem::new(events.register_reader());
+        let cursor = Cursor {
+            mode: CursorMode::Locked,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            system.update(&cursor, &events),
+            Some((true, true, MouseCursor::Default))
+        );
+        assert_eq!(system.update(&cursor, &events), None);
+
+        events.single_write(focus_event(false));
+        assert_eq!(
+            system.update(&cursor, &events),
+            Some((false, false, MouseCursor::Default))
+        );
+        assert_eq!(system.update(&cursor, &events), None);
+
+        events.single_write(focus_event(true));
+        assert_eq!(
+            system.update(&cursor, &events),
+            Some((true, true, MouseCursor::Default))
+        );
+    }
+}
@@ -12,6 +12,7 @@
 
 mod bundle;
 mod config;
+mod cursor;
 mod monitor;
 mod resources;
 mod system;
@@ -21,8 +22,9 @@ pub use crate::bundle::{SCREEN_HEIGHT, SCREEN_WIDTH};
 pub use crate::{
     bundle::WindowBundle,
     config::DisplayConfig,
+    cursor::{Cursor, CursorMode, CursorSystem, CursorSystemDesc},
     monitor::{MonitorIdent, MonitorsAccess},
     resources::ScreenDimensions,
     system::{EventsLoopSystem, WindowSystem},
 };
-pub use winit::{Icon, Window};
+pub use winit::{Icon, MouseCursor, Window};